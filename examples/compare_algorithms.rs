@@ -0,0 +1,50 @@
+//! Time hashing with SHA-256 and BLAKE3 for a few parameter sets
+//!
+//! Both algorithms run the same fill (the default `HashParams::new`
+//! options); only the hash function of each step changes. Each cell is the
+//! fastest of `RUNS` hashes. The first line records the core count, which
+//! bounds how many threads run at once. Build in release mode, debug
+//! timings are meaningless:
+//!
+//! ```bash
+//! cargo run --release --features blake3 --example compare_algorithms
+//! ```
+
+use sinkproof::{hash_password_with, HashAlgo, HashParams};
+use std::time::{Duration, Instant};
+
+/// Runs per measurement; the fastest is reported
+const RUNS: usize = 5;
+
+fn fastest(params: &HashParams) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            hash_password_with("benchmark password", params).expect("hashing failed");
+            start.elapsed()
+        })
+        .min()
+        .expect("at least one run")
+}
+
+fn main() {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    println!("{} cores, fastest of {} runs, variant {:?}", cores, RUNS, HashParams::new(1, 1).options.variant);
+    println!();
+    println!("| Hilos | Memoria (MB) | SHA-256 (ms) | BLAKE3 (ms) |");
+    println!("|-------|--------------|--------------|-------------|");
+
+    for (threads, memory_mb) in [(2, 10), (4, 25), (8, 50)] {
+        let params = HashParams::new(threads, memory_mb);
+        let sha256 = fastest(&params.clone().algorithm(HashAlgo::Sha256));
+        let blake3 = fastest(&params.algorithm(HashAlgo::Blake3));
+
+        println!(
+            "| {:<5} | {:<12} | {:<12} | {:<11} |",
+            threads,
+            memory_mb,
+            sha256.as_millis(),
+            blake3.as_millis()
+        );
+    }
+}
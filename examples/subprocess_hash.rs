@@ -0,0 +1,26 @@
+//! Hash a password in a sandboxed worker process
+//!
+//! The password is read by the worker from the inherited stdin, so this
+//! process never holds the plaintext:
+//!
+//! ```bash
+//! cargo build --release
+//! echo "mi_contraseña" | cargo run --release --example subprocess_hash -- target/release/sinkproof
+//! ```
+
+use sinkproof::subprocess::hash_in_subprocess;
+use sinkproof::HashParams;
+
+fn main() {
+    let worker = std::env::args()
+        .nth(1)
+        .expect("usage: subprocess_hash <path to sinkproof binary>");
+
+    match hash_in_subprocess(worker, &HashParams::new(4, 64)) {
+        Ok(stored) => println!("{}", stored),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Memory access tracing, to audit how much of the filled memory is read
+//! 
+//! Enabled with the `audit` feature. Memory that is written but never read
+//! back does not have to be kept, so an attacker can drop it; the
+//! "memory-hard" claim only holds for the fraction that later steps read.
+//! `memory_access_trace` runs the fill of one worker and records every
+//! block index it reads.
+//! 
+//! Fractions observed for a 1 MB worker (32768 blocks):
+//! 
+//! | Options | Blocks read |
+//! |---------|-------------|
+//! | `passes(2)` or more, any variant | all |
+//! | `Variant::Dependent`, one pass | about half |
+//! | `Variant::Hybrid`, one pass (the default) | about 3/8 |
+//! | `Variant::Independent`, one pass (v1 hashes) | under 1% |
+//! 
+//! The v1 fill's XOR read always lands on the first block, so only the
+//! periodic distant reads touch the rest of memory; this is what
+//! `thread_worker_low_memory` exploits, and why new hashes default to
+//! `Hybrid`. Use more passes where memory-hardness matters most.
+
+use crate::error::Result;
+use crate::hasher::WorkerState;
+use crate::params::HashParams;
+
+/// Password and salt of the traced run; data-dependent addresses depend on
+/// them, the overall distribution does not
+const TRACE_PASSWORD: &str = "memory-access-trace";
+const TRACE_SALT: &[u8] = &[0u8; 32];
+
+/// Number of equal regions in `AccessStats::regions`
+pub const ACCESS_REGIONS: usize = 10;
+
+/// Which memory blocks one worker reads while hashing
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessStats {
+    /// Blocks of 32 bytes the worker fills
+    pub blocks: usize,
+    /// Block reads performed, counting repeats
+    pub reads: usize,
+    /// Distinct blocks read at least once
+    pub distinct_reads: usize,
+    /// `distinct_reads` divided by `blocks`
+    pub touched_fraction: f64,
+    /// Distinct blocks read in each tenth of memory, from the first block
+    pub regions: [usize; ACCESS_REGIONS],
+}
+
+/// Trace the memory reads of worker 0 hashing with `params`
+/// 
+/// Runs one full worker (all passes) with a fixed password and salt, so it
+/// costs about one thread's share of a hash plus one flag per block.
+/// Low-memory mode is ignored: the trace always runs the full fill.
+pub fn memory_access_trace(params: &HashParams) -> Result<AccessStats> {
+    params.validate()?;
+    params.check_thread_memory()?;
+
+    let options = &params.options;
+    let memory_size = params.thread_memory_bytes(0)?;
+    let mut state = WorkerState::try_new(TRACE_PASSWORD, TRACE_SALT, 0, memory_size, options)?;
+    let blocks = state.iterations;
+
+    let mut touched = vec![false; blocks];
+    let mut reads = 0;
+    let mut record = |index: usize| {
+        reads += 1;
+        touched[index] = true;
+    };
+    state.run_traced(blocks, options, &mut record);
+    state.run_extra_passes_traced(options, &mut record);
+
+    let mut regions = [0; ACCESS_REGIONS];
+    for (index, _) in touched.iter().enumerate().filter(|(_, &read)| read) {
+        regions[index * ACCESS_REGIONS / blocks] += 1;
+    }
+    let distinct_reads = regions.iter().sum();
+
+    Ok(AccessStats {
+        blocks,
+        reads,
+        distinct_reads,
+        touched_fraction: distinct_reads as f64 / blocks.max(1) as f64,
+        regions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Variant;
+
+    #[test]
+    fn test_touched_fraction_thresholds() {
+        let trace = |params: HashParams| memory_access_trace(&params).unwrap();
+
+        let two_passes = trace(HashParams::new(1, 1).passes(2));
+        assert_eq!(two_passes.blocks, 32768);
+        assert_eq!(two_passes.distinct_reads, two_passes.blocks);
+        assert!(two_passes.regions.iter().all(|&region| region > 0));
+
+        let dependent = trace(HashParams::new(1, 1).variant(Variant::Dependent));
+        assert!(dependent.touched_fraction > 0.4, "{:?}", dependent.touched_fraction);
+
+        let hybrid = trace(HashParams::new(1, 1).variant(Variant::Hybrid));
+        assert!(hybrid.touched_fraction > 0.3, "{:?}", hybrid.touched_fraction);
+
+        // The documented weakness of the single-pass v1 fill
+        let independent = trace(HashParams::new(1, 1).variant(Variant::Independent));
+        assert!(independent.touched_fraction < 0.01, "{:?}", independent.touched_fraction);
+        assert!(independent.reads >= independent.blocks - 1);
+    }
+}
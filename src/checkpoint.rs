@@ -0,0 +1,251 @@
+use crate::error::{Result, SinkproofError};
+use crate::hasher::{block_count, derive_key_with, generate_salt, pepper_key, run_parallel, thread_indices, WorkerState};
+use crate::params::HashParams;
+use crate::reader::ByteReader;
+use crate::storage::SinkproofHash;
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Leading bytes of a serialized checkpoint
+const CHECKPOINT_MAGIC: &[u8] = b"SPCK";
+
+/// Saved progress of a hash that was stopped part-way through
+///
+/// Each worker's chaining value, iteration index and filled memory are kept,
+/// so resuming reproduces exactly what an uninterrupted run would compute.
+/// A checkpoint is roughly `threads * memory_mb` MB in size and is as
+/// sensitive as the derived key: anyone holding it can finish the hash.
+/// `Debug` therefore prints only the progress, never the salt or memory.
+#[derive(Clone, PartialEq, Eq)]
+pub struct HashCheckpoint {
+    pub salt: Vec<u8>,
+    pub workers: Vec<WorkerState>,
+}
+
+impl HashCheckpoint {
+    /// Whether every worker has finished filling its memory
+    pub fn is_complete(&self) -> bool {
+        self.workers.iter().all(WorkerState::is_complete)
+    }
+
+    /// Serialize the checkpoint to bytes
+    ///
+    /// Layout (integers little-endian):
+    /// `SPCK | salt_len u32 | salt | workers u32 | per worker: iteration u64,
+    /// iterations u64, current_hash (32 bytes), iteration * 32 memory bytes`.
+    /// The buffer is wiped when dropped.
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut bytes = Zeroizing::new(Vec::new());
+        bytes.extend_from_slice(CHECKPOINT_MAGIC);
+        bytes.extend_from_slice(&(self.salt.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&(self.workers.len() as u32).to_le_bytes());
+
+        for worker in &self.workers {
+            bytes.extend_from_slice(&(worker.iteration as u64).to_le_bytes());
+            bytes.extend_from_slice(&(worker.iterations as u64).to_le_bytes());
+            bytes.extend_from_slice(&worker.current_hash);
+            for block in &worker.memory {
+                bytes.extend_from_slice(block);
+            }
+        }
+
+        bytes
+    }
+
+    /// Parse a checkpoint produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(bytes, "checkpoint");
+
+        if reader.take(CHECKPOINT_MAGIC.len())? != CHECKPOINT_MAGIC {
+            return Err(invalid("missing checkpoint header"));
+        }
+
+        let salt = reader.read_prefixed()?;
+
+        let worker_count = reader.read_u32()? as usize;
+        let mut workers = Vec::new();
+        for _ in 0..worker_count {
+            let iteration = usize::try_from(reader.read_u64()?)
+                .map_err(|_| invalid("iteration out of range"))?;
+            let iterations = usize::try_from(reader.read_u64()?)
+                .map_err(|_| invalid("iterations out of range"))?;
+            if iteration > iterations {
+                return Err(invalid("iteration past the end of the fill"));
+            }
+
+            let current_hash = reader.take_vec(32)?;
+
+            // Check the claimed length against the buffer before allocating
+            let memory_len = iteration
+                .checked_mul(32)
+                .filter(|len| *len <= reader.remaining())
+                .ok_or_else(|| invalid("truncated memory"))?;
+            let memory = reader
+                .take(memory_len)?
+                .chunks(32)
+                .map(<[u8]>::to_vec)
+                .collect();
+
+            workers.push(WorkerState {
+                iteration,
+                iterations,
+                current_hash,
+                memory,
+            });
+        }
+
+        reader.finish()?;
+
+        Ok(HashCheckpoint { salt, workers })
+    }
+}
+
+impl fmt::Debug for HashCheckpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let progress: Vec<_> = self.workers.iter().map(|worker| (worker.iteration, worker.iterations)).collect();
+        f.debug_struct("HashCheckpoint")
+            .field("progress", &progress)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Start hashing a password, pausing every worker after `iterations` iterations
+///
+/// Passing an iteration count at or beyond the end of the fill produces a
+/// complete checkpoint. Checkpoints cover the initial fill; further passes
+/// (see `HashParams::passes`) run when resuming.
+pub fn hash_checkpoint(password: &str, params: &HashParams, iterations: usize) -> Result<HashCheckpoint> {
+    params.validate()?;
+
+    let salt = generate_salt();
+    let password = params.options.prepare_password(password);
+
+    let workers = run_parallel(thread_indices(params.threads)?, params.thread_stack_size, |thread_index| {
+        let memory_size = params.thread_memory_bytes(thread_index)?;
+        let mut state = WorkerState::try_new(&password, &salt, thread_index, memory_size, &params.options)?;
+        state.run(iterations, &params.options);
+        Ok(state)
+    })?
+    .into_iter()
+    .collect::<Result<Vec<_>>>()?;
+
+    Ok(HashCheckpoint { salt, workers })
+}
+
+/// Finish a checkpointed hash with the parameters it was started with
+///
+/// The result is identical to hashing the same password and salt without
+/// interruption.
+pub fn resume_hash(checkpoint: HashCheckpoint, params: &HashParams) -> Result<SinkproofHash> {
+    let (salt, key) = resume_key(checkpoint, params)?;
+    SinkproofHash::create(params, salt, &key)
+}
+
+/// Run the remaining iterations and derive the key
+fn resume_key(checkpoint: HashCheckpoint, params: &HashParams) -> Result<(Vec<u8>, Zeroizing<Vec<u8>>)> {
+    params.validate()?;
+
+    let consistent = checkpoint.workers.len() == params.threads
+        && checkpoint.workers.iter().enumerate().all(|(thread_index, worker)| {
+            params.thread_memory_bytes(thread_index).is_ok_and(|bytes| worker.iterations == block_count(bytes))
+                && worker.memory.len() == worker.iteration
+                && worker.current_hash.len() == 32
+        });
+    if !consistent {
+        return Err(invalid("checkpoint does not match the hash parameters"));
+    }
+
+    let thread_outputs = run_parallel(checkpoint.workers, params.thread_stack_size, |mut state| {
+        state.finish(&params.options)
+    })?;
+
+    let key = pepper_key(derive_key_with(&thread_outputs, &params.options), params)?;
+    Ok((checkpoint.salt, key))
+}
+
+fn invalid(reason: &str) -> SinkproofError {
+    SinkproofError::InvalidFormat(format!("Invalid checkpoint: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::compute_key;
+    use crate::pepper::{verify_password_peppered, PepperSource};
+    use crate::verifier::verify_password;
+
+    #[test]
+    fn test_resume_matches_uninterrupted_run() {
+        for params in [HashParams::new(2, 1), HashParams::new(2, 1).passes(2)] {
+            let checkpoint = hash_checkpoint("password", &params, 10_000).expect("Failed to checkpoint");
+            assert!(!checkpoint.is_complete());
+
+            let salt = checkpoint.salt.clone();
+            let (_, resumed_key) = resume_key(checkpoint, &params).expect("Failed to resume");
+            let straight_key = compute_key("password", &salt, &params).expect("Failed to hash");
+
+            assert_eq!(resumed_key, straight_key);
+        }
+    }
+
+    #[test]
+    fn test_resumed_hash_verifies() {
+        let params = HashParams::new(1, 1);
+        let checkpoint = hash_checkpoint("password", &params, 5_000).expect("Failed to checkpoint");
+
+        // Debug shows the progress but none of the secret state
+        let debug = format!("{:?}", checkpoint);
+        assert!(debug.contains("5000"), "{}", debug);
+        assert!(!debug.contains(&format!("{:?}", checkpoint.salt)), "{}", debug);
+
+        let bytes = checkpoint.to_bytes();
+        let restored = HashCheckpoint::from_bytes(&bytes).expect("Failed to parse checkpoint");
+        assert_eq!(restored, checkpoint);
+
+        let hash = resume_hash(restored, &params).expect("Failed to resume");
+        assert!(verify_password("password", &hash.to_string()).expect("Failed to verify"));
+    }
+
+    #[test]
+    fn test_resumed_hash_applies_pepper() {
+        let path = std::env::temp_dir().join(format!("sinkproof-checkpoint-pepper-{}", std::process::id()));
+        std::fs::write(&path, b"server secret").expect("Failed to write pepper file");
+        let params = HashParams::new(1, 1).pepper_from_file(&path);
+
+        let checkpoint = hash_checkpoint("password", &params, 5_000).expect("Failed to checkpoint");
+        let salt = checkpoint.salt.clone();
+        let (_, resumed_key) = resume_key(checkpoint.clone(), &params).expect("Failed to resume");
+        assert_eq!(resumed_key, compute_key("password", &salt, &params).expect("Failed to hash"));
+
+        let stored = resume_hash(checkpoint, &params).expect("Failed to resume").to_string();
+        assert!(stored.contains("pep=1"), "{}", stored);
+        let source = PepperSource::File(path.clone());
+        assert!(verify_password_peppered("password", &stored, &source).expect("Failed to verify"));
+        assert!(!verify_password_peppered("wrong", &stored, &source).expect("Failed to verify"));
+
+        // Without a pepper source the resumed key cannot be derived
+        let checkpoint = hash_checkpoint("password", &params, 5_000).expect("Failed to checkpoint");
+        let unpeppered = HashParams { pepper: None, ..params };
+        assert!(matches!(resume_hash(checkpoint, &unpeppered), Err(SinkproofError::PepperUnavailable(_))));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_resume_rejects_mismatched_params() {
+        let checkpoint = hash_checkpoint("password", &HashParams::new(1, 1), 100).expect("Failed to checkpoint");
+
+        assert!(resume_hash(checkpoint.clone(), &HashParams::new(2, 1)).is_err());
+        assert!(resume_hash(checkpoint, &HashParams::new(1, 2)).is_err());
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_truncated_data() {
+        let checkpoint = hash_checkpoint("password", &HashParams::new(1, 1), 100).expect("Failed to checkpoint");
+        let bytes = checkpoint.to_bytes();
+
+        assert!(HashCheckpoint::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(HashCheckpoint::from_bytes(&bytes[..10]).is_err());
+        assert!(HashCheckpoint::from_bytes(b"nope").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Runtime throughput comparison across hashing configurations
+//! 
+//! A programmatic tuning aid, e.g. for an admin endpoint. It only measures
+//! the configurations it is given; it does not search for parameters.
+
+use crate::error::Result;
+use crate::hasher::hash_password_with;
+use crate::params::HashParams;
+use std::time::{Duration, Instant};
+
+/// Password hashed for every measured configuration
+const SAMPLE_PASSWORD: &str = "Sinkproof benchmark sample";
+
+/// Metric used to order comparison results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchMetric {
+    /// Fastest configuration first
+    Duration,
+    /// Smallest memory footprint first
+    Memory,
+}
+
+/// Measured cost of hashing with one configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchResult {
+    /// The configuration that was measured
+    pub params: HashParams,
+    /// Wall-clock time of one hash
+    pub duration: Duration,
+    /// Worker memory filled by one hash, in bytes (saturating at
+    /// `usize::MAX`, which 32-bit targets can reach)
+    pub memory_bytes: usize,
+}
+
+/// Hash a sample password under each configuration and compare the costs
+/// 
+/// Configurations are measured one after another, so they don't compete
+/// for cores. Results are sorted ascending by `sort_by`.
+pub fn compare_configs(configs: &[HashParams], sort_by: BenchMetric) -> Result<Vec<BenchResult>> {
+    let mut results = configs
+        .iter()
+        .map(|params| {
+            let start = Instant::now();
+            hash_password_with(SAMPLE_PASSWORD, params)?;
+
+            Ok(BenchResult {
+                params: params.clone(),
+                duration: start.elapsed(),
+                memory_bytes: params.total_memory_mb().saturating_mul(1024 * 1024),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    match sort_by {
+        BenchMetric::Duration => results.sort_by_key(|result| result.duration),
+        BenchMetric::Memory => results.sort_by_key(|result| result.memory_bytes),
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_two_configs() {
+        let configs = [HashParams::new(1, 2), HashParams::new(1, 1)];
+
+        let by_memory = compare_configs(&configs, BenchMetric::Memory).expect("Failed to compare");
+        assert_eq!(by_memory.len(), 2);
+        assert_eq!(by_memory[0].params, configs[1]);
+        assert_eq!(by_memory[0].memory_bytes, 1024 * 1024);
+        assert_eq!(by_memory[1].memory_bytes, 2 * 1024 * 1024);
+        assert!(by_memory.iter().all(|r| r.duration > Duration::ZERO && r.duration < Duration::from_secs(60)));
+
+        let by_duration = compare_configs(&configs, BenchMetric::Duration).expect("Failed to compare");
+        assert!(by_duration[0].duration <= by_duration[1].duration);
+    }
+
+    #[test]
+    fn test_invalid_config_errors() {
+        assert!(compare_configs(&[HashParams::new(0, 1)], BenchMetric::Duration).is_err());
+    }
+}
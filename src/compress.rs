@@ -0,0 +1,61 @@
+//! Optional DEFLATE compression of the verification phrase
+//! 
+//! The phrase is compressed before encryption (compressing ciphertext would
+//! gain nothing). Compressing before encrypting leaks how compressible the
+//! plaintext is through the ciphertext length; that is harmless for the
+//! fixed verification phrase, but must never be used for variable-length
+//! secret data mixed with attacker-influenced data (CRIME/BREACH-style
+//! attacks). Requires the `deflate` feature.
+
+use crate::error::{Result, SinkproofError};
+
+/// Name of the compression in the v2 storage format and in errors
+pub(crate) const NAME: &str = "deflate";
+
+/// Check that this build can compress and decompress the phrase
+pub(crate) fn ensure_available() -> Result<()> {
+    if cfg!(feature = "deflate") {
+        Ok(())
+    } else {
+        Err(SinkproofError::AlgorithmUnavailable { algorithm: NAME, feature: "deflate" })
+    }
+}
+
+/// Raw DEFLATE of `data`
+#[cfg(all(feature = "deflate", feature = "hashing"))]
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec(data, 9)
+}
+
+/// Inflate `data`, refusing output longer than `limit` bytes
+#[cfg(feature = "deflate")]
+pub(crate) fn decompress(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_with_limit(data, limit)
+        .map_err(|e| SinkproofError::DecryptionFailed(format!("Invalid compressed phrase: {}", e)))
+}
+
+#[cfg(all(not(feature = "deflate"), feature = "hashing"))]
+pub(crate) fn compress(_data: &[u8]) -> Vec<u8> {
+    unreachable!("compression availability is checked before hashing")
+}
+
+#[cfg(not(feature = "deflate"))]
+pub(crate) fn decompress(_data: &[u8], _limit: usize) -> Result<Vec<u8>> {
+    Err(SinkproofError::AlgorithmUnavailable { algorithm: NAME, feature: "deflate" })
+}
+
+#[cfg(all(test, feature = "deflate"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_and_limit() {
+        let data = b"No vendo cigarros sueltos, no vendo cigarros sueltos";
+        let compressed = compress(data);
+        assert_ne!(compressed, data);
+
+        assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+        assert!(decompress(&compressed, data.len() - 1).is_err());
+        assert!(decompress(b"\xff\xff\xff", 64).is_err());
+    }
+}
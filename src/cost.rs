@@ -0,0 +1,118 @@
+//! Rough estimate of the offline attack cost of a parameter set
+//! 
+//! The figure comes from timing one hash on the current machine, so it
+//! varies between runs and machines, and a real attacker's hardware will
+//! differ by orders of magnitude. Use it to compare parameter sets, not as
+//! a security proof.
+
+use crate::error::Result;
+use crate::hasher::compute_key;
+use crate::params::{HashParams, Variant};
+use std::time::{Duration, Instant};
+
+/// Total memory below which a guess fits comfortably in GPU memory, in MB
+const LOW_RESISTANCE_MEMORY_MB: usize = 16;
+
+/// Total memory from which GPU/ASIC parallelism is strongly limited, in MB
+const HIGH_RESISTANCE_MEMORY_MB: usize = 256;
+
+/// Qualitative resistance to massively parallel hardware
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Resistance {
+    /// Thousands of guesses fit in parallel on a single GPU
+    Low,
+    /// Memory bounds parallelism, but commodity GPUs still help
+    Moderate,
+    /// Memory per guess makes GPUs and ASICs comparatively expensive
+    High,
+}
+
+/// Estimated cost of one offline guess against a parameter set
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttackerCost {
+    /// Wall-clock time of one guess measured on this machine
+    pub time_per_guess: Duration,
+    /// Memory one guess occupies, in MB
+    pub memory_mb: usize,
+    /// `memory_mb` times `time_per_guess` in seconds: the area an attacker
+    /// pays for each guess
+    pub memory_mb_seconds: f64,
+    /// Resistance to GPU/ASIC attacks derived from memory and access pattern
+    pub resistance: Resistance,
+    /// Human-readable explanation of `resistance`
+    pub note: String,
+}
+
+/// Estimate what one brute-force guess against `params` costs an attacker
+/// 
+/// Runs one full hash with `params` as a self-benchmark, so it takes as
+/// long as hashing a password. This is a rough estimate; see the module
+/// docs.
+pub fn attacker_cost(params: &HashParams) -> Result<AttackerCost> {
+    params.validate()?;
+
+    let start = Instant::now();
+    compute_key("attacker-cost-benchmark", &[0u8; 32], params)?;
+    let time_per_guess = start.elapsed();
+
+    let memory_mb = params.total_memory_mb();
+    let (resistance, note) = resistance(params, memory_mb);
+
+    Ok(AttackerCost {
+        time_per_guess,
+        memory_mb,
+        memory_mb_seconds: memory_mb as f64 * time_per_guess.as_secs_f64(),
+        resistance,
+        note,
+    })
+}
+
+/// Classify `params` and explain the classification
+fn resistance(params: &HashParams, memory_mb: usize) -> (Resistance, String) {
+    let resistance = if memory_mb < LOW_RESISTANCE_MEMORY_MB {
+        Resistance::Low
+    } else if memory_mb < HIGH_RESISTANCE_MEMORY_MB {
+        Resistance::Moderate
+    } else {
+        Resistance::High
+    };
+
+    let mut note = match resistance {
+        Resistance::Low => format!("{} MB per guess fits many parallel guesses on one GPU", memory_mb),
+        Resistance::Moderate => format!("{} MB per guess limits GPU parallelism to hundreds of guesses", memory_mb),
+        Resistance::High => format!("{} MB per guess makes GPU and ASIC attacks memory-bound", memory_mb),
+    };
+    if params.options.variant == Variant::Independent && params.options.passes == 1 {
+        note.push_str("; data-independent single-pass fill allows time-memory tradeoffs");
+    }
+
+    (resistance, note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_more_memory_costs_more() {
+        let small = attacker_cost(&HashParams::new(1, 1).variant(Variant::Independent)).unwrap();
+        let large = attacker_cost(&HashParams::new(1, 4)).unwrap();
+
+        assert_eq!(small.memory_mb, 1);
+        assert!(large.memory_mb_seconds > small.memory_mb_seconds);
+        assert_eq!(small.resistance, Resistance::Low);
+        assert!(small.note.contains("time-memory tradeoffs"));
+    }
+
+    #[test]
+    fn test_resistance_levels() {
+        let level = |params: HashParams| resistance(&params, params.total_memory_mb());
+
+        assert_eq!(level(HashParams::new(4, 8)).0, Resistance::Moderate);
+        assert_eq!(level(HashParams::new(4, 64)).0, Resistance::High);
+
+        let (_, note) = level(HashParams::new(4, 64).variant(Variant::Dependent));
+        assert!(!note.contains("tradeoffs"));
+        assert!(attacker_cost(&HashParams::new(0, 1)).is_err());
+    }
+}
@@ -1,10 +1,186 @@
 use aes_gcm::{
-    aead::{Aead, AeadCore, KeyInit, OsRng},
-    Aes256Gcm, Nonce,
+    aead::{self, Aead, KeyInit, Payload},
+    Aes256Gcm,
 };
 use sha2::{Sha256, Digest};
+use crate::error::{Result, SinkproofError};
+use crate::rng::fill_random;
+use subtle::ConstantTimeEq;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
-const VERIFICATION_PHRASE: &str = "No vendo cigarros sueltos";
+/// Phrase encrypted by hashes that do not set
+/// `HashParams::verification_phrase`
+pub(crate) const VERIFICATION_PHRASE: &str = "No vendo cigarros sueltos";
+
+/// Length of the nonce prepended to every ciphertext (same for both ciphers)
+pub(crate) const NONCE_LEN: usize = 12;
+
+/// Length of the authentication tag appended to every ciphertext
+pub(crate) const TAG_LEN: usize = 16;
+
+/// AEAD used to encrypt the verification phrase
+/// 
+/// Both ciphers take a 32-byte key and a 12-byte nonce, so the stored
+/// layout (nonce + ciphertext + tag) is the same. The choice is recorded in
+/// v2 hashes; decrypting with the other cipher always fails authentication.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cipher {
+    /// AES-256-GCM, the original v1 cipher
+    #[default]
+    Aes256Gcm,
+    /// ChaCha20-Poly1305, faster in software on CPUs without AES
+    /// instructions; requires the `chacha20` feature and is not FIPS-approved
+    ChaCha20Poly1305,
+}
+
+impl Cipher {
+    /// Name used for this cipher in the v2 storage format
+    pub fn name(self) -> &'static str {
+        match self {
+            Cipher::Aes256Gcm => "aes256gcm",
+            Cipher::ChaCha20Poly1305 => "chacha20poly1305",
+        }
+    }
+
+    /// Look up a cipher by its storage name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "aes256gcm" => Some(Cipher::Aes256Gcm),
+            "chacha20poly1305" => Some(Cipher::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    /// Whether this machine runs the cipher with dedicated CPU instructions
+    /// 
+    /// True for AES-256-GCM when the CPU has AES and carry-less multiply
+    /// instructions (AES-NI + PCLMULQDQ on x86, the crypto extensions on
+    /// aarch64); the `aes-gcm` backend picks them up at runtime as well.
+    /// ChaCha20-Poly1305 is a pure software cipher and always reports false,
+    /// although it is fast without special instructions.
+    pub fn is_hardware_accelerated(self) -> bool {
+        match self {
+            Cipher::Aes256Gcm => aes_instructions_available(),
+            Cipher::ChaCha20Poly1305 => false,
+        }
+    }
+
+    /// Check that this build can use the cipher
+    pub fn ensure_available(self) -> Result<()> {
+        match self {
+            Cipher::Aes256Gcm => Ok(()),
+            Cipher::ChaCha20Poly1305 if cfg!(feature = "fips") => {
+                Err(SinkproofError::AlgorithmNotPermitted(self.name()))
+            }
+            Cipher::ChaCha20Poly1305 if !cfg!(feature = "chacha20") => {
+                Err(SinkproofError::AlgorithmUnavailable { algorithm: self.name(), feature: "chacha20" })
+            }
+            Cipher::ChaCha20Poly1305 => Ok(()),
+        }
+    }
+}
+
+/// Messages encrypted per cipher by the `fastest_cipher` benchmark
+const CIPHER_BENCH_ROUNDS: usize = 64;
+
+/// Size of each benchmark message in bytes
+const CIPHER_BENCH_MESSAGE_LEN: usize = 4096;
+
+static FASTEST_CIPHER: OnceLock<Cipher> = OnceLock::new();
+
+/// The fastest cipher available in this build on this machine
+/// 
+/// Micro-benchmarks every available cipher the first time it is called
+/// (a few milliseconds) and caches the winner for the rest of the process.
+/// Builds with a single available cipher (no `chacha20` feature, or
+/// `fips`) return AES-256-GCM without benchmarking. The cipher is recorded
+/// in each hash, so hashes verify on machines that would pick the other.
+pub fn fastest_cipher() -> Cipher {
+    *FASTEST_CIPHER.get_or_init(|| {
+        let available: Vec<Cipher> = [Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305]
+            .into_iter()
+            .filter(|cipher| cipher.ensure_available().is_ok())
+            .collect();
+        if available.len() == 1 {
+            return available[0];
+        }
+
+        available
+            .into_iter()
+            .min_by_key(|&cipher| bench_cipher(cipher))
+            .unwrap_or_default()
+    })
+}
+
+/// Time `CIPHER_BENCH_ROUNDS` encryptions of a fixed message
+fn bench_cipher(cipher: Cipher) -> Duration {
+    let key = [0x42u8; 32];
+    let message = [0u8; CIPHER_BENCH_MESSAGE_LEN];
+    let start = Instant::now();
+    for _ in 0..CIPHER_BENCH_ROUNDS {
+        std::hint::black_box(encrypt_bytes_with(cipher, &key, &message).ok());
+    }
+    start.elapsed()
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn aes_instructions_available() -> bool {
+    std::arch::is_x86_feature_detected!("aes") && std::arch::is_x86_feature_detected!("pclmulqdq")
+}
+
+#[cfg(target_arch = "aarch64")]
+fn aes_instructions_available() -> bool {
+    std::arch::is_aarch64_feature_detected!("aes") && std::arch::is_aarch64_feature_detected!("pmull")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn aes_instructions_available() -> bool {
+    false
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Calls to `constant_time_eq` on this thread, so tests can prove that
+    /// verification goes through it; per-thread so parallel tests don't
+    /// see each other's calls
+    pub(crate) static CONSTANT_TIME_COMPARISONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Compare two byte strings in time independent of their contents
+/// 
+/// Uses `subtle::ConstantTimeEq`; only the lengths, which are not secret,
+/// can end the comparison early.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    #[cfg(test)]
+    CONSTANT_TIME_COMPARISONS.with(|count| count.set(count.get() + 1));
+
+    a.ct_eq(b).into()
+}
+
+/// Whether decrypted bytes are the built-in verification phrase
+pub(crate) fn is_verification_phrase(decrypted: &[u8]) -> bool {
+    matches_phrase(decrypted, VERIFICATION_PHRASE)
+}
+
+/// Whether decrypted bytes are `phrase`, compared in constant time
+pub(crate) fn matches_phrase(decrypted: &[u8], phrase: &str) -> bool {
+    constant_time_eq(decrypted, phrase.as_bytes())
+}
+
+/// Short, non-secret identifier of a verification phrase
+/// 
+/// The first 4 bytes of SHA-256 over the phrase, in hex. Stored in v2
+/// hashes so a build with a different phrase can report the mismatch
+/// instead of a silent verification failure.
+pub fn phrase_id(phrase: &str) -> String {
+    hex::encode(&Sha256::digest(phrase.as_bytes())[..4])
+}
+
+/// Identifier of the phrase this build encrypts
+pub(crate) fn current_phrase_id() -> String {
+    phrase_id(VERIFICATION_PHRASE)
+}
 
 /// Encrypt the verification phrase using AES-256-GCM
 /// 
@@ -13,86 +189,171 @@ const VERIFICATION_PHRASE: &str = "No vendo cigarros sueltos";
 /// 
 /// # Returns
 /// Encrypted data as bytes (nonce + ciphertext + tag all combined)
-pub fn encrypt_phrase(key: &[u8]) -> Result<Vec<u8>, String> {
-    // Ensure key is exactly 32 bytes
-    let key = if key.len() > 32 {
-        &key[..32]
-    } else if key.len() < 32 {
-        // Hash the key to get exactly 32 bytes
-        let mut hasher = Sha256::new();
-        hasher.update(key);
-        return encrypt_phrase(&hasher.finalize());
-    } else {
-        key
-    };
+pub fn encrypt_phrase(key: &[u8]) -> Result<Vec<u8>> {
+    encrypt_phrase_with(key, Cipher::Aes256Gcm)
+}
+
+/// Encrypt the verification phrase with the given cipher
+pub fn encrypt_phrase_with(key: &[u8], cipher: Cipher) -> Result<Vec<u8>> {
+    encrypt_custom_phrase(key, VERIFICATION_PHRASE, cipher)
+}
+
+/// Encrypt a caller-chosen verification phrase with the given cipher
+/// 
+/// `decrypt_phrase_with` returns it; comparing the result with the same
+/// phrase is up to the caller.
+pub fn encrypt_custom_phrase(key: &[u8], phrase: &str, cipher: Cipher) -> Result<Vec<u8>> {
+    encrypt_bytes_with(cipher, key, phrase.as_bytes())
+}
+
+/// Decrypt the verification phrase
+/// 
+/// # Arguments
+/// * `key` - 32-byte encryption key
+/// * `encrypted_data` - Combined nonce + ciphertext
+/// 
+/// # Returns
+/// Decrypted phrase as String
+pub fn decrypt_phrase(key: &[u8], encrypted_data: &[u8]) -> Result<String> {
+    decrypt_phrase_with(key, encrypted_data, Cipher::Aes256Gcm)
+}
+
+/// Decrypt the verification phrase with the given cipher
+pub fn decrypt_phrase_with(key: &[u8], encrypted_data: &[u8], cipher: Cipher) -> Result<String> {
+    let plaintext = decrypt_bytes_with(cipher, key, encrypted_data)?;
+
+    String::from_utf8(plaintext).map_err(|e| SinkproofError::Utf8(e.to_string()))
+}
+
+/// Encrypt arbitrary bytes with AES-256-GCM under a fresh random nonce
+/// 
+/// Returns nonce + ciphertext + tag combined, like `encrypt_phrase`
+pub fn encrypt_bytes(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    encrypt_bytes_with(Cipher::Aes256Gcm, key, plaintext)
+}
+
+/// Decrypt data produced by `encrypt_bytes`
+/// 
+/// Unlike `decrypt_phrase` the plaintext is returned as raw bytes, so
+/// binary payloads need no UTF-8 validation.
+pub fn decrypt_bytes(key: &[u8], encrypted_data: &[u8]) -> Result<Vec<u8>> {
+    decrypt_bytes_with(Cipher::Aes256Gcm, key, encrypted_data)
+}
+
+/// Encrypt arbitrary bytes with the given cipher under a fresh random nonce
+pub fn encrypt_bytes_with(cipher: Cipher, key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    encrypt_bytes_with_aad(cipher, key, plaintext, &[])
+}
+
+/// Decrypt data produced by `encrypt_bytes_with` using the same cipher
+pub fn decrypt_bytes_with(cipher: Cipher, key: &[u8], encrypted_data: &[u8]) -> Result<Vec<u8>> {
+    decrypt_bytes_with_aad(cipher, key, encrypted_data, &[])
+}
+
+/// `encrypt_bytes_with`, authenticating `aad` along with the plaintext
+/// 
+/// Empty associated data is the same as none, so ciphertexts made without
+/// it decrypt with `aad = &[]`.
+pub(crate) fn encrypt_bytes_with_aad(cipher: Cipher, key: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    cipher.ensure_available()?;
+
+    match cipher {
+        Cipher::Aes256Gcm => seal::<Aes256Gcm>(key, plaintext, aad),
+        #[cfg(all(feature = "chacha20", not(feature = "fips")))]
+        Cipher::ChaCha20Poly1305 => seal::<chacha20poly1305::ChaCha20Poly1305>(key, plaintext, aad),
+        #[cfg(not(all(feature = "chacha20", not(feature = "fips"))))]
+        Cipher::ChaCha20Poly1305 => unreachable!("cipher availability is checked above"),
+    }
+}
+
+/// Decrypt data produced by `encrypt_bytes_with_aad` with the same cipher and `aad`
+pub(crate) fn decrypt_bytes_with_aad(cipher: Cipher, key: &[u8], encrypted_data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    cipher.ensure_available()?;
+
+    match cipher {
+        Cipher::Aes256Gcm => open::<Aes256Gcm>(key, encrypted_data, aad),
+        #[cfg(all(feature = "chacha20", not(feature = "fips")))]
+        Cipher::ChaCha20Poly1305 => open::<chacha20poly1305::ChaCha20Poly1305>(key, encrypted_data, aad),
+        #[cfg(not(all(feature = "chacha20", not(feature = "fips"))))]
+        Cipher::ChaCha20Poly1305 => unreachable!("cipher availability is checked above"),
+    }
+}
+
+/// Encrypt with AEAD `C`, returning nonce + ciphertext + tag
+fn seal<C: Aead + KeyInit>(key: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let key = normalize_key(key);
 
     // Create cipher
-    let cipher = Aes256Gcm::new_from_slice(key)
-        .map_err(|e| format!("Failed to create cipher: {}", e))?;
+    let cipher = C::new_from_slice(&key)
+        .map_err(|e| SinkproofError::EncryptionFailed(format!("Failed to create cipher: {}", e)))?;
 
-    // Generate random nonce (12 bytes for GCM)
-    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    // Generate random nonce (12 bytes for both ciphers) from the configured RNG
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    fill_random(&mut nonce_bytes);
+    let nonce = aead::Nonce::<C>::from_slice(&nonce_bytes);
 
-    // Encrypt the phrase
+    // Encrypt the plaintext
     let ciphertext = cipher
-        .encrypt(&nonce, VERIFICATION_PHRASE.as_bytes())
-        .map_err(|e| format!("Encryption failed: {}", e))?;
+        .encrypt(nonce, Payload { msg: plaintext, aad })
+        .map_err(|e| SinkproofError::EncryptionFailed(format!("Encryption failed: {}", e)))?;
 
     // Combine nonce + ciphertext
     let mut result = Vec::new();
-    result.extend_from_slice(&nonce);
+    result.extend_from_slice(nonce);
     result.extend_from_slice(&ciphertext);
 
     Ok(result)
 }
 
-/// Decrypt the verification phrase
-/// 
-/// # Arguments
-/// * `key` - 32-byte encryption key
-/// * `encrypted_data` - Combined nonce + ciphertext
-/// 
-/// # Returns
-/// Decrypted phrase as String
-pub fn decrypt_phrase(key: &[u8], encrypted_data: &[u8]) -> Result<String, String> {
-    // Ensure key is exactly 32 bytes
-    let key = if key.len() > 32 {
-        &key[..32]
-    } else if key.len() < 32 {
-        // Hash the key to get exactly 32 bytes
-        let mut hasher = Sha256::new();
-        hasher.update(key);
-        let hashed = hasher.finalize();
-        return decrypt_phrase(&hashed, encrypted_data);
-    } else {
-        key
-    };
+/// Decrypt nonce + ciphertext + tag produced by `seal` with the same AEAD
+fn open<C: Aead + KeyInit>(key: &[u8], encrypted_data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let key = normalize_key(key);
 
-    if encrypted_data.len() < 12 {
-        return Err("Encrypted data too short".to_string());
+    if encrypted_data.len() < NONCE_LEN {
+        return Err(SinkproofError::DecryptionFailed("Encrypted data too short".to_string()));
     }
 
     // Split nonce and ciphertext
-    let (nonce_bytes, ciphertext) = encrypted_data.split_at(12);
-    let nonce = Nonce::from_slice(nonce_bytes);
+    let (nonce_bytes, ciphertext) = encrypted_data.split_at(NONCE_LEN);
+    let nonce = aead::Nonce::<C>::from_slice(nonce_bytes);
 
     // Create cipher
-    let cipher = Aes256Gcm::new_from_slice(key)
-        .map_err(|e| format!("Failed to create cipher: {}", e))?;
+    let cipher = C::new_from_slice(&key)
+        .map_err(|e| SinkproofError::DecryptionFailed(format!("Failed to create cipher: {}", e)))?;
 
     // Decrypt
-    let plaintext = cipher
-        .decrypt(nonce, ciphertext)
-        .map_err(|e| format!("Decryption failed: {}", e))?;
+    cipher
+        .decrypt(nonce, Payload { msg: ciphertext, aad })
+        .map_err(|e| SinkproofError::DecryptionFailed(format!("Decryption failed: {}", e)))
+}
 
-    String::from_utf8(plaintext)
-        .map_err(|e| format!("Invalid UTF-8: {}", e))
+/// Ensure key is exactly 32 bytes
+/// 
+/// Longer keys are truncated, shorter keys are hashed with SHA-256
+fn normalize_key(key: &[u8]) -> Vec<u8> {
+    if key.len() < 32 {
+        let mut hasher = Sha256::new();
+        hasher.update(key);
+        hasher.finalize().to_vec()
+    } else {
+        key[..32].to_vec()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_fastest_cipher_is_available_and_cached() {
+        let cipher = fastest_cipher();
+        assert!(cipher.ensure_available().is_ok());
+        assert_eq!(fastest_cipher(), cipher);
+        if cfg!(any(feature = "fips", not(feature = "chacha20"))) {
+            assert_eq!(cipher, Cipher::Aes256Gcm);
+        }
+    }
+
     #[test]
     fn test_encrypt_decrypt_roundtrip() {
         let key = b"this_is_a_32_byte_key_for_aes!!";
@@ -102,6 +363,57 @@ mod tests {
         
         let decrypted = decrypt_phrase(key, &encrypted).expect("Decryption failed");
         assert_eq!(decrypted, VERIFICATION_PHRASE);
+
+        let custom = encrypt_custom_phrase(key, "Deployment canary", Cipher::Aes256Gcm).expect("Encryption failed");
+        let decrypted = decrypt_phrase(key, &custom).expect("Decryption failed");
+        assert_eq!(decrypted, "Deployment canary");
+        assert!(matches_phrase(decrypted.as_bytes(), "Deployment canary"));
+        assert!(!is_verification_phrase(decrypted.as_bytes()));
+    }
+
+    #[cfg(all(feature = "chacha20", not(feature = "fips")))]
+    #[test]
+    fn test_chacha_roundtrip_and_cipher_binding() {
+        let key = b"this_is_a_32_byte_key_for_aes!!";
+
+        let encrypted = encrypt_phrase_with(key, Cipher::ChaCha20Poly1305).expect("Encryption failed");
+        let decrypted = decrypt_phrase_with(key, &encrypted, Cipher::ChaCha20Poly1305).expect("Decryption failed");
+        assert_eq!(decrypted, VERIFICATION_PHRASE);
+
+        // The other cipher never authenticates the ciphertext
+        assert!(decrypt_phrase_with(key, &encrypted, Cipher::Aes256Gcm).is_err());
+        let aes = encrypt_phrase(key).expect("Encryption failed");
+        assert!(decrypt_phrase_with(key, &aes, Cipher::ChaCha20Poly1305).is_err());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+        assert!(is_verification_phrase(VERIFICATION_PHRASE.as_bytes()));
+    }
+
+    #[test]
+    fn test_cipher_names_roundtrip() {
+        for cipher in [Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305] {
+            assert_eq!(Cipher::from_name(cipher.name()), Some(cipher));
+        }
+        assert_eq!(Cipher::from_name("des"), None);
+    }
+
+    #[test]
+    fn test_binary_payload_roundtrip() {
+        let key = b"this_is_a_32_byte_key_for_aes!!";
+        let payload = [0xff, 0x00, 0xfe, 0x80, 0xc3];
+        assert!(String::from_utf8(payload.to_vec()).is_err());
+
+        let encrypted = encrypt_bytes(key, &payload).expect("Encryption failed");
+        assert_eq!(decrypt_bytes(key, &encrypted).expect("Decryption failed"), payload);
+
+        // The string variant still rejects non-UTF-8 plaintext
+        assert!(decrypt_phrase(key, &encrypted).is_err());
     }
 
     #[test]
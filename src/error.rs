@@ -0,0 +1,192 @@
+use std::fmt;
+
+/// Errors returned by Sinkproof operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkproofError {
+    /// The requested number of threads was zero
+    InvalidThreads,
+    /// The requested or stored thread count is above this build's
+    /// `MAX_THREADS`
+    ThreadLimitExceeded { requested: usize, max: usize },
+    /// The requested memory size was zero
+    InvalidMemory,
+    /// The requested or stored per-thread memory is above this build's
+    /// `MAX_MEMORY_MB`
+    MemoryLimitExceeded { requested: usize, max: usize },
+    /// The requested or stored total memory (threads times the largest
+    /// thread memory) is above this build's `MAX_TOTAL_MEMORY_MB` or a
+    /// `ParseLimits` ceiling
+    TotalMemoryLimitExceeded { requested: usize, max: usize },
+    /// The requested number of passes was zero
+    InvalidPasses,
+    /// The requested or stored pass count is above this build's
+    /// `MAX_PASSES`
+    PassLimitExceeded { requested: u32, max: u32 },
+    /// A stored hash string could not be parsed
+    InvalidFormat(String),
+    /// The salt or phrase field of a stored hash is not valid base64
+    Base64Decode(String),
+    /// Decrypted bytes that must be text are not valid UTF-8
+    Utf8(String),
+    /// Parameters or arguments that no more specific variant covers:
+    /// unknown option values or memory sizes in a configuration file or
+    /// the environment, low-memory mode without the v1 variant and a single
+    /// pass, an invalid worker output length, an empty verification phrase,
+    /// an invalid namespace, an empty or oversized thread range, or an
+    /// out-of-range derived key length
+    InvalidConfig(String),
+    /// Encrypting the verification phrase failed
+    EncryptionFailed(String),
+    /// Decrypting the verification phrase failed (usually a wrong key)
+    DecryptionFailed(String),
+    /// A worker thread panicked while filling memory
+    ThreadPanicked,
+    /// The OS refused to start a worker thread (e.g. a thread ulimit)
+    ThreadSpawn(String),
+    /// A buffer sized by the parameters could not be allocated
+    OutOfMemory,
+    /// The algorithm is not allowed in this build (e.g. BLAKE3 under `fips`)
+    AlgorithmNotPermitted(&'static str),
+    /// The algorithm's cargo feature is not enabled in this build
+    AlgorithmUnavailable { algorithm: &'static str, feature: &'static str },
+    /// A v2 hash's checksum does not match its contents (storage corruption)
+    ChecksumMismatch,
+    /// A hashing subprocess could not be started or failed
+    SubprocessFailed(String),
+    /// The stored hash was created with a different verification phrase
+    PhraseMismatch,
+    /// The stored hash is empty or whitespace, e.g. the user never set a password
+    EmptyHash,
+    /// The hash needs a pepper that is not configured or cannot be loaded
+    PepperUnavailable(String),
+    /// A per-thread memory list does not have one entry per thread
+    PerThreadMemoryMismatch { threads: usize, sizes: usize },
+}
+
+/// Convenience alias for results returned by this crate
+///
+/// ```
+/// use sinkproof::Result;
+///
+/// fn login(password: &str, stored: &str) -> Result<bool> {
+///     let ok = sinkproof::verify_password(password, stored)?;
+///     Ok(ok)
+/// }
+///
+/// assert!(login("hunter2", "not a hash").is_err());
+/// ```
+pub type Result<T> = std::result::Result<T, SinkproofError>;
+
+impl fmt::Display for SinkproofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkproofError::InvalidThreads => write!(f, "Number of threads must be greater than 0"),
+            SinkproofError::ThreadLimitExceeded { requested, max } => {
+                write!(f, "Thread count {} exceeds this build's maximum of {}", requested, max)
+            }
+            SinkproofError::InvalidMemory => write!(f, "Memory size must be greater than 0"),
+            SinkproofError::MemoryLimitExceeded { requested, max } => {
+                write!(f, "Memory of {} MB per thread exceeds this build's maximum of {} MB", requested, max)
+            }
+            SinkproofError::TotalMemoryLimitExceeded { requested, max } => {
+                write!(f, "Total memory of {} MB exceeds the maximum of {} MB", requested, max)
+            }
+            SinkproofError::InvalidPasses => write!(f, "Number of passes must be greater than 0"),
+            SinkproofError::PassLimitExceeded { requested, max } => {
+                write!(f, "Pass count {} exceeds this build's maximum of {}", requested, max)
+            }
+            SinkproofError::InvalidFormat(msg)
+            | SinkproofError::Base64Decode(msg)
+            | SinkproofError::InvalidConfig(msg)
+            | SinkproofError::EncryptionFailed(msg)
+            | SinkproofError::DecryptionFailed(msg)
+            | SinkproofError::SubprocessFailed(msg) => write!(f, "{}", msg),
+            SinkproofError::Utf8(reason) => write!(f, "Invalid UTF-8: {}", reason),
+            SinkproofError::ThreadPanicked => write!(f, "Thread panicked during execution"),
+            SinkproofError::ThreadSpawn(reason) => write!(f, "Failed to spawn worker thread: {}", reason),
+            SinkproofError::OutOfMemory => write!(f, "Memory allocation failed: requested size is too large"),
+            SinkproofError::AlgorithmNotPermitted(name) => {
+                write!(f, "Algorithm '{}' is not permitted in FIPS mode", name)
+            }
+            SinkproofError::AlgorithmUnavailable { algorithm, feature } => {
+                write!(f, "Algorithm '{}' requires the '{}' feature", algorithm, feature)
+            }
+            SinkproofError::ChecksumMismatch => write!(f, "Hash checksum mismatch: stored hash is corrupt"),
+            SinkproofError::EmptyHash => write!(f, "Stored hash is empty"),
+            SinkproofError::PepperUnavailable(reason) => write!(f, "Pepper unavailable: {}", reason),
+            SinkproofError::PerThreadMemoryMismatch { threads, sizes } => write!(
+                f,
+                "Per-thread memory list has {} entries for {} threads",
+                sizes, threads
+            ),
+            SinkproofError::PhraseMismatch => {
+                write!(f, "Stored hash was created with a different verification phrase")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SinkproofError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_messages() {
+        assert_eq!(
+            SinkproofError::InvalidThreads.to_string(),
+            "Number of threads must be greater than 0"
+        );
+        assert_eq!(
+            SinkproofError::InvalidFormat("Invalid hash format: expected 6 parts, got 1".to_string()).to_string(),
+            "Invalid hash format: expected 6 parts, got 1"
+        );
+    }
+
+    #[test]
+    fn test_unavailable_algorithm_names_its_feature() {
+        use crate::encryption::Cipher;
+        use crate::params::HashAlgo;
+
+        assert_eq!(
+            SinkproofError::AlgorithmUnavailable { algorithm: "chacha20poly1305", feature: "chacha20" }.to_string(),
+            "Algorithm 'chacha20poly1305' requires the 'chacha20' feature"
+        );
+
+        // Each feature name is one Cargo.toml actually declares
+        let manifest = include_str!("../Cargo.toml");
+        let unavailable = [
+            Cipher::ChaCha20Poly1305.ensure_available(),
+            HashAlgo::Blake3.ensure_available(),
+            crate::compress::ensure_available(),
+        ];
+        for result in unavailable {
+            if let Err(SinkproofError::AlgorithmUnavailable { feature, .. }) = result {
+                assert!(manifest.contains(&format!("\n{} = [", feature)), "no '{}' feature", feature);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decoding_errors_are_distinct() {
+        use crate::encryption::{decrypt_phrase_with, encrypt_bytes_with, Cipher};
+        use crate::storage::SinkproofHash;
+
+        let err = "Sinkproof:v1:4:64:!!!!:BAUG".parse::<SinkproofHash>().unwrap_err();
+        assert!(matches!(err, SinkproofError::Base64Decode(_)), "{:?}", err);
+        assert!(err.to_string().starts_with("Invalid salt encoding"), "{}", err);
+
+        let key = [7u8; 32];
+        let not_text = encrypt_bytes_with(Cipher::Aes256Gcm, &key, &[0xff, 0xfe]).unwrap();
+        let err = decrypt_phrase_with(&key, &not_text, Cipher::Aes256Gcm).unwrap_err();
+        assert!(matches!(err, SinkproofError::Utf8(_)), "{:?}", err);
+        assert!(err.to_string().starts_with("Invalid UTF-8: "), "{}", err);
+    }
+
+    #[test]
+    fn test_is_std_error() {
+        let err: Box<dyn std::error::Error> = Box::new(SinkproofError::ThreadPanicked);
+        assert_eq!(err.to_string(), "Thread panicked during execution");
+    }
+}
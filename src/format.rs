@@ -0,0 +1,521 @@
+//! Pluggable serializers for stored hashes
+//! 
+//! `SinkproofHash`'s `Display` and `FromStr` use `ColonFormat`; the other
+//! formats carry the same fields, so a hash converts between them without
+//! the password.
+
+use base64::{Engine as _, engine::general_purpose};
+use crate::error::{Result, SinkproofError};
+use crate::storage::{
+    checksum, decode_standard, encode_options, parse_options, split_namespace, unwrap_double_encoded, OptionsField,
+    HashVersion, SinkproofHash, BINARY_MAGIC,
+};
+use serde_json::{json, Value};
+
+/// A textual representation of `SinkproofHash`
+/// 
+/// Implement this to plug in a custom storage format; every method that
+/// accepts `&dyn HashSerializer` then works with it.
+pub trait HashSerializer {
+    /// Encode a hash
+    fn serialize(&self, hash: &SinkproofHash) -> String;
+
+    /// Decode a hash produced by `serialize`
+    fn deserialize(&self, input: &str) -> Result<SinkproofHash>;
+}
+
+/// The native `Sinkproof:v1:threads:memory:salt:phrase` format
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ColonFormat;
+
+/// PHC string format, e.g. `$sinkproof$v=1$t=4,m=64$salt$phrase`
+/// 
+/// Salt and phrase use unpadded standard base64, as PHC requires. v2
+/// options follow `t` and `m` in the parameter list.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PhcFormat;
+
+/// JSON object with one member per field, base64 for binary fields
+/// 
+/// Example: `{"version":"v1","threads":4,"memory_mb":64,"salt":"...","encrypted_phrase":"..."}`.
+/// v2 hashes add an `"options"` member holding the v2 options field; it
+/// is required for v2 and rejected for v1.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFormat;
+
+/// Representation of a stored hash, as recognized by `detect_format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatVersion {
+    /// `Sinkproof:v1:...`, possibly with a `namespace|` prefix
+    ColonV1,
+    /// `Sinkproof:v2:...`, possibly with a `namespace|` prefix
+    ColonV2,
+    /// `$sinkproof$...` (`PhcFormat`)
+    Phc,
+    /// A JSON object (`JsonFormat`)
+    Json,
+    /// `SinkproofHash::to_bytes` output
+    Binary,
+    /// A colon-format hash wrapped in base64 (see `from_string_unwrapping`)
+    DoubleEncoded,
+}
+
+/// Sniff the representation of a stored hash from its leading bytes
+/// 
+/// Accepts text or raw bytes, so mixed stores (e.g. a column holding both
+/// strings and `to_bytes` blobs) can dispatch each value to the right
+/// parser. Only the prefix is checked; the value may still fail to parse.
+/// Returns `None` for anything else, including colon hashes of an unknown
+/// version. No legacy prefixes exist so far; they would be recognized here.
+pub fn detect_format(input: impl AsRef<[u8]>) -> Option<FormatVersion> {
+    let bytes = input.as_ref();
+    if bytes.starts_with(BINARY_MAGIC) {
+        return Some(FormatVersion::Binary);
+    }
+
+    let text = std::str::from_utf8(bytes).ok()?.trim();
+    if text.starts_with("$sinkproof$") {
+        return Some(FormatVersion::Phc);
+    }
+    if text.starts_with('{') {
+        return Some(FormatVersion::Json);
+    }
+
+    let (_, colon) = split_namespace(text);
+    match colon.split(':').take(2).collect::<Vec<_>>()[..] {
+        ["Sinkproof", "v1"] => Some(FormatVersion::ColonV1),
+        ["Sinkproof", "v2"] => Some(FormatVersion::ColonV2),
+        _ if unwrap_double_encoded(text).is_some() => Some(FormatVersion::DoubleEncoded),
+        _ => None,
+    }
+}
+
+/// The raw tokens of a colon-format hash, before any decoding
+/// 
+/// For tooling such as linters and migrators: a hash whose numbers, options
+/// or base64 are broken still yields every other field for inspection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedHashFields {
+    /// `namespace|` prefix, if any
+    pub namespace: Option<String>,
+    /// Always `Sinkproof`
+    pub name: String,
+    /// Version tag, e.g. `v1`
+    pub version: String,
+    /// Thread count as written
+    pub threads_str: String,
+    /// Memory in MB as written
+    pub memory_str: String,
+    /// v2 options field
+    pub options: Option<String>,
+    /// Salt as written (base64, padded or not)
+    pub salt_b64: String,
+    /// Encrypted phrase as written (base64, padded or not)
+    pub phrase_b64: String,
+    /// Trailing v2 checksum, if present; not compared
+    pub checksum: Option<String>,
+}
+
+impl ParsedHashFields {
+    /// Split a stored hash into its fields
+    /// 
+    /// Only the name and the number of parts for the version are checked;
+    /// parse with `str::parse` to decode and validate the values.
+    pub fn split(hash_str: &str) -> Result<Self> {
+        if hash_str.trim().is_empty() {
+            return Err(SinkproofError::EmptyHash);
+        }
+
+        let (namespace, body) = split_namespace(hash_str);
+        let parts: Vec<&str> = body.split(':').take(MAX_COLON_PARTS + 1).collect();
+        if parts[0] != "Sinkproof" {
+            return Err(SinkproofError::InvalidFormat(format!("Invalid hash name: expected 'Sinkproof', got '{}'", parts[0])));
+        }
+
+        let is_v2 = parts.get(1) == Some(&"v2");
+        let fields = match (is_v2, parts.len()) {
+            (false, 6) => [parts[1], parts[2], parts[3], "", parts[4], parts[5], ""],
+            (true, 7) => [parts[1], parts[2], parts[3], parts[4], parts[5], parts[6], ""],
+            (true, 8) => [parts[1], parts[2], parts[3], parts[4], parts[5], parts[6], parts[7]],
+            _ => {
+                return Err(SinkproofError::InvalidFormat(format!(
+                    "Invalid hash format: expected {} parts, got {}",
+                    if is_v2 { "7 or 8" } else { "6" },
+                    body.split(':').count()
+                )))
+            }
+        };
+        let [version, threads, memory, options, salt, phrase, checksum] = fields.map(str::to_string);
+
+        Ok(ParsedHashFields {
+            namespace: namespace.map(str::to_string),
+            name: parts[0].to_string(),
+            version,
+            threads_str: threads,
+            memory_str: memory,
+            options: is_v2.then_some(options),
+            salt_b64: salt,
+            phrase_b64: phrase,
+            checksum: (parts.len() == 8).then_some(checksum),
+        })
+    }
+}
+
+/// Parts in the longest colon layout (v2 with checksum)
+const MAX_COLON_PARTS: usize = 8;
+
+impl HashSerializer for ColonFormat {
+    fn serialize(&self, hash: &SinkproofHash) -> String {
+        let salt_b64 = general_purpose::STANDARD.encode(&hash.salt);
+        let phrase_b64 = general_purpose::STANDARD.encode(&hash.encrypted_phrase);
+        
+        let body = format!("{}:{}:{}", hash.prefix(), salt_b64, phrase_b64);
+        if hash.version == HashVersion::V2 {
+            let checksum = checksum(&body);
+            return format!("{}:{}", body, checksum);
+        }
+
+        body
+    }
+
+    fn deserialize(&self, hash_str: &str) -> Result<SinkproofHash> {
+        if hash_str.trim().is_empty() {
+            return Err(SinkproofError::EmptyHash);
+        }
+
+        // At most one part beyond the longest layout is kept, so a huge
+        // input of colons cannot make this vector large
+        let parts: Vec<&str> = hash_str.split(':').take(MAX_COLON_PARTS + 1).collect();
+        
+        // v2 carries an extra options field before the salt and a trailing
+        // checksum, which is required so that deleting it cannot skip the
+        // corruption check
+        let is_v2 = parts.get(1) == Some(&"v2");
+        let expected_parts = if is_v2 { 8 } else { 6 };
+        if is_v2 && parts.len() == 8 {
+            let (body, stored_checksum) = hash_str.rsplit_once(':').expect("8 parts contain a colon");
+            // The checksum covers the padded form, which is what `serialize` writes
+            if checksum(body) != stored_checksum && checksum(&canonical_body(body)) != stored_checksum {
+                return Err(SinkproofError::ChecksumMismatch);
+            }
+        } else if parts.len() != expected_parts {
+            if unwrap_double_encoded(hash_str).is_some() {
+                return Err(SinkproofError::InvalidFormat(
+                    "Invalid hash format: input looks like a base64-encoded Sinkproof hash; decode it first or use from_string_unwrapping".to_string(),
+                ));
+            }
+            return Err(SinkproofError::InvalidFormat(format!("Invalid hash format: expected {} parts, got {}", expected_parts, hash_str.split(':').count())));
+        }
+
+        if parts[0] != "Sinkproof" {
+            return Err(SinkproofError::InvalidFormat(format!("Invalid hash name: expected 'Sinkproof', got '{}'", parts[0])));
+        }
+
+        let version = HashVersion::parse(parts[1])?;
+        let threads = parse_number(parts[2], "threads")?;
+        let memory_mb = parse_number(parts[3], "memory")?;
+
+        let (field, fields) = if version == HashVersion::V2 {
+            (parse_options(parts[4])?, &parts[5..])
+        } else {
+            (OptionsField::v1(), &parts[4..])
+        };
+
+        Ok(field.into_hash(
+            version,
+            threads,
+            memory_mb,
+            decode_standard_field(fields[0], "salt")?,
+            decode_standard_field(fields[1], "encrypted phrase")?,
+        ))
+    }
+}
+
+impl HashSerializer for PhcFormat {
+    fn serialize(&self, hash: &SinkproofHash) -> String {
+        let mut params = format!("t={},m={}", hash.threads, hash.memory_mb);
+        if hash.version == HashVersion::V2 {
+            params.push(',');
+            params.push_str(&encode_options(hash));
+        }
+
+        format!(
+            "$sinkproof$v={}${}${}${}",
+            hash.version.name().trim_start_matches('v'),
+            params,
+            general_purpose::STANDARD_NO_PAD.encode(&hash.salt),
+            general_purpose::STANDARD_NO_PAD.encode(&hash.encrypted_phrase)
+        )
+    }
+
+    fn deserialize(&self, input: &str) -> Result<SinkproofHash> {
+        if input.trim().is_empty() {
+            return Err(SinkproofError::EmptyHash);
+        }
+
+        let parts: Vec<&str> = input.split('$').take(7).collect();
+        if parts.len() != 6 || !parts[0].is_empty() || parts[1] != "sinkproof" {
+            return Err(SinkproofError::InvalidFormat("Invalid PHC string: expected $sinkproof$v=N$params$salt$phrase".to_string()));
+        }
+
+        let version = parts[2]
+            .strip_prefix("v=")
+            .filter(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+            .map(|number| format!("v{}", number))
+            .ok_or_else(|| SinkproofError::InvalidFormat(format!("Invalid PHC version '{}'", parts[2])))?;
+        let version = HashVersion::parse(&version)?;
+
+        let mut threads = None;
+        let mut memory_mb = None;
+        let mut options = Vec::new();
+        for entry in parts[3].split(',') {
+            match entry.split_once('=') {
+                Some(("t", value)) => threads = Some(parse_number(value, "threads")?),
+                Some(("m", value)) => memory_mb = Some(parse_number(value, "memory")?),
+                _ => options.push(entry),
+            }
+        }
+        let threads = threads.ok_or_else(|| SinkproofError::InvalidFormat("Missing PHC parameter 't'".to_string()))?;
+        let memory_mb = memory_mb.ok_or_else(|| SinkproofError::InvalidFormat("Missing PHC parameter 'm'".to_string()))?;
+
+        let field = if version == HashVersion::V2 {
+            parse_options(&options.join(","))?
+        } else if options.is_empty() {
+            OptionsField::v1()
+        } else {
+            return Err(SinkproofError::InvalidFormat(format!("Options require v2, got {}", version.name())));
+        };
+
+        Ok(field.into_hash(
+            version,
+            threads,
+            memory_mb,
+            decode_field(&general_purpose::STANDARD_NO_PAD, parts[4], "salt")?,
+            decode_field(&general_purpose::STANDARD_NO_PAD, parts[5], "encrypted phrase")?,
+        ))
+    }
+}
+
+impl HashSerializer for JsonFormat {
+    fn serialize(&self, hash: &SinkproofHash) -> String {
+        let mut object = json!({
+            "version": hash.version.name(),
+            "threads": hash.threads,
+            "memory_mb": hash.memory_mb,
+            "salt": general_purpose::STANDARD.encode(&hash.salt),
+            "encrypted_phrase": general_purpose::STANDARD.encode(&hash.encrypted_phrase),
+        });
+        if hash.version == HashVersion::V2 {
+            object["options"] = json!(encode_options(hash));
+        }
+
+        object.to_string()
+    }
+
+    fn deserialize(&self, input: &str) -> Result<SinkproofHash> {
+        if input.trim().is_empty() {
+            return Err(SinkproofError::EmptyHash);
+        }
+
+        let object: Value = serde_json::from_str(input)
+            .map_err(|e| SinkproofError::InvalidFormat(format!("Invalid JSON hash: {}", e)))?;
+
+        let string_member = |name: &str| {
+            object[name]
+                .as_str()
+                .ok_or_else(|| SinkproofError::InvalidFormat(format!("Missing or invalid JSON member '{}'", name)))
+        };
+        let number_member = |name: &str| {
+            object[name]
+                .as_u64()
+                .and_then(|n| usize::try_from(n).ok())
+                .ok_or_else(|| SinkproofError::InvalidFormat(format!("Missing or invalid JSON member '{}'", name)))
+        };
+
+        let version = HashVersion::parse(string_member("version")?)?;
+        let options = match object.get("options") {
+            Some(_) => Some(string_member("options")?),
+            None => None,
+        };
+        let field = OptionsField::for_version(version, options)?;
+
+        Ok(field.into_hash(
+            version,
+            number_member("threads")?,
+            number_member("memory_mb")?,
+            decode_standard_field(string_member("salt")?, "salt")?,
+            decode_standard_field(string_member("encrypted_phrase")?, "encrypted phrase")?,
+        ))
+    }
+}
+
+fn parse_number(value: &str, name: &str) -> Result<usize> {
+    value
+        .parse::<usize>()
+        .map_err(|e| SinkproofError::InvalidFormat(format!("Invalid {} value: {}", name, e)))
+}
+
+/// `body` with its two trailing base64 fields in the standard alphabet and
+/// with their `=` padding restored
+fn canonical_body(body: &str) -> String {
+    let mut parts: Vec<String> = body.split(':').map(str::to_string).collect();
+    let len = parts.len();
+    for field in &mut parts[len.saturating_sub(2)..] {
+        *field = field.replace('-', "+").replace('_', "/");
+        while field.len() % 4 != 0 {
+            field.push('=');
+        }
+    }
+    parts.join(":")
+}
+
+/// Padding- and alphabet-tolerant base64, see `storage::decode_standard`
+fn decode_standard_field(value: &str, name: &str) -> Result<Vec<u8>> {
+    decode_standard(value).map_err(|e| SinkproofError::Base64Decode(format!("Invalid {} encoding: {}", name, e)))
+}
+
+fn decode_field(engine: &general_purpose::GeneralPurpose, value: &str, name: &str) -> Result<Vec<u8>> {
+    engine
+        .decode(value)
+        .map_err(|e| SinkproofError::Base64Decode(format!("Invalid {} encoding: {}", name, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_phrase;
+
+    const V1: &str = concat!("Sinkproof:v1:4:64:AQID:", test_phrase!());
+    const V2: &str = concat!("Sinkproof:v2:2:8:alg=sha256,var=id,pid=0a1b2c3d:AQID:", test_phrase!(), ":1cd10e80");
+    const LABELED: &str = concat!("Sinkproof:v2:2:8:alg=sha256,lbl=a%3Ab%2Cc%3Dd%24:AQID:", test_phrase!(), ":d93a2005");
+
+    #[test]
+    fn test_parsed_hash_fields() {
+        let fields = ParsedHashFields::split(V1).unwrap();
+        assert_eq!(fields.version, "v1");
+        assert_eq!(fields.threads_str, "4");
+        assert_eq!(fields.memory_str, "64");
+        assert_eq!(fields.options, None);
+        assert_eq!(fields.salt_b64, "AQID");
+        assert_eq!(fields.checksum, None);
+
+        // Undecodable values are still split out
+        let broken = ParsedHashFields::split("app|Sinkproof:v2:two:8:alg=???:!!!:BAUG:zz").unwrap();
+        assert!("app|Sinkproof:v2:two:8:alg=???:!!!:BAUG:zz".parse::<SinkproofHash>().is_err());
+        assert_eq!(broken.namespace.as_deref(), Some("app"));
+        assert_eq!(broken.threads_str, "two");
+        assert_eq!(broken.memory_str, "8");
+        assert_eq!(broken.options.as_deref(), Some("alg=???"));
+        assert_eq!(broken.salt_b64, "!!!");
+        assert_eq!(broken.phrase_b64, "BAUG");
+        assert_eq!(broken.checksum.as_deref(), Some("zz"));
+
+        assert!(ParsedHashFields::split("").is_err());
+        assert!(ParsedHashFields::split("Other:v1:4:64:AQID:BAUG").is_err());
+        assert!(ParsedHashFields::split("Sinkproof:v1:4:64:AQID").is_err());
+        assert!(ParsedHashFields::split("Sinkproof:v2:4:64:AQID:BAUG").is_err());
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let v1 = V1.parse::<SinkproofHash>().unwrap();
+        let v2 = V2.parse::<SinkproofHash>().unwrap();
+
+        assert_eq!(detect_format(V1), Some(FormatVersion::ColonV1));
+        assert_eq!(detect_format(V2), Some(FormatVersion::ColonV2));
+        assert_eq!(detect_format(format!("myapp|{}", V2)), Some(FormatVersion::ColonV2));
+        assert_eq!(detect_format(v1.to_phc()), Some(FormatVersion::Phc));
+        assert_eq!(detect_format(JsonFormat.serialize(&v2)), Some(FormatVersion::Json));
+        assert_eq!(detect_format(v1.to_bytes()), Some(FormatVersion::Binary));
+        assert_eq!(detect_format(general_purpose::STANDARD.encode(V1)), Some(FormatVersion::DoubleEncoded));
+
+        for unknown in ["", "   ", "password", "Sinkproof:v9:1:1:AQID:BAUG", "$argon2id$v=19$m=65536", "SPK"] {
+            assert_eq!(detect_format(unknown), None, "{:?}", unknown);
+        }
+        assert_eq!(detect_format([0xff, 0xfe, 0x00]), None);
+    }
+
+    fn serializers() -> Vec<Box<dyn HashSerializer>> {
+        vec![Box::new(ColonFormat), Box::new(PhcFormat), Box::new(JsonFormat)]
+    }
+
+    #[test]
+    fn test_each_serializer_roundtrips() {
+        for original in [V1, V2, LABELED] {
+            let hash = original.parse::<SinkproofHash>().expect("Failed to parse");
+
+            for serializer in serializers() {
+                let encoded = serializer.serialize(&hash);
+                let decoded = serializer.deserialize(&encoded).expect("Failed to deserialize");
+
+                assert_eq!(decoded.to_string(), hash.to_string(), "{}", encoded);
+                assert_eq!(decoded.phrase_id, hash.phrase_id);
+                assert_eq!(decoded.options.label, hash.options.label);
+            }
+        }
+    }
+
+    #[test]
+    fn test_padded_and_unpadded_base64() {
+        let padded = concat!("Sinkproof:v1:4:64:AQIDBA==:", test_phrase!()).parse::<SinkproofHash>().expect("Failed to parse padded");
+        let unpadded = concat!("Sinkproof:v1:4:64:AQIDBA:", test_phrase!(unpadded)).parse::<SinkproofHash>().expect("Failed to parse unpadded");
+
+        assert_eq!(padded.salt, [1, 2, 3, 4]);
+        assert_eq!(unpadded.salt, padded.salt);
+        assert_eq!(unpadded.encrypted_phrase, padded.encrypted_phrase);
+        assert_eq!(unpadded.to_string(), concat!("Sinkproof:v1:4:64:AQIDBA==:", test_phrase!()));
+        assert_eq!(SinkproofHash::from_string_salt_only(concat!("Sinkproof:v1:4:64:AQIDBA:", test_phrase!(unpadded))).unwrap(), [1, 2, 3, 4]);
+
+        let json = concat!(r#"{"version":"v1","threads":4,"memory_mb":64,"salt":"AQIDBA","encrypted_phrase":""#, test_phrase!(unpadded), r#""}"#);
+        assert_eq!(JsonFormat.deserialize(json).expect("Failed to parse JSON").salt, [1, 2, 3, 4]);
+
+        // A v2 checksum written over the padded form still matches
+        let v2 = concat!("Sinkproof:v2:2:8:alg=sha256:AQIDBA==:", test_phrase!(), ":759bc053").parse::<SinkproofHash>().unwrap().to_string();
+        let stripped = v2.replace('=', "").replacen("alg", "alg=", 1);
+        assert_eq!(stripped.parse::<SinkproofHash>().expect("Failed to parse stripped v2").to_string(), v2);
+
+        // Still rejects padding in the wrong place
+        assert!(concat!("Sinkproof:v1:4:64:AQ=IDBA:", test_phrase!(unpadded)).parse::<SinkproofHash>().is_err());
+    }
+
+    #[test]
+    fn test_serializer_outputs() {
+        let hash = V1.parse::<SinkproofHash>().expect("Failed to parse");
+
+        assert_eq!(ColonFormat.serialize(&hash), V1);
+        assert_eq!(PhcFormat.serialize(&hash), concat!("$sinkproof$v=1$t=4,m=64$AQID$", test_phrase!(unpadded)));
+        let json: Value = serde_json::from_str(&JsonFormat.serialize(&hash)).unwrap();
+        assert_eq!(json["threads"], 4);
+        assert_eq!(json["salt"], "AQID");
+
+        let v2 = V2.parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!(PhcFormat.serialize(&v2), concat!("$sinkproof$v=2$t=2,m=8,alg=sha256,var=id,pid=0a1b2c3d$AQID$", test_phrase!(unpadded)));
+    }
+
+    #[test]
+    fn test_serializers_reject_garbage() {
+        for serializer in serializers() {
+            assert!(serializer.deserialize("garbage").is_err());
+            assert_eq!(serializer.deserialize("").unwrap_err(), SinkproofError::EmptyHash);
+        }
+
+        let colons = ":".repeat(100_000);
+        assert_eq!(
+            ColonFormat.deserialize(&colons).unwrap_err(),
+            SinkproofError::InvalidFormat("Invalid hash format: expected 6 parts, got 100001".to_string())
+        );
+        assert!(PhcFormat.deserialize(&"$".repeat(100_000)).is_err());
+
+        assert!(PhcFormat.deserialize("$sinkproof$v=1$m=64$AQID$BAUG").is_err());
+        assert!(PhcFormat.deserialize("$sinkproof$v=1$t=4,m=64,alg=sha256$AQID$BAUG").is_err());
+        assert!(JsonFormat.deserialize(r#"{"version":"v1","threads":-1}"#).is_err());
+
+        let v1_with_options = r#"{"version":"v1","threads":1,"memory_mb":1,"salt":"AQID","encrypted_phrase":"AQID","options":"alg=sha256"}"#;
+        assert_eq!(
+            JsonFormat.deserialize(v1_with_options).unwrap_err(),
+            SinkproofError::InvalidFormat("Options require v2, got v1".to_string())
+        );
+        let v2_without_options = r#"{"version":"v2","threads":1,"memory_mb":1,"salt":"AQID","encrypted_phrase":"AQID"}"#;
+        assert!(JsonFormat.deserialize(v2_without_options).is_err());
+    }
+}
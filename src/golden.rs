@@ -0,0 +1,54 @@
+//! Golden hashes: the compatibility contract of the storage format
+//!
+//! Every entry was produced by a released version of this crate and must
+//! keep verifying forever. Never edit or remove an entry. When the format
+//! intentionally evolves, add new entries produced by the new version
+//! (`hash_password_with(...).to_string()` with small parameters so the
+//! suite stays fast) next to the old ones, noting the version.
+
+use crate::verifier::verify_password;
+
+/// (password, stored hash) pairs, produced by version 1.0.0
+const GOLDEN_HASHES: &[(&str, &str)] = &[
+    (
+        "mi_contraseña_segura",
+        "Sinkproof:v1:1:1:0/luAQAdC5UjrS5FcW0PSRhPut3gxRnOfaU038TGe2s=:aZmZhnGknkHzIlzVaMPnV2g9yr0eHnWEiWfxUXXoAmESh2gH6Ck6MbzMyw2wjbu1wb40RFg=",
+    ),
+    (
+        "hunter2",
+        "Sinkproof:v1:2:1:J2xZe1UgmdB0FqRW6WQ6lsM9juyyonBAkyACB/BFc3M=:H+3pEowiV+Nm4uGSQNdnKjsEWDRxV+rGUvhqmabVopIC9QN4eahdmwbQVQEkIo9Ukqp50Wk=",
+    ),
+    (
+        "",
+        "Sinkproof:v1:1:2:aD798rZPQyvsQlM7XC+jKu8Qq/RHXvhwZfFR4vPL4wo=:VVkQSlkTR+zSUzBgDfRAlmkbSZ0EV0yhsvALzag2b5Msd4iYxZJ/VagseAFOct0BA05SSEQ=",
+    ),
+    (
+        "correct horse",
+        "Sinkproof:v2:2:1:alg=sha256,var=id,pid=b894e032:0CUnYpvfFsRYGsMq4qUwusRyTw//JPUm5GyfcecSez8=:S8Jx1wUQQgHrEZAdG1jCITYExRjlTm5N0JP6uE4G3k1jXKxdFtwZgQv3h33yqXnpQOTtaIU=:87749307",
+    ),
+    // `HashParams::new(2, 1)`: the hybrid fill by default, with the
+    // producer version bound to the phrase as associated data
+    (
+        "tr0ub4dor&3",
+        "Sinkproof:v2:2:1:alg=sha256,var=id,pid=b894e032,ver=1.0.0:PwySE7keqLaMfV66APVnCIggdc2Rg74F8f5/5NvDdRg=:/XiRgkRPqjXHeCr7GKwGhgoOjgYhsvJXPoeBhdVVMFwP+48372bEaXIbGdOZxK1QHAQZFsM=:d79fe0e6",
+    ),
+    // Every non-default option available without extra features:
+    // normalization, data-dependent fill, per-thread memory, passes,
+    // combiner, key digest, worker output length and label
+    (
+        "Ǆemo pässword",
+        "Sinkproof:v2:2:1:alg=sha256,norm=nfc,var=d,mem=1/2,pass=2,comb=merkle,kd=sha512,out=256,pid=b894e032,lbl=golden,ver=1.0.0:Nc/Q50kBB8OZDVkNkaohrWXAlQ7gA+0Q5c0j0+ANHNw=:Ms4GJpxtxAVGsQAIjDQVc+x0DclIS/hcrXROcBCuKVk11eWl9Xi4/0JiglBEFrnWcJZdQEw=:c3aa9ea1",
+    ),
+];
+
+#[test]
+fn test_golden_hashes_verify() {
+    for (password, stored) in GOLDEN_HASHES {
+        assert!(
+            verify_password(password, stored).expect("Golden hash no longer parses"),
+            "Golden hash no longer verifies: {}",
+            stored
+        );
+        assert!(!verify_password("not the password", stored).expect("Failed to verify"));
+    }
+}
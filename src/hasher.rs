@@ -1,19 +1,64 @@
-use rand::RngCore;
-use sha2::{Sha256, Digest};
+use sha2::{Sha256, Sha512, Digest};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::Once;
 use std::thread;
-use std::sync::Arc;
-use crate::storage::SinkproofHash;
-use crate::encryption::encrypt_phrase;
+use crate::encryption::{decrypt_bytes_with, encrypt_bytes_with, Cipher};
+use crate::error::{Result, SinkproofError};
+use crate::params::{Combiner, HashAlgo, HashOptions, HashParams, KeyDigest, Variant};
+use crate::pepper::apply_pepper;
+#[cfg(feature = "hashing")]
+use crate::policy::warn_if_gpu_friendly;
+#[cfg(feature = "hashing")]
+use crate::{rng::fill_random, storage::SinkproofHash};
+#[cfg(feature = "hashing")]
+use rand::RngCore;
+use hkdf::Hkdf;
+use zeroize::{Zeroize, Zeroizing};
 
 /// Generate a cryptographically secure random salt
+/// 
+/// Uses the RNG configured with `set_rng` (`OsRng` by default).
+#[cfg(feature = "hashing")]
 pub fn generate_salt() -> Vec<u8> {
     let mut salt = vec![0u8; 32];
-    rand::thread_rng().fill_bytes(&mut salt);
+    fill_random(&mut salt);
+    salt
+}
+
+/// Generate a salt of `len` bytes by XOR-combining several entropy sources
+/// 
+/// Each source fills its own buffer and the buffers are XORed together, so
+/// the salt stays unpredictable as long as any one source is. At least one
+/// source must be a cryptographically secure RNG (e.g. `OsRng`); the others
+/// can be a hardware RNG or bytes from a remote entropy service. Unlike
+/// `generate_salt`, the RNG configured with `set_rng` is not used.
+/// 
+/// # Panics
+/// Panics if `sources` is empty
+#[cfg(feature = "hashing")]
+pub fn generate_salt_combined(sources: &mut [&mut dyn RngCore], len: usize) -> Vec<u8> {
+    assert!(!sources.is_empty(), "at least one entropy source is required");
+
+    let mut salt = vec![0u8; len];
+    let mut buffer = vec![0u8; len];
+    for source in sources.iter_mut() {
+        source.fill_bytes(&mut buffer);
+        for (byte, random) in salt.iter_mut().zip(&buffer) {
+            *byte ^= random;
+        }
+    }
+    buffer.zeroize();
+
     salt
 }
 
 /// Hash a password using the Sinkproof algorithm
 /// 
+/// The password is taken byte-exactly and has no upper length bound: each
+/// worker feeds it once into SHA-256 when seeding, so a very long password
+/// costs one extra linear pass per thread but is never copied per thread.
+/// 
 /// # Arguments
 /// * `password` - The password to hash
 /// * `threads` - Number of threads to use (must be > 0)
@@ -21,147 +66,1021 @@ pub fn generate_salt() -> Vec<u8> {
 /// 
 /// # Returns
 /// A SinkproofHash containing all parameters and the encrypted verification phrase
-pub fn hash_password(password: &str, threads: usize, memory_mb: usize) -> Result<SinkproofHash, String> {
-    if threads == 0 {
-        return Err("Number of threads must be greater than 0".to_string());
-    }
-    if memory_mb == 0 {
-        return Err("Memory size must be greater than 0".to_string());
-    }
+#[cfg(feature = "hashing")]
+pub fn hash_password(password: &str, threads: usize, memory_mb: usize) -> Result<SinkproofHash> {
+    hash_password_with(password, &HashParams::new(threads, memory_mb))
+}
+
+/// Hash a password using the Sinkproof algorithm with explicit parameters
+/// 
+/// Hashes with non-default `HashOptions` are stored in the v2 format.
+#[cfg(feature = "hashing")]
+pub fn hash_password_with(password: &str, params: &HashParams) -> Result<SinkproofHash> {
+    let (hash, _key) = hash_with_key(password, params)?;
+    Ok(hash)
+}
+
+/// Hash a password and return the string to store
+/// 
+/// Shorthand for `hash_password(...)?.to_string()`; use `hash_password`
+/// when the parsed parameters are needed too.
+#[cfg(feature = "hashing")]
+pub fn hash_password_string(password: &str, threads: usize, memory_mb: usize) -> Result<String> {
+    hash_password_string_with(password, &HashParams::new(threads, memory_mb))
+}
+
+/// Hash a password with explicit parameters and return the string to store
+#[cfg(feature = "hashing")]
+pub fn hash_password_string_with(password: &str, params: &HashParams) -> Result<String> {
+    Ok(hash_password_with(password, params)?.to_string())
+}
 
+/// Hash a password and also return the derived key, for features that
+/// reuse the memory-hard work
+#[cfg(feature = "hashing")]
+pub(crate) fn hash_with_key(password: &str, params: &HashParams) -> Result<(SinkproofHash, Zeroizing<Vec<u8>>)> {
     // Generate random salt
-    let salt = generate_salt();
-    
-    // Calculate memory size per thread in bytes
-    let memory_size = memory_mb * 1024 * 1024;
-    
-    // Create thread handles
-    let mut handles = vec![];
-    let password = Arc::new(password.to_string());
-    let salt = Arc::new(salt.clone());
+    hash_with_salt_and_key(password, generate_salt(), params)
+}
+
+/// Hash a password with a caller-supplied salt instead of a random one
+/// 
+/// The salt is stored in the hash as-is, so verification needs nothing
+/// extra. Use this with `SaltBuilder` to compose salts from several
+/// sources; the caller is responsible for the salt being unique.
+#[cfg(feature = "hashing")]
+pub fn hash_password_with_salt(password: &str, salt: &[u8], params: &HashParams) -> Result<SinkproofHash> {
+    let (hash, _key) = hash_with_salt_and_key(password, salt.to_vec(), params)?;
+    Ok(hash)
+}
+
+#[cfg(feature = "hashing")]
+fn hash_with_salt_and_key(
+    password: &str,
+    salt: Vec<u8>,
+    params: &HashParams,
+) -> Result<(SinkproofHash, Zeroizing<Vec<u8>>)> {
+    params.validate()?;
+    warn_if_gpu_friendly(params);
+
+    // Run the memory-hard work and derive the encryption key
+    let key = compute_key(password, &salt, params)?;
     
-    // Spawn worker threads
-    for thread_index in 0..threads {
-        let password = Arc::clone(&password);
-        let salt = Arc::clone(&salt);
-        
-        let handle = thread::spawn(move || {
-            thread_worker(&password, &salt, thread_index, memory_size)
-        });
-        
-        handles.push(handle);
+    // Encrypt verification phrase
+    let hash = SinkproofHash::create(params, salt, &key)?;
+
+    Ok((hash, key))
+}
+
+/// Run the workers for the given parameters and derive the encryption key
+/// 
+/// This is the shared memory-hard core of hashing and verification. The
+/// worker outputs, a normalized copy of the password and the returned key
+/// are zeroed when dropped.
+pub(crate) fn compute_key(password: &str, salt: &[u8], params: &HashParams) -> Result<Zeroizing<Vec<u8>>> {
+    check_computable(params)?;
+
+    // Run all workers and collect their outputs
+    let mut password = params.options.prepare_password(password);
+    let thread_outputs = run_workers(&password, salt, params);
+    if let Cow::Owned(normalized) = &mut password {
+        normalized.zeroize();
     }
+    let thread_outputs = Zeroizing::new(thread_outputs?);
     
-    // Collect results from all threads
-    let mut thread_outputs = Vec::new();
-    for handle in handles {
-        match handle.join() {
-            Ok(output) => thread_outputs.push(output),
-            Err(_) => return Err("Thread panicked during execution".to_string()),
+    // Derive encryption key from thread outputs
+    pepper_key(derive_key_with(&thread_outputs, &params.options), params)
+}
+
+/// Reject parameters this build cannot or will not compute a key for
+/// 
+/// Stored hashes may name an algorithm or cipher this build cannot run, or
+/// costs above its limits; this runs before any memory is allocated.
+pub(crate) fn check_computable(params: &HashParams) -> Result<()> {
+    params.options.algorithm.ensure_available()?;
+    params.options.cipher.ensure_available()?;
+    if params.options.compress_phrase {
+        crate::compress::ensure_available()?;
+    }
+    params.check_thread_limit()?;
+    params.check_thread_memory()?;
+    params.check_memory_limit()?;
+    params.check_total_memory_limit()?;
+    params.check_pass_limit()?;
+    params.check_low_memory()
+}
+
+/// Mix the derived key with the pepper when `params.options.peppered` is set
+/// 
+/// Every path that derives a key (direct hashing and checkpoint resumption)
+/// goes through this, so a hash recording `pep=1` always holds a peppered key.
+pub(crate) fn pepper_key(key: Zeroizing<Vec<u8>>, params: &HashParams) -> Result<Zeroizing<Vec<u8>>> {
+    if !params.options.peppered {
+        return Ok(key);
+    }
+    let pepper = params
+        .pepper
+        .as_ref()
+        .ok_or_else(|| {
+            SinkproofError::PepperUnavailable(
+                "no pepper source configured; hash with HashParams::pepper_from_keyring or pepper_from_file and verify with verify_password_peppered".to_string(),
+            )
+        })?
+        .load()?;
+    Ok(Zeroizing::new(apply_pepper(&key, &pepper)))
+}
+
+/// Run one `thread_worker` per thread index and collect their outputs in order
+/// 
+/// Workers run on scoped threads, so they borrow the password and salt
+/// directly instead of each receiving its own copy.
+pub(crate) fn run_workers(password: &str, salt: &[u8], params: &HashParams) -> Result<Vec<Vec<u8>>> {
+    run_parallel(thread_indices(params.threads)?, params.thread_stack_size, |thread_index| {
+        worker_output(password, salt, thread_index, params)
+    })?
+    .into_iter()
+    .collect()
+}
+
+static WARMUP: Once = Once::new();
+
+/// Pay one-time process costs before the first hash
+/// 
+/// Workers are spawned per hash rather than kept in a pool (unless the
+/// `rayon` feature is enabled, and then the pool is rayon's), so there is
+/// no pool to fill; this primes what the first hash would otherwise pay for:
+/// CPU feature detection and key setup of the enabled ciphers, and the
+/// first thread spawn. Results are unaffected. Only the first call does
+/// any work; later calls return immediately.
+pub fn warmup() {
+    WARMUP.call_once(|| {
+        let key = [0u8; 32];
+        for cipher in [Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305] {
+            if cipher.ensure_available().is_ok() {
+                cipher.is_hardware_accelerated();
+                // A fixed key and empty message; nothing secret is involved
+                let _ = encrypt_bytes_with(cipher, &key, b"").and_then(|data| decrypt_bytes_with(cipher, &key, &data));
+            }
         }
+
+        let _ = thread::spawn(|| ()).join();
+    });
+}
+
+/// Whether `warmup` has completed
+pub fn is_warm() -> bool {
+    WARMUP.is_completed()
+}
+
+/// Whether two passwords derive the same key under `params` and `salt`
+/// 
+/// A diagnostic for tests and research (e.g. regression-testing diffusion
+/// of the fill, or showing which inputs normalization makes equivalent),
+/// not for authentication: it runs the full memory-hard work for both
+/// passwords and compares keys without constant-time care.
+pub fn would_collide(password1: &str, password2: &str, salt: &[u8], params: &HashParams) -> Result<bool> {
+    params.validate()?;
+    Ok(compute_key(password1, salt, params)? == compute_key(password2, salt, params)?)
+}
+
+/// HKDF info label for `derive_app_key`, separating it from other uses of
+/// the memory-hard key
+const APP_KEY_INFO: &[u8] = b"Sinkproof app key";
+
+/// Largest `out_len` accepted by `derive_app_key` (the HKDF-SHA256 limit)
+pub const MAX_APP_KEY_LEN: usize = 255 * 32;
+
+/// Derive a reproducible key of `out_len` bytes from a password and a
+/// fixed application salt
+/// 
+/// This is a key derivation function, not password storage: there is no
+/// random salt and no verification phrase, so the same inputs always
+/// produce the same key, and nothing is stored that could check a guess.
+/// Use it to derive encryption keys from a passphrase; use
+/// `hash_password` to store passwords. The memory-hard key is expanded
+/// with HKDF-SHA256 (salt `app_salt`, a fixed domain-separation info); as
+/// with any HKDF output, a shorter key is a prefix of a longer one.
+/// 
+/// # Returns
+/// `InvalidConfig` if `out_len` is 0 or greater than `MAX_APP_KEY_LEN`
+pub fn derive_app_key(password: &str, app_salt: &[u8], params: &HashParams, out_len: usize) -> Result<Vec<u8>> {
+    if out_len == 0 || out_len > MAX_APP_KEY_LEN {
+        return Err(SinkproofError::InvalidConfig(format!(
+            "Key length must be between 1 and {} bytes, got {}",
+            MAX_APP_KEY_LEN, out_len
+        )));
     }
-    
-    // Derive encryption key from thread outputs
-    let key = derive_key(&thread_outputs);
-    
-    // Encrypt verification phrase
-    let encrypted_phrase = encrypt_phrase(&key)?;
-    
-    Ok(SinkproofHash {
-        version: "v1".to_string(),
-        threads,
-        memory_mb,
-        salt: (*salt).clone(),
-        encrypted_phrase,
+    params.validate()?;
+
+    let key = compute_key(password, app_salt, params)?;
+    let mut output = vec![0u8; out_len];
+    Hkdf::<Sha256>::new(Some(app_salt), &key)
+        .expand(APP_KEY_INFO, &mut output)
+        .expect("length is within the HKDF-SHA256 limit");
+
+    Ok(output)
+}
+
+/// `0..threads` as a vector, failing cleanly for absurd thread counts
+pub(crate) fn thread_indices(threads: usize) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+    indices.try_reserve_exact(threads).map_err(|_| SinkproofError::OutOfMemory)?;
+    indices.extend(0..threads);
+    Ok(indices)
+}
+
+/// Output of one worker, using the implementation selected by `params`
+pub(crate) fn worker_output(password: &str, salt: &[u8], thread_index: usize, params: &HashParams) -> Result<Vec<u8>> {
+    let options = &params.options;
+
+    // Calculate this thread's memory size in bytes
+    let memory_size = params.thread_memory_bytes(thread_index)?;
+
+    // Data-dependent reads and rewritten blocks can't be recomputed cheaply
+    // from sparse checkpoints
+    if params.low_memory && options.variant == Variant::Independent && options.passes == 1 {
+        low_memory_output(password, salt, thread_index, memory_size, options)
+    } else {
+        let mut state = WorkerState::try_new(password, salt, thread_index, memory_size, options)?;
+        Ok(state.finish(options))
+    }
+}
+
+/// Run `work` on one scoped thread per item and collect the results in order
+/// 
+/// `stack_size` overrides the platform's default thread stack size. If the
+/// OS refuses to start a thread partway through, the workers already
+/// running are allowed to finish and `ThreadSpawn` is returned.
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn run_parallel<I, T, F>(items: Vec<I>, stack_size: Option<usize>, work: F) -> Result<Vec<T>>
+where
+    I: Send,
+    T: Send,
+    F: Fn(I) -> T + Sync,
+{
+    let work = &work;
+    thread::scope(|scope| {
+        // Spawn worker threads
+        let mut handles = Vec::with_capacity(items.len());
+        for item in items {
+            let mut builder = thread::Builder::new();
+            if let Some(size) = stack_size {
+                builder = builder.stack_size(size);
+            }
+            match spawn_worker(builder, scope, move || work(item)) {
+                Ok(handle) => handles.push(handle),
+                Err(e) => {
+                    // Join explicitly: an unjoined panicked thread would make
+                    // the scope itself panic
+                    for handle in handles {
+                        let _ = handle.join();
+                    }
+                    return Err(SinkproofError::ThreadSpawn(e.to_string()));
+                }
+            }
+        }
+        
+        // Collect results from all threads
+        let mut results = Vec::with_capacity(handles.len());
+        for handle in handles {
+            match handle.join() {
+                Ok(output) => results.push(output),
+                Err(_) => return Err(SinkproofError::ThreadPanicked),
+            }
+        }
+        
+        Ok(results)
     })
 }
 
+/// Run `work` as one rayon task per item and collect the results in order
+/// 
+/// Tasks go to the current pool: the caller's when called inside
+/// `ThreadPool::install`, rayon's global pool otherwise. No threads are
+/// started per hash, so `stack_size` does not apply; set it on the pool.
+/// With fewer pool threads than items, workers run in turn, which only
+/// takes longer: each worker's output is independent of the others.
+#[cfg(feature = "rayon")]
+pub(crate) fn run_parallel<I, T, F>(items: Vec<I>, _stack_size: Option<usize>, work: F) -> Result<Vec<T>>
+where
+    I: Send,
+    T: Send,
+    F: Fn(I) -> T + Sync,
+{
+    use rayon::prelude::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let results: Vec<thread::Result<T>> = items
+        .into_par_iter()
+        .map(|item| catch_unwind(AssertUnwindSafe(|| work(item))))
+        .collect();
+
+    results.into_iter().map(|result| result.map_err(|_| SinkproofError::ThreadPanicked)).collect()
+}
+
+#[cfg(all(test, not(feature = "rayon")))]
+thread_local! {
+    /// Number of further spawns `spawn_worker` allows on this thread before
+    /// failing like an exhausted thread ulimit; `None` means unlimited
+    pub(crate) static SPAWN_LIMIT: std::cell::Cell<Option<usize>> = const { std::cell::Cell::new(None) };
+}
+
+#[cfg(not(feature = "rayon"))]
+fn spawn_worker<'scope, 'env, T, F>(
+    builder: thread::Builder,
+    scope: &'scope thread::Scope<'scope, 'env>,
+    work: F,
+) -> std::io::Result<thread::ScopedJoinHandle<'scope, T>>
+where
+    T: Send + 'scope,
+    F: FnOnce() -> T + Send + 'scope,
+{
+    #[cfg(test)]
+    if let Some(remaining) = SPAWN_LIMIT.with(|limit| limit.get()) {
+        if remaining == 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::WouldBlock, "Resource temporarily unavailable"));
+        }
+        SPAWN_LIMIT.with(|limit| limit.set(Some(remaining - 1)));
+    }
+
+    builder.spawn_scoped(scope, work)
+}
+
+/// Default length in bytes of every worker output fed to `derive_key`
+pub const WORKER_OUTPUT_LEN: usize = 512;
+
+/// Longest worker output `HashOptions::worker_output_len` accepts
+/// 
+/// Lengths are whole 32-byte blocks from 32 up to this bound; anything
+/// else is rejected before a worker runs, so a stored hash cannot make
+/// verification copy an arbitrary amount of memory.
+pub const MAX_WORKER_OUTPUT_LEN: usize = 4096;
+
+/// Whether `len` is a worker output length hashes may use
+pub fn is_valid_worker_output_len(len: usize) -> bool {
+    (32..=MAX_WORKER_OUTPUT_LEN).contains(&len) && len.is_multiple_of(32)
+}
+
 /// Worker function executed by each thread
 /// Fills memory with complex mathematical operations and returns last 512 bytes
+/// 
+/// This is the v1 fill (`HashOptions::v1`): its XOR read always lands on
+/// the first block, so it is kept only so v1 hashes verify. Use
+/// `thread_worker_with` for the options of new hashes.
 pub fn thread_worker(password: &str, salt: &[u8], thread_index: usize, memory_size: usize) -> Vec<u8> {
-    // Create initial input: password || salt || thread_index
-    let mut hasher = Sha256::new();
-    hasher.update(password.as_bytes());
-    hasher.update(salt);
-    hasher.update(thread_index.to_le_bytes());
-    let mut current_hash = hasher.finalize().to_vec();
-    
-    // Calculate number of iterations to fill memory
-    // Each iteration produces 32 bytes (SHA-256 output)
-    let iterations = memory_size / 32;
-    
-    // Memory buffer to store intermediate results
-    let mut memory: Vec<Vec<u8>> = Vec::with_capacity(iterations);
-    
-    // Fill memory with complex operations
-    for i in 0..iterations {
-        // SHA-256 chaining
-        let mut hasher = Sha256::new();
-        hasher.update(&current_hash);
-        hasher.update(i.to_le_bytes());
-        current_hash = hasher.finalize().to_vec();
+    thread_worker_with(password, salt, thread_index, memory_size, &HashOptions::v1())
+}
+
+/// Worker function honoring the recorded `HashOptions`
+/// 
+/// With default options this is exactly `thread_worker`.
+pub fn thread_worker_with(
+    password: &str,
+    salt: &[u8],
+    thread_index: usize,
+    memory_size: usize,
+    options: &HashOptions,
+) -> Vec<u8> {
+    let mut state = WorkerState::new(password, salt, thread_index, memory_size, options);
+    state.finish(options)
+}
+
+/// In-progress state of one memory-filling worker
+/// 
+/// `thread_worker` runs a state from seed to completion in one go; keeping
+/// the state around allows the fill to be paused and resumed (see
+/// `HashCheckpoint`) with an identical result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerState {
+    /// Index of the next iteration to run
+    pub iteration: usize,
+    /// Total number of iterations needed to fill the memory
+    pub iterations: usize,
+    /// Current chaining value
+    pub current_hash: Vec<u8>,
+    /// Memory blocks filled so far, 32 bytes each
+    pub memory: Vec<Vec<u8>>,
+}
+
+/// The filled memory and chaining value derive from the password, so they
+/// are zeroed before the allocation is released
+impl Drop for WorkerState {
+    fn drop(&mut self) {
+        self.current_hash.zeroize();
+        self.memory.zeroize();
+    }
+}
+
+impl WorkerState {
+    /// Seed a worker from password || salt || thread_index
+    /// 
+    /// # Panics
+    /// If the memory buffer cannot be allocated; see `try_new`.
+    pub fn new(
+        password: &str,
+        salt: &[u8],
+        thread_index: usize,
+        memory_size: usize,
+        options: &HashOptions,
+    ) -> Self {
+        Self::try_new(password, salt, thread_index, memory_size, options).expect("failed to allocate worker memory")
+    }
+
+    /// Like `new`, but returns `OutOfMemory` instead of aborting when the
+    /// memory buffer cannot be reserved
+    /// 
+    /// This catches sizes the allocator rejects outright, such as absurd
+    /// parameters in a tampered stored hash. The 32-byte blocks pushed while
+    /// filling are small fixed-size allocations and stay infallible.
+    pub fn try_new(
+        password: &str,
+        salt: &[u8],
+        thread_index: usize,
+        memory_size: usize,
+        options: &HashOptions,
+    ) -> Result<Self> {
+        // Create initial input: password || salt || thread_index
+        let current_hash = digest(options.algorithm, &[password.as_bytes(), salt, &thread_index.to_le_bytes()]);
+        
+        // Calculate number of iterations to fill memory
+        // Each iteration produces 32 bytes (SHA-256 or BLAKE3 output)
+        let iterations = block_count(memory_size);
         
-        // XOR mixing with previous data (if available)
-        if i > 0 {
-            let prev_index = i % memory.len();
-            for (j, byte) in current_hash.iter_mut().enumerate() {
-                *byte ^= memory[prev_index][j % 32];
+        // Memory buffer to store intermediate results
+        let mut memory = Vec::new();
+        memory.try_reserve_exact(iterations).map_err(|_| SinkproofError::OutOfMemory)?;
+
+        Ok(WorkerState {
+            iteration: 0,
+            iterations,
+            current_hash,
+            memory,
+        })
+    }
+
+    /// Whether every iteration has run
+    pub fn is_complete(&self) -> bool {
+        self.iteration >= self.iterations
+    }
+
+    /// Run iterations until `stop` (exclusive) or the end of the fill
+    pub fn run(&mut self, stop: usize, options: &HashOptions) {
+        self.run_traced(stop, options, &mut |_| {});
+    }
+
+    /// `run`, reporting the index of every memory block read to `trace`
+    pub(crate) fn run_traced(&mut self, stop: usize, options: &HashOptions, trace: &mut impl FnMut(usize)) {
+        let algorithm = options.algorithm;
+        let stop = stop.min(self.iterations);
+        
+        // Fill memory with complex operations
+        for i in self.iteration..stop {
+            let memory = &self.memory;
+            let dependent = options.variant.is_data_dependent(i, self.iterations);
+            let (block, next_hash) = fill_step(algorithm, i, &self.current_hash, memory.len(), dependent, |index| {
+                trace(index);
+                memory[index].clone()
+            });
+            
+            // Store in memory
+            self.memory.push(block);
+            self.current_hash = next_hash;
+        }
+
+        self.iteration = self.iteration.max(stop);
+    }
+
+    /// Complete the fill and any further passes, returning the output
+    pub fn finish(&mut self, options: &HashOptions) -> Vec<u8> {
+        self.run(self.iterations, options);
+        self.run_extra_passes(options);
+        self.output_len(options.worker_output_len)
+    }
+
+    /// Rewrite the filled memory `options.passes - 1` more times
+    /// 
+    /// Each block becomes the digest of the chaining value, a reference
+    /// block and its own previous content; the result is also the new
+    /// chaining value. The reference is half the memory away, or chosen by
+    /// the chaining value for data-dependent variants.
+    fn run_extra_passes(&mut self, options: &HashOptions) {
+        self.run_extra_passes_traced(options, &mut |_| {});
+    }
+
+    /// `run_extra_passes`, reporting the index of every block read to `trace`
+    pub(crate) fn run_extra_passes_traced(&mut self, options: &HashOptions, trace: &mut impl FnMut(usize)) {
+        let len = self.memory.len();
+        if len == 0 {
+            return;
+        }
+        let dependent = options.variant != Variant::Independent;
+
+        for _ in 1..options.passes {
+            for i in 0..len {
+                let reference = if dependent {
+                    let mut address = [0u8; 8];
+                    address.copy_from_slice(&self.current_hash[..8]);
+                    (u64::from_le_bytes(address) % len as u64) as usize
+                } else {
+                    (i + len / 2) % len
+                };
+                trace(reference);
+                trace(i);
+                self.current_hash = digest(options.algorithm, &[&self.current_hash, &self.memory[reference], &self.memory[i]]);
+                self.memory[i].copy_from_slice(&self.current_hash);
             }
         }
+    }
+
+    /// The worker output: the last `WORKER_OUTPUT_LEN` bytes of memory
+    pub fn output(&self) -> Vec<u8> {
+        self.output_len(WORKER_OUTPUT_LEN)
+    }
+
+    /// The last `len` bytes of memory, padded with the final hash when
+    /// memory is smaller
+    pub fn output_len(&self, len: usize) -> Vec<u8> {
+        // We take the last len / 32 entries (16 * 32 = 512 bytes by default)
+        let blocks = len.div_ceil(32);
+        let mut result = Vec::with_capacity(len);
+        let start_index = self.memory.len().saturating_sub(blocks);
         
-        // Byte rotation for additional complexity
-        if i % 100 == 0 {
-            current_hash.rotate_left((i % 16) + 1);
+        for chunk in &self.memory[start_index..] {
+            result.extend_from_slice(chunk);
         }
         
-        // Store in memory
-        memory.push(current_hash.clone());
-        
-        // Periodic mixing with distant memory locations
-        if i > 1000 && i % 500 == 0 {
-            let distant_index = (i / 2) % memory.len();
-            let mut hasher = Sha256::new();
-            hasher.update(&current_hash);
-            hasher.update(&memory[distant_index]);
-            current_hash = hasher.finalize().to_vec();
+        // Pad with final hash if needed
+        while result.len() < len {
+            result.extend_from_slice(&self.current_hash);
         }
+        
+        result.truncate(len);
+        debug_assert_eq!(result.len(), len);
+        result
     }
+}
+
+/// Number of 32-byte blocks filled for `memory_size` bytes
+/// 
+/// At least one: with no block at all the output would be the seed digest
+/// repeated, skipping the fill entirely. Sizes below 32 bytes only arise
+/// through the byte-sized worker functions; `HashParams` always asks for
+/// whole megabytes.
+pub(crate) fn block_count(memory_size: usize) -> usize {
+    (memory_size / 32).max(1)
+}
+
+/// One iteration of the memory fill
+/// 
+/// `filled` is the number of blocks already stored and `read` returns an
+/// earlier block by index. Every block read has an index below `i`.
+/// `dependent` selects a data-dependent address for the XOR read.
+/// Returns the block to store at index `i` and the next chaining value.
+fn fill_step(
+    algorithm: HashAlgo,
+    i: usize,
+    current_hash: &[u8],
+    filled: usize,
+    dependent: bool,
+    mut read: impl FnMut(usize) -> Vec<u8>,
+) -> (Vec<u8>, Vec<u8>) {
+    // Hash chaining
+    let mut current_hash = digest(algorithm, &[current_hash, &i.to_le_bytes()]);
     
-    // Return last 512 bytes
-    // We take the last 16 entries (16 * 32 = 512 bytes)
-    let mut result = Vec::with_capacity(512);
-    let start_index = if memory.len() > 16 { memory.len() - 16 } else { 0 };
+    // XOR mixing with previous data (if available)
+    if i > 0 {
+        let index = if dependent {
+            let mut address = [0u8; 8];
+            address.copy_from_slice(&current_hash[..8]);
+            (u64::from_le_bytes(address) % filled as u64) as usize
+        } else {
+            i % filled
+        };
+        let previous = read(index);
+        for (j, byte) in current_hash.iter_mut().enumerate() {
+            *byte ^= previous[j % 32];
+        }
+    }
     
-    for chunk in &memory[start_index..] {
-        result.extend_from_slice(chunk);
+    // Byte rotation for additional complexity
+    if i.is_multiple_of(100) {
+        current_hash.rotate_left((i % 16) + 1);
     }
     
-    // Pad with final hash if needed
-    while result.len() < 512 {
-        result.extend_from_slice(&current_hash);
+    let block = current_hash.clone();
+    
+    // Periodic mixing with distant memory locations
+    // (counting the block just produced, as it is stored before this read)
+    if i > 1000 && i.is_multiple_of(500) {
+        let distant = read((i / 2) % (filled + 1));
+        current_hash = digest(algorithm, &[&current_hash, &distant]);
     }
     
-    result.truncate(512);
-    result
+    (block, current_hash)
+}
+
+/// Number of iterations between stored chaining values in low-memory mode
+const LOW_MEMORY_INTERVAL: usize = 64;
+
+/// Low-memory variant of `thread_worker_with` producing identical output
+/// for `Variant::Independent` (other variants are not supported here)
+/// 
+/// Instead of every block, only the chaining value at every 64th iteration,
+/// the first block and the last 16 blocks are kept, cutting peak memory
+/// roughly 64-fold. Blocks needed by back-references are recomputed from the
+/// nearest stored chaining value, which costs some extra hashing.
+/// 
+/// # Panics
+/// If the checkpoint buffer cannot be allocated.
+pub fn thread_worker_low_memory(
+    password: &str,
+    salt: &[u8],
+    thread_index: usize,
+    memory_size: usize,
+    options: &HashOptions,
+) -> Vec<u8> {
+    low_memory_output(password, salt, thread_index, memory_size, options).expect("failed to allocate worker memory")
+}
+
+/// Fallible body of `thread_worker_low_memory`
+fn low_memory_output(
+    password: &str,
+    salt: &[u8],
+    thread_index: usize,
+    memory_size: usize,
+    options: &HashOptions,
+) -> Result<Vec<u8>> {
+    let algorithm = options.algorithm;
+    let seed = WorkerState::try_new(password, salt, thread_index, 0, options)?;
+    let iterations = block_count(memory_size);
+
+    let mut fill = SparseFill {
+        algorithm,
+        checkpoints: Vec::new(),
+        first_block: Vec::new(),
+    };
+    fill.checkpoints
+        .try_reserve_exact(iterations / LOW_MEMORY_INTERVAL + 1)
+        .map_err(|_| SinkproofError::OutOfMemory)?;
+    let mut current_hash = seed.current_hash.clone();
+    let output_blocks = options.worker_output_len.div_ceil(32);
+    let mut tail: VecDeque<Vec<u8>> = VecDeque::with_capacity(output_blocks + 1);
+
+    for i in 0..iterations {
+        if i % LOW_MEMORY_INTERVAL == 0 {
+            fill.checkpoints.push(current_hash.clone());
+        }
+
+        let (block, next_hash) = fill_step(algorithm, i, &current_hash, i, false, |index| {
+            match (i - index).checked_sub(1) {
+                Some(back) if back < tail.len() => tail[tail.len() - 1 - back].clone(),
+                _ => fill.block(index),
+            }
+        });
+
+        if i == 0 {
+            fill.first_block = block.clone();
+        }
+        tail.push_back(block);
+        if tail.len() > output_blocks {
+            tail.pop_front();
+        }
+        current_hash = next_hash;
+    }
+
+    let state = WorkerState {
+        iteration: iterations,
+        iterations,
+        current_hash,
+        memory: tail.into(),
+    };
+    Ok(state.output_len(options.worker_output_len))
+}
+
+/// Sparse record of a memory fill used by the low-memory worker
+struct SparseFill {
+    algorithm: HashAlgo,
+    /// Chaining value before iteration `n * LOW_MEMORY_INTERVAL`
+    checkpoints: Vec<Vec<u8>>,
+    /// Block 0, read by every iteration's XOR step
+    first_block: Vec<u8>,
+}
+
+impl Drop for SparseFill {
+    fn drop(&mut self) {
+        self.checkpoints.zeroize();
+        self.first_block.zeroize();
+    }
+}
+
+impl SparseFill {
+    /// Recompute block `target` from the nearest checkpoint at or before it
+    fn block(&self, target: usize) -> Vec<u8> {
+        if target == 0 && !self.first_block.is_empty() {
+            return self.first_block.clone();
+        }
+
+        let start = target - target % LOW_MEMORY_INTERVAL;
+        let mut current_hash = self.checkpoints[start / LOW_MEMORY_INTERVAL].clone();
+        let mut block = Vec::new();
+
+        for i in start..=target {
+            let (next_block, next_hash) = fill_step(self.algorithm, i, &current_hash, i, false, |index| self.block(index));
+            block = next_block;
+            current_hash = next_hash;
+        }
+
+        block
+    }
 }
 
 /// Derive encryption key from thread outputs
-/// Combines all thread outputs and hashes them to create a 32-byte key
-pub fn derive_key(thread_outputs: &[Vec<u8>]) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    
-    // Hash all thread outputs together
-    for output in thread_outputs {
-        hasher.update(output);
+/// Combines all thread outputs and hashes them to create a 32-byte key,
+/// zeroed when dropped
+pub fn derive_key(thread_outputs: &[Vec<u8>]) -> Zeroizing<Vec<u8>> {
+    derive_key_with(thread_outputs, &HashOptions::v1())
+}
+
+/// Derive the key from arbitrary worker outputs with the default options
+/// and the given combiner
+/// 
+/// Lets tests feed known vectors to a combiner without running the memory
+/// fill. Outputs may have any length and count:
+/// 
+/// * no outputs: both combiners return SHA-256 of the empty string
+/// * one output: `Concat` returns SHA-256 of it, `Merkle` its leaf hash,
+///   SHA-256 of `0x00` followed by the output
+/// * many outputs: as described on `Combiner`
+/// 
+/// Real hashes always have at least one worker output.
+pub fn derive_key_from(outputs: &[Vec<u8>], combiner: Combiner) -> Zeroizing<Vec<u8>> {
+    derive_key_with(outputs, &HashOptions { combiner, ..HashOptions::v1() })
+}
+
+/// Derive the encryption key using the algorithm recorded in `options`
+/// 
+/// Empty and single-output inputs behave as documented on `derive_key_from`.
+pub fn derive_key_with(thread_outputs: &[Vec<u8>], options: &HashOptions) -> Zeroizing<Vec<u8>> {
+    Zeroizing::new(match options.combiner {
+        Combiner::Concat => {
+            // Hash all thread outputs together
+            let parts: Vec<&[u8]> = thread_outputs.iter().map(Vec::as_slice).collect();
+            match options.key_digest {
+                KeyDigest::Algorithm => digest(options.algorithm, &parts),
+                KeyDigest::Sha512 => sha512(&parts),
+            }
+        }
+        Combiner::Merkle => {
+            // The tree keeps the fill algorithm; a wide key hashes its root
+            let root = merkle_root(options.algorithm, thread_outputs);
+            match options.key_digest {
+                KeyDigest::Algorithm => root,
+                KeyDigest::Sha512 => sha512(&[&root]),
+            }
+        }
+    })
+}
+
+/// SHA-512 of the concatenation of `parts` (64 bytes)
+fn sha512(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
     }
-    
     hasher.finalize().to_vec()
 }
 
+/// Root of a binary Merkle tree over `leaves`
+/// 
+/// Leaves are hashed with a `0x00` prefix and inner nodes with `0x01`; a
+/// node without a sibling moves up to the next level unchanged.
+fn merkle_root(algorithm: HashAlgo, leaves: &[Vec<u8>]) -> Vec<u8> {
+    let mut level: Vec<Vec<u8>> = leaves
+        .iter()
+        .map(|leaf| digest(algorithm, &[&[0x00], leaf]))
+        .collect();
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => digest(algorithm, &[&[0x01], left, right]),
+                [single] => single.clone(),
+                _ => unreachable!("chunks(2) yields one or two nodes"),
+            })
+            .collect();
+    }
+
+    level.pop().unwrap_or_else(|| digest(algorithm, &[]))
+}
+
+/// Hash the concatenation of `parts` with the selected algorithm
+/// 
+/// Both algorithms produce 32 bytes.
+fn digest(algorithm: HashAlgo, parts: &[&[u8]]) -> Vec<u8> {
+    match algorithm {
+        HashAlgo::Sha256 => {
+            let mut hasher = Sha256::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().to_vec()
+        }
+        #[cfg(all(feature = "blake3", not(feature = "fips")))]
+        HashAlgo::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().as_bytes().to_vec()
+        }
+        #[cfg(not(all(feature = "blake3", not(feature = "fips"))))]
+        HashAlgo::Blake3 => unreachable!("algorithm availability is checked before hashing"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::params::NormalizationForm;
+    use crate::storage::test_phrase;
+    #[cfg(feature = "hashing")]
+    use crate::storage::with_checksum;
+    use crate::verifier::verify_password;
+
+    #[test]
+    fn test_passes() {
+        let salt = [7u8; 16];
+        let key = |params: HashParams| compute_key("password", &salt, &params).unwrap();
+
+        let one = key(HashParams::new(2, 1));
+        assert_eq!(key(HashParams::new(2, 1).passes(1)), one);
+        let two = key(HashParams::new(2, 1).passes(2));
+        let three = key(HashParams::new(2, 1).passes(3));
+        assert_ne!(two, one);
+        assert_ne!(three, two);
+        assert_ne!(key(HashParams::new(2, 1).passes(2).variant(Variant::Dependent)), two);
+
+        assert_eq!(HashParams::new(1, 1).passes(0).validate(), Err(SinkproofError::InvalidPasses));
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_passes_roundtrip_through_verification() {
+        let params = HashParams::new(1, 1).passes(2);
+        let stored = hash_password_with("password", &params).unwrap().to_string();
+
+        assert!(stored.contains("pass=2"));
+        assert_eq!(stored.parse::<SinkproofHash>().unwrap().params().options.passes, 2);
+        assert!(verify_password("password", &stored).unwrap());
+        assert!(!verify_password("wrong", &stored).unwrap());
+
+        // The pass count is part of the key: a hash claiming one pass fails
+        let (body, _) = stored.rsplit_once(':').unwrap();
+        assert!(!verify_password("password", &with_checksum(&body.replace(",pass=2", ""))).unwrap());
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_worker_output_len_roundtrip() {
+        let salt = [7u8; 32];
+        for len in [256, 512, 1024] {
+            let params = HashParams::new(2, 1).worker_output_len(len);
+            let stored = hash_password_with("password", &params).unwrap().to_string();
+
+            assert_eq!(stored.contains(&format!("out={}", len)), len != WORKER_OUTPUT_LEN, "{}", stored);
+            assert_eq!(stored.parse::<SinkproofHash>().unwrap().params().options.worker_output_len, len);
+            assert!(verify_password("password", &stored).unwrap(), "{}", len);
+            assert!(!verify_password("wrong", &stored).unwrap(), "{}", len);
+
+            let options = &HashOptions { variant: Variant::Independent, ..params.options.clone() };
+            assert_eq!(thread_worker_with("test", &salt, 0, 1024 * 1024, options).len(), len);
+            assert_eq!(
+                thread_worker_low_memory("test", &salt, 0, 1024 * 1024, options),
+                thread_worker_with("test", &salt, 0, 1024 * 1024, options),
+            );
+        }
+
+        let key = |len| hash_with_salt_and_key("password", vec![1u8; 32], &HashParams::new(1, 1).worker_output_len(len));
+        assert_ne!(key(1024).unwrap().1, key(512).unwrap().1);
+
+        for len in [0, 100, MAX_WORKER_OUTPUT_LEN + 32, usize::MAX] {
+            assert!(matches!(
+                HashParams::new(1, 1).worker_output_len(len).validate(),
+                Err(SinkproofError::InvalidConfig(_))
+            ));
+        }
+        let stored = hash_password_with("password", &HashParams::new(1, 1).worker_output_len(1024)).unwrap().to_string();
+        let (body, _) = stored.rsplit_once(':').unwrap();
+        for absurd in ["out=100", "out=1048576", "out=-32", "out=0"] {
+            let tampered = body.replace("out=1024", absurd);
+            assert!(matches!(tampered.parse::<SinkproofHash>(), Err(SinkproofError::InvalidFormat(_))), "{}", absurd);
+        }
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_combiners() {
+        let outputs: Vec<Vec<u8>> = (0u8..5).map(|i| vec![i; 512]).collect();
+        let with = |combiner| derive_key_with(&outputs, &HashParams::new(5, 1).combiner(combiner).options);
+
+        assert_eq!(with(Combiner::Concat), derive_key(&outputs));
+        assert_eq!(with(Combiner::Merkle), with(Combiner::Merkle));
+        assert_ne!(with(Combiner::Merkle), with(Combiner::Concat));
+        assert_eq!(with(Combiner::Merkle).len(), 32);
+
+        let stored = hash_password_with("password", &HashParams::new(3, 1).combiner(Combiner::Merkle))
+            .unwrap()
+            .to_string();
+        assert!(stored.contains("comb=merkle"));
+        assert!(verify_password("password", &stored).unwrap());
+        assert!(!verify_password("wrong", &stored).unwrap());
+    }
 
+    #[test]
+    fn test_derive_key_from_edge_cases() {
+        let empty_digest = Sha256::digest(b"").to_vec();
+        assert_eq!(*derive_key_from(&[], Combiner::Concat), empty_digest);
+        assert_eq!(*derive_key_from(&[], Combiner::Merkle), empty_digest);
+
+        let single = vec![b"only output".to_vec()];
+        assert_eq!(*derive_key_from(&single, Combiner::Concat), Sha256::digest(b"only output").to_vec());
+        assert_eq!(*derive_key_from(&single, Combiner::Merkle), Sha256::digest(b"\x00only output").to_vec());
+
+        // Three leaves: the third is carried up and joined at the root
+        let many: Vec<Vec<u8>> = vec![b"a".to_vec(), b"bc".to_vec(), Vec::new()];
+        let leaf = |data: &[u8]| Sha256::digest([&[0x00], data].concat()).to_vec();
+        let node = |left: &[u8], right: &[u8]| Sha256::digest([&[0x01], left, right].concat()).to_vec();
+        let expected = node(&node(&leaf(b"a"), &leaf(b"bc")), &leaf(b""));
+        assert_eq!(*derive_key_from(&many, Combiner::Merkle), expected);
+        assert_eq!(*derive_key_from(&many, Combiner::Concat), Sha256::digest(b"abc").to_vec());
+        assert_eq!(derive_key_from(&many, Combiner::Concat), derive_key(&many));
+    }
+
+    #[test]
+    fn test_derive_app_key() {
+        let params = HashParams::new(1, 1);
+        let key = derive_app_key("passphrase", b"app salt", &params, 48).unwrap();
+
+        assert_eq!(key.len(), 48);
+        assert_eq!(derive_app_key("passphrase", b"app salt", &params, 48).unwrap(), key);
+        assert_ne!(derive_app_key("passphrase", b"other salt", &params, 48).unwrap(), key);
+        assert_ne!(derive_app_key("passphrase2", b"app salt", &params, 48).unwrap(), key);
+        assert_ne!(&compute_key("passphrase", b"app salt", &params).unwrap()[..], &key[..32]);
+
+        assert!(matches!(derive_app_key("passphrase", b"app salt", &params, 0), Err(SinkproofError::InvalidConfig(_))));
+        assert!(derive_app_key("passphrase", b"app salt", &params, MAX_APP_KEY_LEN + 1).is_err());
+        assert!(derive_app_key("passphrase", b"app salt", &HashParams::new(0, 1), 32).is_err());
+    }
+
+    #[test]
+    fn test_pathological_sizes_fail_cleanly() {
+        let salt = [1u8; 16];
+        for params in [
+            // Reservation larger than the address space
+            HashParams::new(1, 1 << 40),
+            HashParams::new(1, 1 << 40).variant(Variant::Independent).low_memory(true),
+            // Byte count overflows usize
+            HashParams::new(1, usize::MAX / 2),
+        ] {
+            assert!(matches!(
+                compute_key("password", &salt, &params),
+                Err(SinkproofError::MemoryLimitExceeded { .. })
+            ));
+        }
+        // Builds with a raised limit still fail cleanly
+        assert_eq!(HashParams::new(1, usize::MAX / 2).thread_memory_bytes(0), Err(SinkproofError::OutOfMemory));
+        let options = HashOptions::v1();
+        assert!(matches!(
+            WorkerState::try_new("password", &salt, 0, 1 << 60, &options),
+            Err(SinkproofError::OutOfMemory)
+        ));
+
+        // Absurd thread counts stop at the build's limit before allocating
+        assert!(matches!(
+            compute_key("password", &salt, &HashParams::new(usize::MAX / 4, 1)),
+            Err(SinkproofError::ThreadLimitExceeded { .. })
+        ));
+        assert_eq!(thread_indices(usize::MAX / 4), Err(SinkproofError::OutOfMemory));
+
+        let stored = format!(concat!("Sinkproof:v1:1:{}:AQID:", test_phrase!()), 1usize << 40);
+        assert!(matches!(verify_password("password", &stored), Err(SinkproofError::MemoryLimitExceeded { .. })));
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_spawn_failure_returns_error() {
+        // Two threads start, the third spawn fails
+        SPAWN_LIMIT.with(|limit| limit.set(Some(2)));
+        let result = hash_password("password", 4, 4);
+        SPAWN_LIMIT.with(|limit| limit.set(None));
+
+        assert!(matches!(result, Err(SinkproofError::ThreadSpawn(_))), "{:?}", result);
+        assert!(hash_password("password", 4, 4).is_ok());
+    }
+
+    #[cfg(feature = "hashing")]
     #[test]
     fn test_salt_generation() {
         let salt1 = generate_salt();
@@ -185,6 +1104,134 @@ mod tests {
         assert_eq!(output1, output2); // Same inputs should produce same output
     }
 
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_generate_salt_combined() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let single = |seed| generate_salt_combined(&mut [&mut StdRng::seed_from_u64(seed)], 32);
+        let a = single(1);
+        let b = single(2);
+        let expected: Vec<u8> = a.iter().zip(&b).map(|(x, y)| x ^ y).collect();
+
+        let combined = generate_salt_combined(&mut [&mut StdRng::seed_from_u64(1), &mut StdRng::seed_from_u64(2)], 32);
+        assert_eq!(combined, expected);
+        assert_eq!(generate_salt_combined(&mut [&mut rand::rngs::OsRng], 16).len(), 16);
+        assert!(std::panic::catch_unwind(|| generate_salt_combined(&mut [], 32)).is_err());
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_sha512_key_digest() {
+        let salt = [6u8; 16];
+        let params = HashParams::new(2, 1).key_digest(KeyDigest::Sha512);
+        let key = compute_key("password", &salt, &params).unwrap();
+        assert_eq!(key.len(), 64);
+        assert_eq!(key.len(), params.options.key_digest.key_len());
+        assert_ne!(key[..32], compute_key("password", &salt, &HashParams::new(2, 1)).unwrap()[..]);
+
+        let merkle = compute_key("password", &salt, &params.clone().combiner(Combiner::Merkle)).unwrap();
+        assert_eq!(merkle.len(), 64);
+        assert_ne!(merkle, key);
+
+        let stored = hash_password_with("password", &params).unwrap().to_string();
+        assert!(stored.contains("kd=sha512"));
+        assert!(verify_password("password", &stored).unwrap());
+        assert!(!verify_password("wrong", &stored).unwrap());
+
+        // The digest choice is part of the key: a hash claiming the default fails
+        let (body, _) = stored.rsplit_once(':').unwrap();
+        assert!(!verify_password("password", &with_checksum(&body.replace(",kd=sha512", ""))).unwrap());
+    }
+
+    #[test]
+    fn test_memory_fill_is_not_suspiciously_fast() {
+        // Every 32-byte block of the fill costs at least one SHA-256
+        // compression plus mixing, well over 100 ns even with SHA
+        // instructions; the floor allows 10 ns per block, so only a fill
+        // that stopped doing per-block work (e.g. mixing optimized away or
+        // the loop short-circuited) can go under it, whatever the machine
+        const MEMORY_MB: usize = 8;
+        const FLOOR_NS_PER_BLOCK: u128 = 10;
+        let blocks = (MEMORY_MB * 1024 * 1024 / 32) as u128;
+
+        let salt = [5u8; 16];
+        let params = HashParams::new(1, MEMORY_MB).variant(Variant::Independent);
+        let start = std::time::Instant::now();
+        let key = compute_key("password", &salt, &params).unwrap();
+        let elapsed = start.elapsed();
+
+        assert!(
+            elapsed.as_nanos() >= blocks * FLOOR_NS_PER_BLOCK,
+            "{} MB fill took only {:?}",
+            MEMORY_MB,
+            elapsed
+        );
+
+        // The time must buy work that the key depends on
+        let output = thread_worker("password", &salt, 0, MEMORY_MB * 1024 * 1024);
+        assert_eq!(derive_key(&[output]), key);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_warmup_is_idempotent_and_transparent() {
+        let salt = [4u8; 16];
+        let params = HashParams::new(1, 1);
+        let before = compute_key("password", &salt, &params).unwrap();
+
+        warmup();
+        assert!(is_warm());
+        warmup();
+
+        assert_eq!(compute_key("password", &salt, &params).unwrap(), before);
+        let stored = hash_password_with_salt("password", &salt, &params).unwrap().to_string();
+        assert!(verify_password("password", &stored).unwrap());
+    }
+
+    #[test]
+    fn test_would_collide() {
+        let salt = [9u8; 16];
+        let params = HashParams::new(1, 1);
+
+        assert!(would_collide("password", "password", &salt, &params).unwrap());
+        assert!(!would_collide("password", "passwore", &salt, &params).unwrap());
+
+        // Normalization makes canonically equivalent spellings collide
+        let nfc = params.clone().normalize_unicode(NormalizationForm::Nfc);
+        assert!(!would_collide("\u{f1}", "n\u{303}", &salt, &params).unwrap());
+        assert!(would_collide("\u{f1}", "n\u{303}", &salt, &nfc).unwrap());
+
+        assert!(would_collide("a", "a", &salt, &HashParams::new(0, 1)).is_err());
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_password_whitespace_is_significant() {
+        let stored = hash_password_with("password ", &HashParams::new(1, 1)).unwrap().to_string();
+
+        assert!(verify_password("password ", &stored).unwrap());
+        assert!(!verify_password("password", &stored).unwrap());
+        assert!(!verify_password(" password ", &stored).unwrap());
+    }
+
+    #[test]
+    fn test_worker_output_length_for_all_sizes() {
+        let salt = [3u8; 16];
+        let passes = HashOptions { passes: 2, ..HashOptions::v1() };
+        let sizes = [0, 1, 31, 32, 33, 64, 480, 511, 512, 513, 544, 1024, 4096, 65_537, 1 << 20, 3 << 20];
+
+        for size in sizes {
+            assert_eq!(thread_worker("test", &salt, 0, size).len(), WORKER_OUTPUT_LEN, "{}", size);
+            let low_memory = thread_worker_low_memory("test", &salt, 0, size, &HashOptions::v1());
+            assert_eq!(low_memory.len(), WORKER_OUTPUT_LEN, "{}", size);
+            if size <= 4096 {
+                assert_eq!(thread_worker_with("test", &salt, 0, size, &passes).len(), WORKER_OUTPUT_LEN, "{}", size);
+            }
+        }
+    }
+
     #[test]
     fn test_different_thread_index_produces_different_output() {
         let password = "test";
@@ -197,6 +1244,7 @@ mod tests {
         assert_ne!(output1, output2);
     }
 
+    #[cfg(feature = "hashing")]
     #[test]
     fn test_hash_password_success() {
         let result = hash_password("test_password", 2, 5);
@@ -209,9 +1257,216 @@ mod tests {
         assert!(!hash.encrypted_phrase.is_empty());
     }
 
+    #[test]
+    fn test_default_options_match_v1_worker() {
+        let salt = vec![1, 2, 3, 4];
+        let output = thread_worker_with("test", &salt, 0, 4096, &HashOptions::v1());
+        assert_eq!(output, thread_worker("test", &salt, 0, 4096));
+    }
+
+    #[cfg(all(feature = "blake3", not(feature = "fips")))]
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_blake3_hash_uses_v2() {
+        let params = HashParams::new(1, 1).algorithm(HashAlgo::Blake3);
+        let hash = hash_password_with("test_password", &params).expect("Failed to hash");
+
+        assert_eq!(hash.version, crate::storage::HashVersion::V2);
+        assert_eq!(hash.options.algorithm, HashAlgo::Blake3);
+
+        let salt = vec![1, 2, 3, 4];
+        let sha = thread_worker("test", &salt, 0, 4096);
+        let blake = thread_worker_with("test", &salt, 0, 4096, &params.options);
+        assert_ne!(sha, blake);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_default_fill_reads_hash_derived_addresses() {
+        let salt = [3u8; 32];
+        let params = HashParams::new(1, 1);
+        assert_eq!(params.options.variant, Variant::Hybrid);
+
+        // Reproducible, but different from the v1 fill
+        let key = compute_key("password", &salt, &params).unwrap();
+        assert_eq!(key, compute_key("password", &salt, &params).unwrap());
+        let v1 = HashParams { options: HashOptions::v1(), ..params.clone() };
+        assert_ne!(key, compute_key("password", &salt, &v1).unwrap());
+
+        // The variant is recorded, and v1 hashes still verify
+        let stored = hash_password_with("password", &params).unwrap().to_string();
+        assert!(stored.starts_with("Sinkproof:v2:1:1:") && stored.contains("var=id"), "{}", stored);
+        assert!(verify_password("password", &stored).unwrap());
+        let stored_v1 = hash_password_with("password", &v1).unwrap().to_string();
+        assert!(stored_v1.starts_with("Sinkproof:v1:1:1:"), "{}", stored_v1);
+        assert!(verify_password("password", &stored_v1).unwrap());
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_variants_are_deterministic_and_verify() {
+        let salt = generate_salt();
+        let mut keys = Vec::new();
+
+        for variant in [Variant::Independent, Variant::Dependent, Variant::Hybrid] {
+            let params = HashParams::new(1, 1).variant(variant);
+            let key = compute_key("password", &salt, &params).expect("Failed to hash");
+            assert_eq!(key, compute_key("password", &salt, &params).expect("Failed to hash"));
+            keys.push(key);
+
+            let stored = hash_password_with("password", &params).expect("Failed to hash").to_string();
+            assert!(verify_password("password", &stored).expect("Failed to verify"));
+            assert!(!verify_password("wrong", &stored).expect("Failed to verify"));
+        }
+
+        // New hashes default to the hybrid fill; the v1 fill stays available
+        assert_eq!(keys[2], compute_key("password", &salt, &HashParams::new(1, 1)).unwrap());
+        let v1_options = HashParams { options: HashOptions::v1(), ..HashParams::new(1, 1) };
+        assert_eq!(keys[0], compute_key("password", &salt, &v1_options).unwrap());
+        assert_ne!(keys[0], keys[1]);
+        assert_ne!(keys[1], keys[2]);
+        assert_ne!(keys[0], keys[2]);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_normalized_passwords_hash_identically() {
+        let precomposed = "contrase\u{00F1}a";
+        let decomposed = "contrase\u{006E}\u{0303}a";
+        let salt = generate_salt();
+
+        let plain = HashParams::new(1, 1);
+        assert_ne!(
+            compute_key(precomposed, &salt, &plain).unwrap(),
+            compute_key(decomposed, &salt, &plain).unwrap()
+        );
+
+        let normalized = HashParams::new(1, 1).normalize_unicode(NormalizationForm::Nfc);
+        assert_eq!(
+            compute_key(precomposed, &salt, &normalized).unwrap(),
+            compute_key(decomposed, &salt, &normalized).unwrap()
+        );
+
+        let stored = hash_password_with(precomposed, &normalized).expect("Failed to hash").to_string();
+        assert!(verify_password(decomposed, &stored).expect("Failed to verify"));
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_hash_password_string() {
+        let stored = hash_password_string("password", 2, 1).expect("Failed to hash");
+        assert!(verify_password("password", &stored).expect("Failed to verify"));
+
+        let parsed = stored.parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!((parsed.threads, parsed.memory_mb), (2, 1));
+
+        let params = HashParams::new(1, 1).variant(Variant::Hybrid);
+        let stored = hash_password_string_with("password", &params).expect("Failed to hash");
+        assert_eq!(stored.parse::<SinkproofHash>().unwrap().params(), params);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_per_thread_memory_hashes_and_verifies() {
+        let params = HashParams::new(3, 1).per_thread_memory(vec![2, 1, 3]);
+        let stored = hash_password_with("password", &params).expect("Failed to hash").to_string();
+
+        let parsed = stored.parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!(parsed.options.per_thread_memory, Some(vec![2, 1, 3]));
+        assert!(verify_password("password", &stored).expect("Failed to verify"));
+        assert!(!verify_password("wrong", &stored).expect("Failed to verify"));
+
+        // The sizes change the key
+        let salt = generate_salt();
+        assert_ne!(
+            compute_key("password", &salt, &params).unwrap(),
+            compute_key("password", &salt, &HashParams::new(3, 1).per_thread_memory(vec![1, 2, 3])).unwrap()
+        );
+
+        let mismatched = HashParams::new(2, 1).per_thread_memory(vec![1]);
+        assert_eq!(
+            hash_password_with("password", &mismatched).unwrap_err(),
+            SinkproofError::PerThreadMemoryMismatch { threads: 2, sizes: 1 }
+        );
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_custom_thread_stack_size() {
+        let params = HashParams::new(2, 1).thread_stack_size(Some(256 * 1024));
+        let hash = hash_password_with("test_password", &params).expect("Failed to hash");
+
+        assert!(crate::verifier::verify_password("test_password", &hash.to_string()).expect("Failed to verify"));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_caller_supplied_pool() {
+        // Three workers on a single pool thread give the same hash
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let params = HashParams::new(3, 1);
+        let hash = pool.install(|| hash_password_with("test_password", &params)).expect("Failed to hash");
+
+        assert!(crate::verifier::verify_password("test_password", &hash.to_string()).expect("Failed to verify"));
+        assert!(!pool.install(|| crate::verifier::verify_password("wrong", &hash.to_string())).unwrap());
+    }
+
+    #[test]
+    fn test_memory_smaller_than_one_block() {
+        let salt = vec![1, 2, 3, 4];
+        let output = thread_worker("test", &salt, 0, 16);
+
+        // One block is filled, as for a full 32 bytes
+        assert_eq!(output.len(), WORKER_OUTPUT_LEN);
+        assert_eq!(output, thread_worker("test", &salt, 0, 16));
+        assert_eq!(output, thread_worker("test", &salt, 0, 32));
+        assert_ne!(output, thread_worker("test", &[5, 6, 7, 8], 0, 16));
+
+        // Not just the seed digest repeated
+        let seed = digest(HashAlgo::Sha256, &[b"test", &salt, &0usize.to_le_bytes()]);
+        assert_ne!(&output[..32], &seed[..]);
+        assert_ne!(&output[output.len() - 32..], &seed[..]);
+    }
+
+    #[test]
+    fn test_low_memory_worker_matches_full_memory() {
+        let salt = vec![1, 2, 3, 4];
+        let options = HashOptions::v1();
+
+        // Cover tiny fills, partial tails and several distant-mixing steps
+        for memory_size in [0, 16, 32, 96, 1024, 16 * 1024, 256 * 1024] {
+            assert_eq!(
+                thread_worker_low_memory("test", &salt, 3, memory_size, &options),
+                thread_worker_with("test", &salt, 3, memory_size, &options),
+                "mismatch for memory_size {}",
+                memory_size
+            );
+        }
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_low_memory_keys_match() {
+        let salt = generate_salt();
+        let params = HashParams::new(2, 1).variant(Variant::Independent);
+        let full = compute_key("password", &salt, &params).expect("Failed to hash");
+        let low = compute_key("password", &salt, &params.clone().low_memory(true)).expect("Failed to hash");
+
+        assert_eq!(full, low);
+
+        // Other variants and multi-pass fills cannot be recomputed from checkpoints
+        for params in [HashParams::new(2, 1), HashParams::new(2, 1).variant(Variant::Independent).passes(2)] {
+            let params = params.low_memory(true);
+            assert!(matches!(params.validate(), Err(SinkproofError::InvalidConfig(_))));
+            assert!(matches!(compute_key("password", &salt, &params), Err(SinkproofError::InvalidConfig(_))));
+        }
+    }
+
+    #[cfg(feature = "hashing")]
     #[test]
     fn test_hash_password_invalid_params() {
-        assert!(hash_password("test", 0, 5).is_err());
-        assert!(hash_password("test", 2, 0).is_err());
+        assert_eq!(hash_password("test", 0, 5).unwrap_err(), SinkproofError::InvalidThreads);
+        assert_eq!(hash_password("test", 2, 0).unwrap_err(), SinkproofError::InvalidMemory);
     }
 }
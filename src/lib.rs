@@ -1,19 +1,83 @@
-/// Sinkproof v1 - Custom Password Hashing System
-/// 
-/// A memory-hard password hashing algorithm that uses multi-threading,
-/// complex mathematical operations, and encryption for password security.
+//! Sinkproof v1 - Custom Password Hashing System
+//! 
+//! A memory-hard password hashing algorithm that uses multi-threading,
+//! complex mathematical operations, and encryption for password security.
+//! 
+//! Passwords are taken byte-exactly: the library never trims whitespace or
+//! changes case, and only normalizes Unicode when asked to
+//! (`HashParams::normalize_unicode`). Callers that read passwords from
+//! lines of text should strip only the line terminator.
 
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "hashing")]
+pub mod checkpoint;
+#[cfg(feature = "hashing")]
+pub mod compare;
+mod compress;
+pub mod cost;
+pub mod error;
 pub mod hasher;
 pub mod encryption;
+pub mod format;
+#[cfg(test)]
+mod golden;
+pub mod params;
+pub mod pepper;
+pub mod policy;
+mod reader;
+#[cfg(feature = "profiling")]
+pub mod profile;
+pub mod report;
+pub mod rng;
+pub mod salt;
+#[cfg(feature = "seal")]
+pub mod seal;
+pub mod split;
 pub mod storage;
+#[cfg(feature = "hashing")]
+pub mod subprocess;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod token;
 pub mod verifier;
 
 // Re-export main public API
-pub use hasher::hash_password;
-pub use verifier::verify_password;
-pub use storage::SinkproofHash;
+#[cfg(feature = "hashing")]
+pub use hasher::{
+    hash_password, hash_password_string, hash_password_string_with, hash_password_with, hash_password_with_salt,
+};
+pub use hasher::{derive_app_key, is_warm, warmup};
+pub use cost::{attacker_cost, AttackerCost, Resistance};
+pub use verifier::{
+    verify_and_derive, verify_and_time, verify_detailed, verify_password, verify_password_min_time,
+    verify_password_with_limits, verify_password_with_phrase, verify_try_thread_range, VerifyResult,
+};
+pub use params::{
+    Combiner, HashAlgo, HashOptions, HashParams, HashParamsBuilder, KeyDigest, NormalizationForm, Variant, ENV_MEMORY_MB,
+    ENV_THREADS, MAX_MEMORY_MB, MAX_PASSES, MAX_THREADS, MAX_TOTAL_MEMORY_MB,
+};
+pub use pepper::{verify_password_peppered, PepperSource};
+pub use policy::{set_weak_hash_threshold, verify_with_policy, HashPolicy, PolicyOutcome};
+pub use report::{audit, AuditReport};
+pub use rng::{reset_rng, set_rng};
+pub use salt::SaltBuilder;
+pub use encryption::{fastest_cipher, Cipher};
+pub use format::{detect_format, ColonFormat, FormatVersion, HashSerializer, JsonFormat, ParsedHashFields, PhcFormat};
+pub use storage::{normalize_hash_string, wipe_string, HashVersion, ParseLimits, SinkproofHash};
+#[cfg(feature = "hashing")]
+pub use token::hash_password_with_token;
+pub use token::verify_token;
+#[cfg(feature = "hashing")]
+pub use split::hash_split;
+pub use split::verify_split;
+pub use error::{Result, SinkproofError};
+#[cfg(feature = "hashing")]
+pub use checkpoint::{hash_checkpoint, resume_hash, HashCheckpoint};
+#[cfg(feature = "hashing")]
+pub use compare::{compare_configs, BenchMetric, BenchResult};
 
-#[cfg(test)]
+#[cfg(all(test, feature = "hashing"))]
 mod tests {
     use super::*;
 
@@ -43,17 +107,31 @@ mod tests {
         assert!(verify_password(password, &hash2.to_string()).expect("Failed to verify"));
     }
 
+    #[test]
+    fn test_very_long_password() {
+        let password = "a".repeat(1024 * 1024);
+        let hash = hash_password(&password, 2, 1).expect("Failed to hash long password");
+
+        assert!(verify_password(&password, &hash.to_string()).expect("Failed to verify"));
+
+        // Differing only in the last byte must not verify
+        let mut wrong = password.clone();
+        wrong.pop();
+        wrong.push('b');
+        assert!(!verify_password(&wrong, &hash.to_string()).expect("Failed to verify"));
+    }
+
     #[test]
     fn test_storage_format() {
         let password = "test_password";
         let hash = hash_password(password, 4, 50).expect("Failed to hash");
         let stored = hash.to_string();
         
-        // Check format starts with Sinkproof:v1:
-        assert!(stored.starts_with("Sinkproof:v1:"));
+        // New hashes record their (hybrid) variant, so they are v2
+        assert!(stored.starts_with("Sinkproof:v2:"));
         
         // Parse it back
-        let parsed = SinkproofHash::from_string(&stored).expect("Failed to parse");
+        let parsed = stored.parse::<SinkproofHash>().expect("Failed to parse");
         assert_eq!(parsed.threads, 4);
         assert_eq!(parsed.memory_mb, 50);
     }
@@ -1,8 +1,20 @@
-use sinkproof::{hash_password, verify_password};
+use base64::{Engine as _, engine::general_purpose};
+use sinkproof::{hash_password, verify_password, ParsedHashFields, SinkproofHash};
+use sinkproof::params::parse_memory_mb;
+use sinkproof::subprocess::{run_worker, WORKER_FLAG};
 use std::time::Instant;
 use std::io::{self, Write};
 
 fn main() {
+    // Sandboxed worker mode used by `subprocess::hash_in_subprocess`
+    if std::env::args().nth(1).as_deref() == Some(WORKER_FLAG) {
+        if let Err(e) = run_worker(std::env::args().skip(2)) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     println!("=== Sinkproof v1 - Sistema de Hashing de Contraseñas ===\n");
     
     loop {
@@ -30,15 +42,31 @@ fn main() {
     }
 }
 
+/// Read a password line, keeping it verbatim except for the line ending
+/// 
+/// Spaces are part of the password, so they are never stripped; a warning
+/// is printed when the password starts or ends with whitespace.
+fn leer_contraseña() -> String {
+    let mut password = String::new();
+    io::stdin().read_line(&mut password).unwrap();
+    let password = password.strip_suffix('\n').unwrap_or(&password);
+    let password = password.strip_suffix('\r').unwrap_or(password);
+
+    if password.trim() != password {
+        println!("⚠️  La contraseña empieza o termina con espacios; se usarán tal cual.");
+    }
+
+    password.to_string()
+}
+
 fn generar_hash() {
     println!("\n--- Generar Hash de Contraseña ---");
     
     // Pedir contraseña
     print!("Ingrese la contraseña: ");
     io::stdout().flush().unwrap();
-    let mut password = String::new();
-    io::stdin().read_line(&mut password).unwrap();
-    let password = password.trim();
+    let password = leer_contraseña();
+    let password = password.as_str();
     
     if password.is_empty() {
         println!("❌ La contraseña no puede estar vacía.");
@@ -59,11 +87,11 @@ fn generar_hash() {
     };
     
     // Pedir memoria
-    print!("Memoria en MB (recomendado: 10-100): ");
+    print!("Memoria en MB, o con unidad como 64M o 1G (recomendado: 10-100 MB): ");
     io::stdout().flush().unwrap();
     let mut memory_str = String::new();
     io::stdin().read_line(&mut memory_str).unwrap();
-    let memory_mb: usize = match memory_str.trim().parse() {
+    let memory_mb = match parse_memory_mb(&memory_str) {
         Ok(n) if n > 0 => n,
         _ => {
             println!("❌ Memoria inválida. Debe ser mayor a 0 MB.");
@@ -99,9 +127,8 @@ fn verificar_contraseña() {
     // Pedir contraseña
     print!("Ingrese la contraseña a verificar: ");
     io::stdout().flush().unwrap();
-    let mut password = String::new();
-    io::stdin().read_line(&mut password).unwrap();
-    let password = password.trim();
+    let password = leer_contraseña();
+    let password = password.as_str();
     
     // Pedir hash almacenado
     print!("Ingrese el hash almacenado: ");
@@ -190,6 +217,7 @@ fn mostrar_ejemplos() {
         let hash = hash_password(password, threads, memory).expect("Error al generar hash");
         let duration = start.elapsed();
         println!("  Tiempo de hash: {:?}", duration);
+        // to_string() garantiza al menos 60 caracteres
         println!("  Hash: {}...", &hash.to_string()[..60]);
         println!();
     }
@@ -215,17 +243,26 @@ fn mostrar_ejemplos() {
     // Ejemplo 6: Formato de almacenamiento
     println!("--- Ejemplo 6: Formato de Almacenamiento ---");
     println!("El hash se almacena en el formato:");
-    println!("Sinkproof:v1:hilos:memoria_mb:salt_base64:frase_encriptada_base64");
+    println!("Sinkproof:v2:hilos:memoria_mb:opciones:salt_base64:frase_encriptada_base64:checksum");
     println!("\nEjemplo completo:");
     println!("{}", stored);
     println!("\nComponentes:");
-    let parts: Vec<&str> = stored.split(':').collect();
-    println!("  Nombre: {}", parts[0]);
-    println!("  Versión: {}", parts[1]);
-    println!("  Hilos: {}", parts[2]);
-    println!("  Memoria (MB): {}", parts[3]);
-    println!("  Salt (base64): {}...", &parts[4][..20]);
-    println!("  Frase encriptada (base64): {}...", &parts[5][..20]);
+    let parsed: SinkproofHash = stored.parse().expect("Error al leer el hash");
+    let phrase_b64 = general_purpose::STANDARD.encode(&parsed.encrypted_phrase);
+    println!("  Versión: {}", parsed.version.name());
+    println!("  Hilos: {}", parsed.threads);
+    println!("  Memoria (MB): {}", parsed.memory_mb);
+    println!(
+        "  Opciones: algoritmo {}, variante {}, {} pasada(s)",
+        parsed.options.algorithm.name(),
+        parsed.options.variant.name(),
+        parsed.options.passes
+    );
+    println!("  Salt (base64): {}...", &parsed.salt_base64()[..20]);
+    println!("  Frase encriptada (base64): {}...", &phrase_b64[..20]);
+    if let Some(checksum) = ParsedHashFields::split(&stored).ok().and_then(|fields| fields.checksum) {
+        println!("  Checksum: {}", checksum);
+    }
     
     println!("\n=== Ejemplos Completados ===");
 }
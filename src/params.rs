@@ -0,0 +1,1460 @@
+use crate::encryption::{fastest_cipher, Cipher, VERIFICATION_PHRASE};
+use crate::error::{Result, SinkproofError};
+use crate::pepper::PepperSource;
+use crate::hasher::{is_valid_worker_output_len, MAX_WORKER_OUTPUT_LEN, WORKER_OUTPUT_LEN};
+use crate::storage::HashVersion;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use unicode_normalization::UnicodeNormalization;
+
+/// Hash function used to fill worker memory and derive the key
+/// 
+/// With the `fips` feature only FIPS-approved primitives are accepted:
+/// SHA-256 (FIPS 180-4) here, plus AES-256-GCM (FIPS 197, SP 800-38D)
+/// for the verification phrase. BLAKE3 is not approved and is rejected
+/// with `SinkproofError::AlgorithmNotPermitted`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgo {
+    /// SHA-256, the original v1 algorithm
+    #[default]
+    Sha256,
+    /// BLAKE3, requires the `blake3` feature and is not FIPS-approved
+    Blake3,
+}
+
+impl HashAlgo {
+    /// Name used for this algorithm in the v2 storage format
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgo::Sha256 => "sha256",
+            HashAlgo::Blake3 => "blake3",
+        }
+    }
+
+    /// Look up an algorithm by its storage name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(HashAlgo::Sha256),
+            "blake3" => Some(HashAlgo::Blake3),
+            _ => None,
+        }
+    }
+
+    /// Check that this build can hash with the algorithm
+    pub fn ensure_available(self) -> Result<()> {
+        match self {
+            HashAlgo::Sha256 => Ok(()),
+            HashAlgo::Blake3 if cfg!(feature = "fips") => {
+                Err(SinkproofError::AlgorithmNotPermitted(self.name()))
+            }
+            HashAlgo::Blake3 if !cfg!(feature = "blake3") => {
+                Err(SinkproofError::AlgorithmUnavailable { algorithm: self.name(), feature: "blake3" })
+            }
+            HashAlgo::Blake3 => Ok(()),
+        }
+    }
+}
+
+/// Memory access pattern of the fill, analogous to Argon2's `i`/`d`/`id`
+/// 
+/// Data-independent reads leak nothing about the password through cache
+/// timing but are easier to attack with time-memory tradeoffs;
+/// data-dependent reads resist tradeoffs but their addresses depend on the
+/// password. `Hybrid` runs the first half of the fill independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    /// Read addresses depend only on the iteration index (the v1 behavior)
+    #[default]
+    Independent,
+    /// Read addresses are derived from the current chaining value
+    Dependent,
+    /// Independent for the first half of the fill, dependent afterwards
+    Hybrid,
+}
+
+impl Variant {
+    /// Name used for this variant in the v2 storage format
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Independent => "i",
+            Variant::Dependent => "d",
+            Variant::Hybrid => "id",
+        }
+    }
+
+    /// Look up a variant by its storage name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "i" => Some(Variant::Independent),
+            "d" => Some(Variant::Dependent),
+            "id" => Some(Variant::Hybrid),
+            _ => None,
+        }
+    }
+
+    /// Whether iteration `i` of `iterations` reads a data-dependent address
+    pub fn is_data_dependent(self, i: usize, iterations: usize) -> bool {
+        match self {
+            Variant::Independent => false,
+            Variant::Dependent => true,
+            Variant::Hybrid => i >= iterations / 2,
+        }
+    }
+}
+
+/// How `derive_key` reduces the thread outputs to one key
+/// 
+/// `Merkle` hashes each output into a leaf, then hashes pairs of nodes
+/// level by level (an odd node is carried up unchanged) until one root
+/// remains. Leaves and inner nodes are domain-separated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Combiner {
+    /// One hash over the concatenated outputs (the v1 behavior)
+    #[default]
+    Concat,
+    /// Binary Merkle-tree reduction of the outputs
+    Merkle,
+}
+
+impl Combiner {
+    /// Name used for this combiner in the v2 storage format
+    pub fn name(self) -> &'static str {
+        match self {
+            Combiner::Concat => "concat",
+            Combiner::Merkle => "merkle",
+        }
+    }
+
+    /// Look up a combiner by its storage name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "concat" => Some(Combiner::Concat),
+            "merkle" => Some(Combiner::Merkle),
+            _ => None,
+        }
+    }
+}
+
+/// Final digest `derive_key` uses to produce the key
+/// 
+/// The AEAD protecting the verification phrase always uses the first 32
+/// bytes; the full width is what `verify_and_derive` and
+/// `seal::derive_and_seal` start from, for modes that need wider keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyDigest {
+    /// The fill algorithm (`HashAlgo`), giving a 32-byte key (the v1 behavior)
+    #[default]
+    Algorithm,
+    /// SHA-512 (FIPS 180-4), giving a 64-byte key
+    Sha512,
+}
+
+impl KeyDigest {
+    /// Name used for this digest in the v2 storage format
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyDigest::Algorithm => "alg",
+            KeyDigest::Sha512 => "sha512",
+        }
+    }
+
+    /// Look up a digest by its storage name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "alg" => Some(KeyDigest::Algorithm),
+            "sha512" => Some(KeyDigest::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Length in bytes of keys derived with this digest
+    pub fn key_len(self) -> usize {
+        match self {
+            KeyDigest::Algorithm => 32,
+            KeyDigest::Sha512 => 64,
+        }
+    }
+}
+
+/// Unicode normalization applied to passwords before hashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizationForm {
+    /// Canonical composition: precomposed and decomposed `ñ` match
+    Nfc,
+    /// Compatibility composition: additionally folds forms such as `ﬁ` to `fi`
+    Nfkc,
+}
+
+impl NormalizationForm {
+    /// Name used for this form in the v2 storage format
+    pub fn name(self) -> &'static str {
+        match self {
+            NormalizationForm::Nfc => "nfc",
+            NormalizationForm::Nfkc => "nfkc",
+        }
+    }
+
+    /// Look up a form by its storage name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nfc" => Some(NormalizationForm::Nfc),
+            "nfkc" => Some(NormalizationForm::Nfkc),
+            _ => None,
+        }
+    }
+}
+
+/// Algorithm choices that change the derived key and are therefore
+/// recorded in the stored hash
+/// 
+/// The default is what `HashParams::new` uses for new hashes
+/// (`Variant::Hybrid`, a v2 hash); `HashOptions::v1` is the original v1
+/// algorithm, which v1 hashes parse to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashOptions {
+    /// Hash function used by the workers
+    pub algorithm: HashAlgo,
+    /// Unicode normalization applied to the password; `None` hashes the
+    /// bytes as given, like v1
+    pub normalization: Option<NormalizationForm>,
+    /// Memory access pattern of the fill
+    pub variant: Variant,
+    /// AEAD used for the verification phrase; does not change the key but
+    /// must match at verification time
+    pub cipher: Cipher,
+    /// Memory in MB for each thread, overriding the uniform `memory_mb`
+    pub per_thread_memory: Option<Vec<usize>>,
+    /// Passes over the filled memory (Argon2's t-cost); 1 is the v1 fill
+    pub passes: u32,
+    /// Reduction of the thread outputs into the key
+    pub combiner: Combiner,
+    /// Final digest producing the key, and thereby its length
+    pub key_digest: KeyDigest,
+    /// Bytes each worker contributes to the key: the end of its memory.
+    /// A multiple of 32 up to `MAX_WORKER_OUTPUT_LEN`
+    pub worker_output_len: usize,
+    /// DEFLATE the verification phrase before encrypting it (`deflate`
+    /// feature); does not change the key
+    pub compress_phrase: bool,
+    /// The key is mixed with a server-side pepper that is not stored in
+    /// the hash; see `HashParams::pepper_from_keyring`
+    pub peppered: bool,
+    /// Free-text label stored with the hash (e.g. a tenant or context
+    /// name); does not change the key and is not authenticated
+    pub label: Option<String>,
+}
+
+impl Default for HashOptions {
+    fn default() -> Self {
+        HashOptions { variant: Variant::Hybrid, ..HashOptions::v1() }
+    }
+}
+
+impl HashOptions {
+    /// The original v1 algorithm, including the v1 fill
+    /// (`Variant::Independent`); hashes made with it are stored as v1
+    pub fn v1() -> Self {
+        HashOptions {
+            algorithm: HashAlgo::default(),
+            normalization: None,
+            variant: Variant::default(),
+            cipher: Cipher::default(),
+            per_thread_memory: None,
+            passes: 1,
+            combiner: Combiner::default(),
+            key_digest: KeyDigest::default(),
+            worker_output_len: WORKER_OUTPUT_LEN,
+            compress_phrase: false,
+            peppered: false,
+            label: None,
+        }
+    }
+
+    /// The password as it is fed to the workers
+    pub fn prepare_password<'a>(&self, password: &'a str) -> Cow<'a, str> {
+        match self.normalization {
+            None => Cow::Borrowed(password),
+            Some(NormalizationForm::Nfc) => Cow::Owned(password.nfc().collect()),
+            Some(NormalizationForm::Nfkc) => Cow::Owned(password.nfkc().collect()),
+        }
+    }
+
+    /// Storage format version needed to record these options
+    pub fn format_version(&self) -> HashVersion {
+        if *self == HashOptions::v1() {
+            HashVersion::V1
+        } else {
+            HashVersion::V2
+        }
+    }
+}
+
+/// Environment variable read by `HashParams::from_env` for `threads`
+pub const ENV_THREADS: &str = "SINKPROOF_THREADS";
+
+/// Environment variable read by `HashParams::from_env` for `memory_mb`
+pub const ENV_MEMORY_MB: &str = "SINKPROOF_MEMORY_MB";
+
+/// Thread count allowed when `SINKPROOF_MAX_THREADS` is not set at build time
+pub const DEFAULT_MAX_THREADS: usize = 1024;
+
+/// Largest thread count this build will hash or verify with
+/// 
+/// Fixed at compile time: set the `SINKPROOF_MAX_THREADS` environment
+/// variable when building to lower it for constrained targets (cargo
+/// rebuilds when it changes). Unlike `SINKPROOF_THREADS`, it is never read
+/// at runtime. Larger requests and stored hashes fail with
+/// `ThreadLimitExceeded` before any thread or buffer is allocated.
+pub const MAX_THREADS: usize = match option_env!("SINKPROOF_MAX_THREADS") {
+    Some(value) => parse_build_limit(value),
+    None => DEFAULT_MAX_THREADS,
+};
+
+/// Per-thread memory allowed when `SINKPROOF_MAX_MEMORY_MB` is not set at
+/// build time (16 GB)
+pub const DEFAULT_MAX_MEMORY_MB: usize = 16 * 1024;
+
+/// Largest per-thread memory in MB this build will hash or verify with
+/// 
+/// Stored hashes are untrusted input: without a ceiling, a planted
+/// `memory_mb` would make verification attempt an arbitrarily large
+/// allocation. Like `MAX_THREADS` it is fixed at compile time, from the
+/// `SINKPROOF_MAX_MEMORY_MB` environment variable; larger requests and
+/// stored hashes fail with `MemoryLimitExceeded` before any buffer is
+/// allocated. Together the two bound the total memory of one hash.
+pub const MAX_MEMORY_MB: usize = match option_env!("SINKPROOF_MAX_MEMORY_MB") {
+    Some(value) => parse_build_limit(value),
+    None => DEFAULT_MAX_MEMORY_MB,
+};
+
+/// Total memory allowed when `SINKPROOF_MAX_TOTAL_MEMORY_MB` is not set at
+/// build time (64 GB)
+pub const DEFAULT_MAX_TOTAL_MEMORY_MB: usize = 64 * 1024;
+
+/// Largest total memory in MB (threads times the largest per-thread memory)
+/// this build will hash or verify with
+/// 
+/// `MAX_THREADS` and `MAX_MEMORY_MB` alone would still allow a stored
+/// `Sinkproof:v1:1024:16384:...` asking for 16 TB. Fixed at compile time
+/// from the `SINKPROOF_MAX_TOTAL_MEMORY_MB` environment variable, like the
+/// other limits; larger requests and stored hashes fail with
+/// `TotalMemoryLimitExceeded` before any buffer is allocated.
+pub const MAX_TOTAL_MEMORY_MB: usize = match option_env!("SINKPROOF_MAX_TOTAL_MEMORY_MB") {
+    Some(value) => parse_build_limit(value),
+    None => DEFAULT_MAX_TOTAL_MEMORY_MB,
+};
+
+/// Pass count allowed when `SINKPROOF_MAX_PASSES` is not set at build time
+pub const DEFAULT_MAX_PASSES: u32 = 64;
+
+/// Largest pass count this build will hash or verify with
+/// 
+/// Each pass rewrites all of memory, so a planted `pass=4294967295` would
+/// keep a verifier busy indefinitely without allocating anything. Fixed at
+/// compile time from the `SINKPROOF_MAX_PASSES` environment variable, like
+/// `MAX_THREADS`; larger requests and stored hashes fail with
+/// `PassLimitExceeded` before any work starts.
+pub const MAX_PASSES: u32 = match option_env!("SINKPROOF_MAX_PASSES") {
+    Some(value) => {
+        let max = parse_build_limit(value);
+        assert!(max <= u32::MAX as usize, "SINKPROOF_MAX_PASSES limit is too large");
+        max as u32
+    }
+    None => DEFAULT_MAX_PASSES,
+};
+
+/// Compile-time parse of a `SINKPROOF_MAX_*` limit; invalid values fail the build
+const fn parse_build_limit(value: &str) -> usize {
+    let bytes = value.as_bytes();
+    assert!(!bytes.is_empty(), "SINKPROOF_MAX_* limits must be positive integers");
+
+    let mut max = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "SINKPROOF_MAX_* limits must be positive integers");
+        max = match max.checked_mul(10) {
+            Some(shifted) => shifted + (bytes[i] - b'0') as usize,
+            None => panic!("SINKPROOF_MAX_* limit is too large"),
+        };
+        i += 1;
+    }
+    assert!(max > 0, "SINKPROOF_MAX_* limits must be positive integers");
+    max
+}
+
+/// Smallest per-thread memory `HashParams::from_budget` will choose, in MB
+pub const MIN_BUDGET_MEMORY_PER_THREAD_MB: usize = 8;
+
+/// Cost parameters used when hashing a password
+/// 
+/// With the `serde` feature it deserializes from configuration files; `threads` and `memory_mb` are
+/// required, algorithm choices are given by their storage names, and the
+/// result is checked with `validate` (zeros are rejected). Memory sizes
+/// are MB or strings with units as accepted by `parse_memory_mb`:
+/// 
+/// ```toml
+/// threads = 4
+/// memory_mb = "64M"   # or 64, "1G"
+/// passes = 2          # optional, like the remaining keys
+/// variant = "id"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(try_from = "ParamsConfig"))]
+pub struct HashParams {
+    /// Number of worker threads (must be > 0)
+    pub threads: usize,
+    /// Memory filled by each thread in MB (must be > 0)
+    pub memory_mb: usize,
+    /// Algorithm choices recorded in the resulting hash
+    pub options: HashOptions,
+    /// Stack size for worker threads; `None` uses the platform default.
+    /// Not recorded in the hash since it does not affect the output.
+    pub thread_stack_size: Option<usize>,
+    /// Trade memory for recomputation (see `thread_worker_low_memory`).
+    /// Produces the same hash, so it is not recorded either. Only possible
+    /// for `Variant::Independent` with a single pass; `validate` rejects it
+    /// for any other variant or pass count.
+    pub low_memory: bool,
+    /// Where to load the pepper from when `options.peppered` is set. Never
+    /// recorded: the hash only records that a pepper is needed.
+    pub pepper: Option<PepperSource>,
+    /// Phrase to encrypt instead of the built-in one; see
+    /// `HashParams::verification_phrase`. Never recorded: the hash only
+    /// records its `phrase_id`.
+    pub verification_phrase: Option<String>,
+}
+
+impl HashParams {
+    /// Create parameters for the given thread count and per-thread memory
+    /// 
+    /// New hashes use `Variant::Hybrid`, whose second half reads addresses
+    /// derived from the chaining value; the v1 fill (`Variant::Independent`)
+    /// always reads the first block and is open to time-memory tradeoffs.
+    /// The variant is recorded in the (v2) hash, and v1 hashes keep
+    /// verifying with the v1 fill. Select `Variant::Independent` explicitly
+    /// to produce v1 hashes.
+    pub fn new(threads: usize, memory_mb: usize) -> Self {
+        HashParams {
+            threads,
+            memory_mb,
+            options: HashOptions::default(),
+            thread_stack_size: None,
+            low_memory: false,
+            pepper: None,
+            verification_phrase: None,
+        }
+    }
+
+    /// Start building parameters with named, validated fields
+    /// 
+    /// Unlike `new`, the thread count and memory cannot be swapped by
+    /// accident, and `HashParamsBuilder::build` rejects invalid values up
+    /// front.
+    /// 
+    /// ```
+    /// use sinkproof::HashParams;
+    /// 
+    /// let params = HashParams::builder().threads(4).memory_mb(64).build().unwrap();
+    /// assert_eq!(params, HashParams::new(4, 64));
+    /// ```
+    pub fn builder() -> HashParamsBuilder {
+        HashParamsBuilder { params: HashParams::new(0, 0) }
+    }
+
+    /// The default cost with the cipher that is fastest on this machine
+    /// (`fastest_cipher()`)
+    /// 
+    /// Without the `chacha20` feature that is always AES-256-GCM; with it,
+    /// machines where ChaCha20-Poly1305 is faster get v2 hashes using it.
+    /// The result depends on the hardware, unlike `HashParams::default()`.
+    pub fn fastest() -> Self {
+        HashParams::default().cipher(fastest_cipher())
+    }
+
+    /// Cheapest valid parameters: one thread filling 1 MB
+    /// 
+    /// **Insecure; for tests only.** Hashes made with these parameters can be
+    /// brute-forced cheaply. Zero memory stays rejected by `validate`; this
+    /// is the smallest cost the storage format can express (memory is
+    /// counted in whole MB, so the fill still runs 32768 iterations), and
+    /// gives test suites a fast path without special-casing zero.
+    pub fn no_memory_hardness_for_tests() -> Self {
+        HashParams::new(1, 1)
+    }
+
+    /// Read cost parameters from the environment
+    /// 
+    /// `SINKPROOF_THREADS` sets `threads` and `SINKPROOF_MEMORY_MB` sets
+    /// `memory_mb`. A set variable always wins; an unset one falls back to
+    /// the `HashParams::default()` value (4 threads, 64 MB). A value that is
+    /// not a number, not valid Unicode or zero is an error rather than a
+    /// silent fallback.
+    pub fn from_env() -> Result<Self> {
+        HashParams::from_lookup(|name| std::env::var(name))
+    }
+
+    /// `from_env` over an arbitrary variable lookup
+    pub(crate) fn from_lookup(
+        lookup: impl Fn(&str) -> std::result::Result<String, std::env::VarError>,
+    ) -> Result<Self> {
+        let defaults = HashParams::default();
+        let read = |name: &str, default: usize, parse: fn(&str) -> Result<usize>| match lookup(name) {
+            Ok(value) => parse(&value).map_err(|e| SinkproofError::InvalidConfig(format!("Invalid {}: {}", name, e))),
+            Err(std::env::VarError::NotPresent) => Ok(default),
+            Err(e) => Err(SinkproofError::InvalidConfig(format!("Invalid {} value: {}", name, e))),
+        };
+        let parse_threads = |value: &str| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|e| SinkproofError::InvalidConfig(format!("'{}': {}", value, e)))
+        };
+
+        let params = HashParams {
+            threads: read(ENV_THREADS, defaults.threads, parse_threads)?,
+            memory_mb: read(ENV_MEMORY_MB, defaults.memory_mb, parse_memory_mb)?,
+            ..defaults
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Split a total memory budget across threads
+    /// 
+    /// Uses as many threads as `available_threads` allows while keeping at
+    /// least `MIN_BUDGET_MEMORY_PER_THREAD_MB` per thread, then divides the
+    /// budget evenly. `threads * memory_mb` never exceeds the budget; a
+    /// budget below the floor yields a single thread with the whole budget,
+    /// and a budget of zero is raised to the valid minimum of 1 MB.
+    pub fn from_budget(total_memory_mb: usize, available_threads: usize) -> Self {
+        let threads = available_threads
+            .min(total_memory_mb / MIN_BUDGET_MEMORY_PER_THREAD_MB)
+            .max(1);
+
+        HashParams::new(threads, (total_memory_mb / threads).max(1))
+    }
+
+    /// Warning for thread counts whose per-thread memory is small enough to
+    /// make parallel attacks cheap, or `None`
+    /// 
+    /// Many threads with little memory each (e.g. 64 × 1 MB) map well onto
+    /// GPU cores even when the total looks large; few threads with large
+    /// memory do not. Flags multi-threaded configurations where a thread
+    /// has less than `MIN_BUDGET_MEMORY_PER_THREAD_MB` and suggests the
+    /// `from_budget` split of the same total. A single thread cannot be
+    /// consolidated further, so it is never flagged.
+    pub fn gpu_resistance_warning(&self) -> Option<String> {
+        let smallest = match &self.options.per_thread_memory {
+            Some(sizes) => sizes.iter().copied().min()?,
+            None => self.memory_mb,
+        };
+        if self.threads <= 1 || smallest >= MIN_BUDGET_MEMORY_PER_THREAD_MB {
+            return None;
+        }
+
+        let total = self.total_memory_mb();
+        let suggested = HashParams::from_budget(total, self.threads);
+        Some(format!(
+            "{} threads with {} MB each are GPU-friendly (under {} MB per thread); consider {} threads × {} MB for the same {} MB",
+            self.threads,
+            smallest,
+            MIN_BUDGET_MEMORY_PER_THREAD_MB,
+            suggested.threads,
+            suggested.memory_mb,
+            total
+        ))
+    }
+
+    /// Select the hash function used by the workers
+    pub fn algorithm(mut self, algorithm: HashAlgo) -> Self {
+        self.options.algorithm = algorithm;
+        self
+    }
+
+    /// Give each thread its own memory size in MB, in thread-index order
+    /// 
+    /// Overrides the uniform size for hashing; `memory_mb` is set to the
+    /// smallest entry so policy checks stay conservative. The list must have
+    /// one entry per thread and is recorded in the (v2) hash.
+    pub fn per_thread_memory(mut self, sizes: Vec<usize>) -> Self {
+        self.memory_mb = sizes.iter().copied().min().unwrap_or(0);
+        self.options.per_thread_memory = Some(sizes);
+        self
+    }
+
+    /// Cap the memory of every thread at `max_mb`, instead of failing later
+    /// on an absurd request
+    /// 
+    /// Opt-in resilience for callers that take sizes from untrusted
+    /// configuration: oversized values are lowered to `max_mb` with a
+    /// `log::warn!` record. Applies to the sizes set so far, so call it
+    /// after `per_thread_memory`. The clamped sizes are what the hash uses
+    /// and records, so verification needs nothing extra.
+    pub fn clamp_memory_to(mut self, max_mb: usize) -> Self {
+        let requested = self.total_memory_mb();
+        self.memory_mb = self.memory_mb.min(max_mb);
+        if let Some(sizes) = &mut self.options.per_thread_memory {
+            for size in sizes.iter_mut() {
+                *size = (*size).min(max_mb);
+            }
+        }
+
+        let clamped = self.total_memory_mb();
+        if clamped < requested {
+            log::warn!(
+                "Sinkproof memory request clamped to {} MB per thread ({} MB total instead of {} MB)",
+                max_mb,
+                clamped,
+                requested
+            );
+        }
+        self
+    }
+
+    /// Memory in MB filled by the thread with the given index
+    /// 
+    /// `None` if there is no such thread, or a per-thread memory list is
+    /// too short to cover it. Sizes beyond the thread count are ignored.
+    pub fn thread_memory_mb(&self, thread_index: usize) -> Option<usize> {
+        if thread_index >= self.threads {
+            return None;
+        }
+        match &self.options.per_thread_memory {
+            Some(sizes) => sizes.get(thread_index).copied(),
+            None => Some(self.memory_mb),
+        }
+    }
+
+    /// Memory in bytes filled by the thread with the given index
+    /// 
+    /// `PerThreadMemoryMismatch` if the thread has no size, `OutOfMemory` if
+    /// the size does not fit in `usize`.
+    pub(crate) fn thread_memory_bytes(&self, thread_index: usize) -> Result<usize> {
+        let memory_mb = self.thread_memory_mb(thread_index).ok_or(SinkproofError::PerThreadMemoryMismatch {
+            threads: self.threads,
+            sizes: self.options.per_thread_memory.as_ref().map_or(self.threads, Vec::len),
+        })?;
+        memory_mb.checked_mul(1024 * 1024).ok_or(SinkproofError::OutOfMemory)
+    }
+
+    /// Memory in MB filled by all threads together
+    /// 
+    /// Saturates at `usize::MAX` instead of overflowing, so it is safe on
+    /// unvalidated parameters.
+    pub fn total_memory_mb(&self) -> usize {
+        match &self.options.per_thread_memory {
+            Some(sizes) => sizes.iter().fold(0, |total: usize, &size| total.saturating_add(size)),
+            None => self.threads.saturating_mul(self.memory_mb),
+        }
+    }
+
+    /// Check the thread count against the build's `MAX_THREADS`
+    pub fn check_thread_limit(&self) -> Result<()> {
+        if self.threads > MAX_THREADS {
+            return Err(SinkproofError::ThreadLimitExceeded { requested: self.threads, max: MAX_THREADS });
+        }
+        Ok(())
+    }
+
+    /// Check the pass count against the build's `MAX_PASSES`
+    pub fn check_pass_limit(&self) -> Result<()> {
+        if self.options.passes > MAX_PASSES {
+            return Err(SinkproofError::PassLimitExceeded { requested: self.options.passes, max: MAX_PASSES });
+        }
+        Ok(())
+    }
+
+    /// Memory in MB of the thread that fills the most
+    pub fn largest_thread_memory_mb(&self) -> usize {
+        match &self.options.per_thread_memory {
+            Some(sizes) => sizes.iter().copied().max().unwrap_or(0),
+            None => self.memory_mb,
+        }
+    }
+
+    /// Check every thread's memory against the build's `MAX_MEMORY_MB`
+    pub fn check_memory_limit(&self) -> Result<()> {
+        let largest = self.largest_thread_memory_mb();
+        if largest > MAX_MEMORY_MB {
+            return Err(SinkproofError::MemoryLimitExceeded { requested: largest, max: MAX_MEMORY_MB });
+        }
+        Ok(())
+    }
+
+    /// Check that low-memory mode is only requested where it applies:
+    /// `Variant::Independent` with a single pass
+    pub fn check_low_memory(&self) -> Result<()> {
+        if self.low_memory && (self.options.variant != Variant::Independent || self.options.passes != 1) {
+            return Err(SinkproofError::InvalidConfig(format!(
+                "Low-memory mode requires the '{}' variant with one pass, got '{}' with {} passes",
+                Variant::Independent.name(),
+                self.options.variant.name(),
+                self.options.passes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check the total memory against the build's `MAX_TOTAL_MEMORY_MB`
+    /// 
+    /// The total is the thread count times the largest thread memory, like
+    /// `ParseLimits::max_total_memory_mb`.
+    pub fn check_total_memory_limit(&self) -> Result<()> {
+        match self.threads.checked_mul(self.largest_thread_memory_mb()) {
+            Some(total) if total <= MAX_TOTAL_MEMORY_MB => Ok(()),
+            total => Err(SinkproofError::TotalMemoryLimitExceeded {
+                requested: total.unwrap_or(usize::MAX),
+                max: MAX_TOTAL_MEMORY_MB,
+            }),
+        }
+    }
+
+    /// Check that a per-thread memory list matches the thread count
+    pub fn check_thread_memory(&self) -> Result<()> {
+        match &self.options.per_thread_memory {
+            Some(sizes) if sizes.len() != self.threads => Err(SinkproofError::PerThreadMemoryMismatch {
+                threads: self.threads,
+                sizes: sizes.len(),
+            }),
+            Some(sizes) if sizes.contains(&0) => Err(SinkproofError::InvalidMemory),
+            _ => Ok(()),
+        }
+    }
+
+    /// Select the AEAD that encrypts the verification phrase
+    pub fn cipher(mut self, cipher: Cipher) -> Self {
+        self.options.cipher = cipher;
+        self
+    }
+
+    /// Store a free-text label with the hash
+    /// 
+    /// Any text is allowed: characters that would clash with the storage
+    /// delimiters are percent-escaped on serialization. Setting a label
+    /// makes the hash v2.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.options.label = Some(label.into());
+        self
+    }
+
+    /// Select the memory access pattern of the fill
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.options.variant = variant;
+        self
+    }
+
+    /// Normalize passwords to `form` before hashing
+    /// 
+    /// The form is recorded in the (v2) hash, so verification normalizes the
+    /// same way. Existing hashes keep the form they were created with:
+    /// enabling this does not make a decomposed password verify against an
+    /// older unnormalized hash, and vice versa, so only enable it for new
+    /// hashes (e.g. on rehash).
+    pub fn normalize_unicode(mut self, form: NormalizationForm) -> Self {
+        self.options.normalization = Some(form);
+        self
+    }
+
+    /// Pass over the memory `passes` times instead of once
+    /// 
+    /// Like Argon2's t-cost, this adds time cost without more RAM: after
+    /// the initial fill, each further pass rewrites every block from the
+    /// chaining value, a reference block and the block's previous content.
+    /// Reference blocks are data-dependent for the `Dependent` and `Hybrid`
+    /// variants. More than one pass is recorded in the (v2) hash; zero
+    /// passes, or more than `MAX_PASSES`, are rejected by `validate`.
+    pub fn passes(mut self, passes: u32) -> Self {
+        self.options.passes = passes;
+        self
+    }
+
+    /// Select how thread outputs are combined into the key
+    pub fn combiner(mut self, combiner: Combiner) -> Self {
+        self.options.combiner = combiner;
+        self
+    }
+
+    /// Select the final digest of `derive_key`, e.g. SHA-512 for 64-byte keys
+    pub fn key_digest(mut self, digest: KeyDigest) -> Self {
+        self.options.key_digest = digest;
+        self
+    }
+
+    /// Take `len` bytes from the end of each worker's memory instead of 512
+    /// 
+    /// Longer outputs feed more of the filled memory into the key. `len`
+    /// must be a multiple of 32 between 32 and `MAX_WORKER_OUTPUT_LEN`,
+    /// checked by `validate`; a non-default length is recorded in the (v2)
+    /// hash so verification uses it too.
+    pub fn worker_output_len(mut self, len: usize) -> Self {
+        self.options.worker_output_len = len;
+        self
+    }
+
+    /// Compress the verification phrase before encrypting it
+    /// 
+    /// Requires the `deflate` feature. See the `compress` module docs for
+    /// why this is only safe for non-secret, fixed plaintext.
+    pub fn compress_phrase(mut self, enabled: bool) -> Self {
+        self.options.compress_phrase = enabled;
+        self
+    }
+
+    /// Mix the key with a pepper read from the OS keyring entry
+    /// `service`/`key` each time a password is hashed
+    /// 
+    /// Requires the `keyring` feature and a platform backend enabled on the
+    /// `keyring` crate (see the README). The pepper never enters the hash,
+    /// which records only that one is needed, so a stolen hash database
+    /// cannot be attacked offline without it. Verify with
+    /// `verify_password_peppered`. The keyring secret must be backed up: if
+    /// it is lost, every hash made with it becomes unverifiable.
+    pub fn pepper_from_keyring(self, service: impl Into<String>, key: impl Into<String>) -> Self {
+        self.pepper(PepperSource::Keyring { service: service.into(), key: key.into() })
+    }
+
+    /// Mix the key with a pepper read from `path` each time a password is
+    /// hashed
+    /// 
+    /// The file's bytes are the pepper, including any trailing newline. The
+    /// same backup caveat as `pepper_from_keyring` applies.
+    pub fn pepper_from_file(self, path: impl Into<PathBuf>) -> Self {
+        self.pepper(PepperSource::File(path.into()))
+    }
+
+    /// Mix the key with the pepper loaded from `source`
+    pub fn pepper(mut self, source: PepperSource) -> Self {
+        self.options.peppered = true;
+        self.pepper = Some(source);
+        self
+    }
+
+    /// Encrypt `phrase` as the verification phrase instead of the built-in one
+    /// 
+    /// Lets each deployment use its own canary plaintext. The phrase is not
+    /// stored, only its `phrase_id`, so such hashes are always v2 and must
+    /// be verified with `verify_password_with_phrase`; `verify_password`
+    /// reports `PhraseMismatch` for them. An empty phrase is rejected by
+    /// `validate`.
+    pub fn verification_phrase(mut self, phrase: impl Into<String>) -> Self {
+        self.verification_phrase = Some(phrase.into());
+        self
+    }
+
+    /// The phrase hashes with these parameters encrypt
+    pub(crate) fn phrase(&self) -> &str {
+        self.verification_phrase.as_deref().unwrap_or(VERIFICATION_PHRASE)
+    }
+
+    /// Spawn workers with a custom stack size instead of the platform default
+    pub fn thread_stack_size(mut self, size: Option<usize>) -> Self {
+        self.thread_stack_size = size;
+        self
+    }
+
+    /// Keep only sparse checkpoints of worker memory and recompute the rest
+    /// 
+    /// Requires `Variant::Independent` and a single pass: the other
+    /// variants read data-dependent addresses and later passes rewrite
+    /// memory, neither of which can be recomputed from checkpoints. Since
+    /// `new` defaults to `Variant::Hybrid`, select the variant explicitly;
+    /// otherwise hashing fails with `InvalidConfig` (see
+    /// `check_low_memory`).
+    pub fn low_memory(mut self, enabled: bool) -> Self {
+        self.low_memory = enabled;
+        self
+    }
+
+    /// Check that the parameters can be used for hashing
+    pub fn validate(&self) -> Result<()> {
+        if self.threads == 0 {
+            return Err(SinkproofError::InvalidThreads);
+        }
+        self.check_thread_limit()?;
+        if self.memory_mb == 0 {
+            return Err(SinkproofError::InvalidMemory);
+        }
+        self.check_memory_limit()?;
+        self.check_total_memory_limit()?;
+        if self.options.passes == 0 {
+            return Err(SinkproofError::InvalidPasses);
+        }
+        self.check_pass_limit()?;
+        self.check_low_memory()?;
+        if !is_valid_worker_output_len(self.options.worker_output_len) {
+            return Err(SinkproofError::InvalidConfig(format!(
+                "Worker output length {} must be a multiple of 32 between 32 and {}",
+                self.options.worker_output_len, MAX_WORKER_OUTPUT_LEN
+            )));
+        }
+        if self.verification_phrase.as_deref() == Some("") {
+            return Err(SinkproofError::InvalidConfig("Verification phrase must not be empty".to_string()));
+        }
+        self.check_thread_memory()?;
+        self.options.algorithm.ensure_available()?;
+        self.options.cipher.ensure_available()?;
+        if self.options.compress_phrase {
+            crate::compress::ensure_available()?;
+        }
+        Ok(())
+    }
+}
+
+/// Fluent builder for `HashParams`, see `HashParams::builder`
+/// 
+/// Thread count and memory have no default and must be set; options
+/// default to `HashOptions::default()`.
+/// Further settings such as `passes` can be chained on the built
+/// `HashParams`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashParamsBuilder {
+    params: HashParams,
+}
+
+impl HashParamsBuilder {
+    /// Number of worker threads
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.params.threads = threads;
+        self
+    }
+
+    /// Memory filled by each thread in MB
+    pub fn memory_mb(mut self, memory_mb: usize) -> Self {
+        self.params.memory_mb = memory_mb;
+        self
+    }
+
+    /// Algorithm choices recorded in the hash, replacing all defaults
+    pub fn options(mut self, options: HashOptions) -> Self {
+        self.params.options = options;
+        self
+    }
+
+    /// Validate and return the parameters
+    /// 
+    /// # Returns
+    /// `InvalidThreads` or `InvalidMemory` if either was not set or is
+    /// zero, or any other error of `HashParams::validate`
+    pub fn build(self) -> Result<HashParams> {
+        self.params.validate()?;
+        Ok(self.params)
+    }
+}
+
+/// Parse a memory size in MB, optionally with a unit
+/// 
+/// Accepts a plain number of MB (`"64"`) or a number followed by `M`/`MB`/
+/// `MiB` or `G`/`GB`/`GiB`, case-insensitive and with optional space
+/// (`"64M"`, `"1G"`, `"2 GiB"`). Units are binary, like the rest of the
+/// crate: `1G` is 1024 MB. Shared by the configuration file, the
+/// environment and the demo CLI.
+pub fn parse_memory_mb(text: &str) -> Result<usize> {
+    let invalid = |reason: &str| SinkproofError::InvalidConfig(format!("Invalid memory size '{}': {}", text, reason));
+
+    let trimmed = text.trim();
+    let digits_end = trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(digits_end);
+    let number: usize = number.parse().map_err(|_| invalid("expected a number of MB, e.g. 64, 64M or 1G"))?;
+
+    let multiplier = match unit.trim_start().to_ascii_lowercase().as_str() {
+        "" | "m" | "mb" | "mib" => 1,
+        "g" | "gb" | "gib" => 1024,
+        _ => return Err(invalid("unknown unit; use M or G")),
+    };
+    number.checked_mul(multiplier).ok_or_else(|| invalid("too large"))
+}
+
+/// Memory size in a configuration file: MB or a string with units
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum MemoryValue {
+    Mb(usize),
+    Text(String),
+}
+
+#[cfg(feature = "serde")]
+impl MemoryValue {
+    fn into_mb(self) -> Result<usize> {
+        match self {
+            MemoryValue::Mb(mb) => Ok(mb),
+            MemoryValue::Text(text) => parse_memory_mb(&text),
+        }
+    }
+}
+
+/// Configuration-file form of `HashParams`
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParamsConfig {
+    threads: usize,
+    memory_mb: MemoryValue,
+    per_thread_memory: Option<Vec<MemoryValue>>,
+    algorithm: Option<String>,
+    normalization: Option<String>,
+    variant: Option<String>,
+    cipher: Option<String>,
+    passes: Option<u32>,
+    combiner: Option<String>,
+    key_digest: Option<String>,
+    worker_output_len: Option<usize>,
+    label: Option<String>,
+    thread_stack_size: Option<usize>,
+    #[serde(default)]
+    low_memory: bool,
+}
+
+/// Look up an optional storage name, reporting unknown ones as `kind`
+#[cfg(feature = "serde")]
+fn lookup<T>(name: Option<String>, kind: &str, from_name: fn(&str) -> Option<T>) -> Result<Option<T>> {
+    name.map(|name| {
+        from_name(&name).ok_or_else(|| SinkproofError::InvalidConfig(format!("Unknown {} '{}'", kind, name)))
+    })
+    .transpose()
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ParamsConfig> for HashParams {
+    type Error = SinkproofError;
+
+    fn try_from(config: ParamsConfig) -> Result<Self> {
+        let mut params = HashParams::new(config.threads, config.memory_mb.into_mb()?)
+            .thread_stack_size(config.thread_stack_size)
+            .low_memory(config.low_memory);
+
+        if let Some(sizes) = config.per_thread_memory {
+            let sizes = sizes.into_iter().map(MemoryValue::into_mb).collect::<Result<_>>()?;
+            params = params.per_thread_memory(sizes);
+        }
+        if let Some(algorithm) = lookup(config.algorithm, "algorithm", HashAlgo::from_name)? {
+            params = params.algorithm(algorithm);
+        }
+        params.options.normalization = lookup(config.normalization, "normalization", NormalizationForm::from_name)?;
+        if let Some(variant) = lookup(config.variant, "variant", Variant::from_name)? {
+            params = params.variant(variant);
+        }
+        if let Some(cipher) = lookup(config.cipher, "cipher", Cipher::from_name)? {
+            params = params.cipher(cipher);
+        }
+        if let Some(passes) = config.passes {
+            params = params.passes(passes);
+        }
+        if let Some(combiner) = lookup(config.combiner, "combiner", Combiner::from_name)? {
+            params = params.combiner(combiner);
+        }
+        if let Some(digest) = lookup(config.key_digest, "key digest", KeyDigest::from_name)? {
+            params = params.key_digest(digest);
+        }
+        if let Some(len) = config.worker_output_len {
+            params = params.worker_output_len(len);
+        }
+        params.options.label = config.label;
+
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+impl Default for HashParams {
+    /// 4 threads with 64 MB each, the same on every machine (see
+    /// `HashParams::fastest` for a hardware-dependent cipher)
+    fn default() -> Self {
+        HashParams::new(4, 64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{test_phrase, ParseLimits, SinkproofHash};
+
+    #[test]
+    fn test_default_is_valid() {
+        assert!(HashParams::default().validate().is_ok());
+        assert_eq!(HashParams::default(), HashParams::new(4, 64));
+
+        let fastest = HashParams::fastest();
+        assert!(fastest.validate().is_ok());
+        assert_eq!(fastest, HashParams::new(4, 64).cipher(fastest_cipher()));
+    }
+
+    #[test]
+    fn test_from_env() {
+        use std::env::VarError;
+
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            HashParams::from_lookup(move |name| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+                    .ok_or(VarError::NotPresent)
+            })
+        };
+
+        // Everything but the cost comes from `HashParams::default()`
+        let with = |threads, memory_mb| Ok(HashParams { threads, memory_mb, ..HashParams::default() });
+
+        assert_eq!(env(&[]), Ok(HashParams::default()));
+        assert_eq!(env(&[(ENV_THREADS, "2"), (ENV_MEMORY_MB, "16")]), with(2, 16));
+        assert_eq!(env(&[(ENV_MEMORY_MB, "8")]), with(4, 8));
+        assert_eq!(env(&[(ENV_THREADS, " 3 ")]), with(3, 64));
+
+        assert!(matches!(env(&[(ENV_THREADS, "four")]), Err(SinkproofError::InvalidConfig(msg)) if msg.contains("SINKPROOF_THREADS")));
+        assert!(matches!(env(&[(ENV_MEMORY_MB, "-1")]), Err(SinkproofError::InvalidConfig(_))));
+        assert_eq!(env(&[(ENV_MEMORY_MB, "1G")]), with(4, 1024));
+        assert_eq!(env(&[(ENV_THREADS, "0")]), Err(SinkproofError::InvalidThreads));
+
+        let not_unicode = HashParams::from_lookup(|_| Err(VarError::NotUnicode("\u{fffd}".into())));
+        assert!(matches!(not_unicode, Err(SinkproofError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_parse_memory_mb() {
+        assert_eq!(parse_memory_mb("64"), Ok(64));
+        assert_eq!(parse_memory_mb("64M"), Ok(64));
+        assert_eq!(parse_memory_mb(" 64 mb "), Ok(64));
+        assert_eq!(parse_memory_mb("1G"), Ok(1024));
+        assert_eq!(parse_memory_mb("2GiB"), Ok(2048));
+
+        for invalid in ["", "M", "64K", "1.5G", "-1", "64 M B", "99999999999999999999G"] {
+            assert!(matches!(parse_memory_mb(invalid), Err(SinkproofError::InvalidConfig(_))), "{}", invalid);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_memory_units() {
+        let plain: HashParams = toml::from_str("threads = 4\nmemory_mb = 1024").unwrap();
+        let with_unit: HashParams = toml::from_str("threads = 4\nmemory_mb = \"1G\"").unwrap();
+        assert_eq!(plain, with_unit);
+        assert_eq!(with_unit, HashParams::new(4, 1024));
+
+        let mixed: HashParams = toml::from_str("threads = 2\nmemory_mb = 1\nper_thread_memory = [\"8M\", 4]").unwrap();
+        assert_eq!(mixed.options.per_thread_memory, Some(vec![8, 4]));
+
+        let err = toml::from_str::<HashParams>("threads = 4\nmemory_mb = \"64K\"").unwrap_err();
+        assert!(err.to_string().contains("unknown unit"), "{}", err);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_from_toml() {
+        let params: HashParams = toml::from_str("threads = 4\nmemory_mb = 64").unwrap();
+        assert_eq!(params, HashParams::new(4, 64));
+
+        let params: HashParams = toml::from_str(
+            "threads = 2\nmemory_mb = 8\npasses = 3\nvariant = \"id\"\nnormalization = \"nfc\"\nlabel = \"tenant\"",
+        )
+        .unwrap();
+        assert_eq!(
+            params,
+            HashParams::new(2, 8)
+                .passes(3)
+                .variant(Variant::Hybrid)
+                .normalize_unicode(NormalizationForm::Nfc)
+                .label("tenant")
+        );
+
+        for invalid in [
+            "threads = 0\nmemory_mb = 64",
+            "threads = 4\nmemory_mb = 0",
+            "threads = 4\nmemory_mb = 64\npasses = 0",
+            "threads = 4\nmemory_mb = 64\nvariant = \"x\"",
+            "threads = 4\nmemory_mb = 64\nmemory = 1",
+            "threads = 4",
+        ] {
+            assert!(toml::from_str::<HashParams>(invalid).is_err(), "{}", invalid);
+        }
+
+        let err = toml::from_str::<HashParams>("threads = 0\nmemory_mb = 64").unwrap_err();
+        assert!(err.to_string().contains("Number of threads must be greater than 0"));
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_no_memory_hardness_preset() {
+        let params = HashParams::no_memory_hardness_for_tests();
+        assert!(params.validate().is_ok());
+        assert_ne!(params, HashParams::default());
+        assert_eq!(params.total_memory_mb(), 1);
+
+        let hash = crate::hasher::hash_password_with("password", &params).expect("Failed to hash");
+        assert!(crate::verifier::verify_password("password", &hash.to_string()).expect("Failed to verify"));
+    }
+
+    #[test]
+    fn test_validate_rejects_zero() {
+        assert_eq!(HashParams::new(0, 10).validate(), Err(SinkproofError::InvalidThreads));
+        assert_eq!(HashParams::new(2, 0).validate(), Err(SinkproofError::InvalidMemory));
+    }
+
+    #[test]
+    fn test_builder() {
+        let params = HashParams::builder()
+            .memory_mb(64)
+            .threads(4)
+            .options(HashOptions { passes: 2, ..HashOptions::default() })
+            .build()
+            .unwrap();
+        assert_eq!(params, HashParams::new(4, 64).passes(2));
+
+        let params = HashParams::builder()
+            .threads(4)
+            .memory_mb(64)
+            .options(HashOptions { passes: 2, ..HashOptions::v1() })
+            .build()
+            .unwrap();
+        assert_eq!(params, HashParams::new(4, 64).passes(2).variant(Variant::Independent));
+        assert_eq!(HashParams::builder().threads(4).memory_mb(64).build().unwrap().options.variant, Variant::Hybrid);
+
+        assert_eq!(HashParams::builder().memory_mb(64).build(), Err(SinkproofError::InvalidThreads));
+        assert_eq!(HashParams::builder().threads(4).build(), Err(SinkproofError::InvalidMemory));
+        assert_eq!(HashParams::builder().threads(0).memory_mb(64).build(), Err(SinkproofError::InvalidThreads));
+        assert_eq!(
+            HashParams::builder().threads(4).memory_mb(1).options(HashOptions { passes: 0, ..HashOptions::default() }).build(),
+            Err(SinkproofError::InvalidPasses)
+        );
+    }
+
+    #[test]
+    fn test_algorithm_names_roundtrip() {
+        for algo in [HashAlgo::Sha256, HashAlgo::Blake3] {
+            assert_eq!(HashAlgo::from_name(algo.name()), Some(algo));
+        }
+        assert_eq!(HashAlgo::from_name("md5"), None);
+    }
+
+    #[test]
+    fn test_variant_names_roundtrip() {
+        for variant in [Variant::Independent, Variant::Dependent, Variant::Hybrid] {
+            assert_eq!(Variant::from_name(variant.name()), Some(variant));
+        }
+        assert_eq!(Variant::from_name("x"), None);
+
+        assert!(!Variant::Hybrid.is_data_dependent(49, 100));
+        assert!(Variant::Hybrid.is_data_dependent(50, 100));
+        assert_eq!(HashParams::new(1, 1).variant(Variant::Dependent).options.format_version(), HashVersion::V2);
+    }
+
+    #[test]
+    fn test_format_version() {
+        assert_eq!(HashOptions::v1().format_version(), HashVersion::V1);
+        assert_eq!(HashOptions::default().format_version(), HashVersion::V2);
+        assert_eq!(HashOptions::default(), HashParams::new(1, 1).options);
+        let params = HashParams::new(1, 1).algorithm(HashAlgo::Blake3);
+        assert_eq!(params.options.format_version(), HashVersion::V2);
+    }
+
+    #[test]
+    fn test_from_budget_stays_within_budget() {
+        for (budget, available) in [(512, 8), (512, 3), (100, 64), (20, 4), (5, 4), (1, 1), (64, 0)] {
+            let params = HashParams::from_budget(budget, available);
+            assert!(params.threads * params.memory_mb <= budget, "{} MB on {} threads", budget, available);
+            assert!(params.threads >= 1 && params.threads <= available.max(1));
+            assert!(params.validate().is_ok());
+        }
+
+        let params = HashParams::from_budget(512, 8);
+        assert_eq!((params.threads, params.memory_mb), (8, 64));
+
+        // Per-thread memory stays above the floor
+        let params = HashParams::from_budget(20, 4);
+        assert_eq!((params.threads, params.memory_mb), (2, 10));
+
+        // A zero budget is clamped to the smallest valid parameters
+        for available in [0, 1, 8] {
+            let params = HashParams::from_budget(0, available);
+            assert_eq!((params.threads, params.memory_mb), (1, 1));
+            assert!(params.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_per_thread_memory() {
+        let params = HashParams::new(3, 64).per_thread_memory(vec![4, 1, 2]);
+        assert!(params.validate().is_ok());
+        assert_eq!(params.memory_mb, 1);
+        assert_eq!(params.thread_memory_mb(0), Some(4));
+        assert_eq!(params.thread_memory_mb(2), Some(2));
+        assert_eq!(params.thread_memory_mb(3), None);
+        assert_eq!(HashParams::new(2, 8).thread_memory_mb(1), Some(8));
+        assert_eq!(HashParams::new(2, 8).thread_memory_mb(2), None);
+
+        // A list shorter than the thread count has no size for the rest
+        let short = HashParams::new(3, 1).per_thread_memory(vec![1, 2]);
+        assert_eq!(short.thread_memory_mb(2), None);
+        assert_eq!(
+            short.thread_memory_bytes(2),
+            Err(SinkproofError::PerThreadMemoryMismatch { threads: 3, sizes: 2 })
+        );
+        assert_eq!(params.total_memory_mb(), 7);
+        assert_eq!(params.options.format_version(), HashVersion::V2);
+
+        // A list longer than the thread count has no thread for the rest
+        let long = HashParams::new(2, 1).per_thread_memory(vec![1, 2, 3]);
+        assert_eq!(long.thread_memory_mb(1), Some(2));
+        assert_eq!(long.thread_memory_mb(2), None);
+        assert_eq!(long.validate(), Err(SinkproofError::PerThreadMemoryMismatch { threads: 2, sizes: 3 }));
+        assert_eq!(
+            HashParams::new(2, 1).per_thread_memory(vec![1, 0]).validate(),
+            Err(SinkproofError::InvalidMemory)
+        );
+    }
+
+    #[test]
+    fn test_gpu_resistance_warning() {
+        let warning = HashParams::new(64, 1).gpu_resistance_warning().expect("64 × 1 MB should warn");
+        assert!(warning.contains("consider 8 threads × 8 MB"), "{}", warning);
+
+        assert_eq!(HashParams::new(4, 64).gpu_resistance_warning(), None);
+        assert_eq!(HashParams::new(1, 1).gpu_resistance_warning(), None);
+        assert!(HashParams::new(2, 1).per_thread_memory(vec![64, 1]).gpu_resistance_warning().is_some());
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_thread_limit() {
+        assert_eq!(parse_build_limit("64"), 64);
+        assert!(HashParams::new(MAX_THREADS, 1).validate().is_ok());
+        assert_eq!(
+            HashParams::new(MAX_THREADS + 1, 1).validate(),
+            Err(SinkproofError::ThreadLimitExceeded { requested: MAX_THREADS + 1, max: MAX_THREADS })
+        );
+        assert!(crate::hasher::hash_password("password", MAX_THREADS + 1, 1).is_err());
+
+        let stored = format!(
+            concat!("Sinkproof:v1:{}:1:AQID:", test_phrase!()),
+            MAX_THREADS + 1
+        );
+        assert_eq!(
+            crate::verifier::verify_password_with_limits("password", &stored, &ParseLimits::BUILD),
+            Err(SinkproofError::ThreadLimitExceeded { requested: MAX_THREADS + 1, max: MAX_THREADS })
+        );
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        assert!(HashParams::new(1, MAX_MEMORY_MB).check_memory_limit().is_ok());
+        assert_eq!(
+            HashParams::new(1, MAX_MEMORY_MB + 1).validate(),
+            Err(SinkproofError::MemoryLimitExceeded { requested: MAX_MEMORY_MB + 1, max: MAX_MEMORY_MB })
+        );
+        assert_eq!(
+            HashParams::new(2, 1).per_thread_memory(vec![1, usize::MAX]).check_memory_limit(),
+            Err(SinkproofError::MemoryLimitExceeded { requested: usize::MAX, max: MAX_MEMORY_MB })
+        );
+
+        // A planted stored hash fails before anything is allocated
+        let stored = format!(
+            concat!("Sinkproof:v1:1:{}:AQID:", test_phrase!()),
+            usize::MAX
+        );
+        assert_eq!(
+            crate::verifier::verify_password_with_limits("password", &stored, &ParseLimits::BUILD),
+            Err(SinkproofError::MemoryLimitExceeded { requested: usize::MAX, max: MAX_MEMORY_MB })
+        );
+    }
+
+    #[test]
+    fn test_total_memory_limit() {
+        let largest = MAX_TOTAL_MEMORY_MB / MAX_MEMORY_MB;
+        assert!(HashParams::new(largest, MAX_MEMORY_MB).validate().is_ok());
+        assert_eq!(
+            HashParams::new(MAX_THREADS, MAX_MEMORY_MB).validate(),
+            Err(SinkproofError::TotalMemoryLimitExceeded {
+                requested: MAX_THREADS * MAX_MEMORY_MB,
+                max: MAX_TOTAL_MEMORY_MB
+            })
+        );
+        assert_eq!(
+            HashParams::new(2, 1).per_thread_memory(vec![1, MAX_MEMORY_MB]).check_total_memory_limit(),
+            Ok(())
+        );
+
+        // Each field is within its own limit, the product is not
+        let stored = format!(
+            concat!("Sinkproof:v1:{}:{}:AQID:", test_phrase!()),
+            MAX_THREADS, MAX_MEMORY_MB
+        );
+        let hash = stored.parse::<SinkproofHash>().unwrap();
+        assert!(matches!(
+            crate::hasher::compute_key("password", &hash.salt, &hash.params()),
+            Err(SinkproofError::TotalMemoryLimitExceeded { .. })
+        ));
+        assert!(matches!(
+            crate::verifier::verify_password_with_limits("password", &stored, &ParseLimits::BUILD),
+            Err(SinkproofError::TotalMemoryLimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pass_limit() {
+        assert!(HashParams::new(1, 1).passes(MAX_PASSES).validate().is_ok());
+        assert_eq!(
+            HashParams::new(1, 1).passes(MAX_PASSES + 1).validate(),
+            Err(SinkproofError::PassLimitExceeded { requested: MAX_PASSES + 1, max: MAX_PASSES })
+        );
+
+        // A planted pass count fails before any work starts
+        let stored = concat!("Sinkproof:v2:1:1:alg=sha256,pass=4294967295:AQID:", test_phrase!(), ":f2a91c9e");
+        assert_eq!(
+            crate::verifier::verify_password_with_limits("password", stored, &ParseLimits::BUILD),
+            Err(SinkproofError::PassLimitExceeded { requested: u32::MAX, max: MAX_PASSES })
+        );
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_clamp_memory_to() {
+        let params = HashParams::new(1, 1 << 40).clamp_memory_to(2);
+        assert_eq!(params, HashParams::new(1, 2));
+
+        let hash = crate::hasher::hash_password_with("password", &params).expect("Failed to hash");
+        let stored = hash.to_string();
+        assert!(stored.starts_with("Sinkproof:v2:1:2:"));
+        assert!(crate::verifier::verify_password("password", &stored).expect("Failed to verify"));
+
+        let per_thread = HashParams::new(3, 1).per_thread_memory(vec![1, 8, 3]).clamp_memory_to(2);
+        assert_eq!(per_thread.options.per_thread_memory, Some(vec![1, 2, 2]));
+        assert_eq!(per_thread.memory_mb, 1);
+        assert_eq!(HashParams::new(4, 64).clamp_memory_to(128), HashParams::new(4, 64));
+
+        // Totals of unvalidated sizes saturate instead of overflowing
+        assert_eq!(HashParams::new(4, usize::MAX).total_memory_mb(), usize::MAX);
+        assert_eq!(HashParams::new(4, usize::MAX).clamp_memory_to(64), HashParams::new(4, 64));
+        let huge = HashParams::new(2, 1).per_thread_memory(vec![usize::MAX, 8]);
+        assert_eq!(huge.total_memory_mb(), usize::MAX);
+        assert_eq!(huge.clamp_memory_to(4).options.per_thread_memory, Some(vec![4, 4]));
+    }
+
+    #[test]
+    fn test_prepare_password() {
+        let decomposed = "contrase\u{006E}\u{0303}a";
+        let precomposed = "contrase\u{00F1}a";
+
+        assert_eq!(HashOptions::v1().prepare_password(decomposed), decomposed);
+
+        let nfc = HashParams::new(1, 1).normalize_unicode(NormalizationForm::Nfc);
+        assert_eq!(nfc.options.prepare_password(decomposed), precomposed);
+        assert_eq!(nfc.options.format_version(), HashVersion::V2);
+
+        let nfkc = HashParams::new(1, 1).normalize_unicode(NormalizationForm::Nfkc);
+        assert_eq!(nfkc.options.prepare_password("\u{FB01}"), "fi");
+    }
+
+    #[cfg(not(feature = "chacha20"))]
+    #[test]
+    fn test_chacha_requires_feature() {
+        let params = HashParams::new(1, 1).cipher(Cipher::ChaCha20Poly1305);
+        assert_eq!(params.validate(), Err(SinkproofError::AlgorithmUnavailable { algorithm: "chacha20poly1305", feature: "chacha20" }));
+    }
+
+    #[cfg(feature = "fips")]
+    #[test]
+    fn test_fips_rejects_blake3() {
+        let params = HashParams::new(1, 1).algorithm(HashAlgo::Blake3);
+        assert_eq!(params.validate(), Err(SinkproofError::AlgorithmNotPermitted("blake3")));
+        assert!(HashParams::new(1, 1).algorithm(HashAlgo::Sha256).validate().is_ok());
+    }
+}
@@ -0,0 +1,250 @@
+//! Server-side pepper loaded from the OS keyring or a file
+//! 
+//! A pepper is a secret shared by all hashes of a deployment and kept out
+//! of the hash database: the derived key is HMAC'd with it, so a stolen
+//! database cannot be attacked offline without also stealing the pepper.
+//! Hashes record only that a pepper is needed (`pep=1`), never the pepper
+//! or where it came from.
+//! 
+//! Operational model: the pepper is loaded on every hash and verification,
+//! so the keyring entry or file must be present on every machine that
+//! verifies. Back it up together with the database; if the pepper is lost
+//! or changed, every hash made with it becomes unverifiable and the only
+//! recovery is a password reset.
+
+use crate::error::{Result, SinkproofError};
+use crate::policy::warn_if_weak;
+use crate::verifier::{parse_for_verification, verified_key};
+use hkdf::Hkdf;
+use sha2::{Sha256, Sha512};
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+/// Where a pepper is loaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PepperSource {
+    /// Secret stored in the OS keyring under `service`/`key` (requires the
+    /// `keyring` feature)
+    Keyring { service: String, key: String },
+    /// Raw bytes of a file
+    File(PathBuf),
+}
+
+impl PepperSource {
+    /// Read the pepper
+    /// 
+    /// `PepperUnavailable` explains why when the keyring or file cannot be
+    /// read, or the pepper is empty.
+    pub fn load(&self) -> Result<Zeroizing<Vec<u8>>> {
+        let pepper = match self {
+            PepperSource::Keyring { service, key } => load_from_keyring(service, key)?,
+            PepperSource::File(path) => Zeroizing::new(std::fs::read(path).map_err(|e| {
+                SinkproofError::PepperUnavailable(format!("cannot read pepper file {}: {}", path.display(), e))
+            })?),
+        };
+
+        if pepper.is_empty() {
+            return Err(SinkproofError::PepperUnavailable(format!("{} holds an empty pepper", self)));
+        }
+        Ok(pepper)
+    }
+}
+
+impl std::fmt::Display for PepperSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PepperSource::Keyring { service, key } => write!(f, "keyring entry '{}/{}'", service, key),
+            PepperSource::File(path) => write!(f, "pepper file {}", path.display()),
+        }
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn load_from_keyring(service: &str, key: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let unavailable =
+        |e: keyring::Error| SinkproofError::PepperUnavailable(format!("keyring entry '{}/{}': {}", service, key, e));
+
+    let entry = keyring::Entry::new(service, key).map_err(unavailable)?;
+    Ok(Zeroizing::new(entry.get_secret().map_err(unavailable)?))
+}
+
+#[cfg(not(feature = "keyring"))]
+fn load_from_keyring(_service: &str, _key: &str) -> Result<Zeroizing<Vec<u8>>> {
+    Err(SinkproofError::PepperUnavailable(
+        "reading the pepper from the OS keyring requires the 'keyring' feature".to_string(),
+    ))
+}
+
+/// HMAC the derived key with the pepper, keeping the key length
+/// 
+/// 64-byte keys (`KeyDigest::Sha512`) use HMAC-SHA512, others HMAC-SHA256.
+pub(crate) fn apply_pepper(key: &[u8], pepper: &[u8]) -> Vec<u8> {
+    // HKDF-Extract is HMAC with the salt as key
+    if key.len() == 64 {
+        Hkdf::<Sha512>::extract(Some(pepper), key).0.to_vec()
+    } else {
+        Hkdf::<Sha256>::extract(Some(pepper), key).0.to_vec()
+    }
+}
+
+/// Verify a password against a hash made with a pepper
+/// 
+/// Loads the pepper from `pepper` first; a hash made without a pepper is
+/// verified normally and `pepper` is not read. `verify_password` on a
+/// peppered hash returns `PepperUnavailable` rather than `Ok(false)`.
+/// 
+/// # Returns
+/// `Ok(true)` if the password matches, `Ok(false)` if it doesn't, `Err` if
+/// the hash cannot be parsed or the pepper cannot be loaded
+pub fn verify_password_peppered(password: &str, stored_hash: &str, pepper: &PepperSource) -> Result<bool> {
+    let hash = parse_for_verification(stored_hash)?;
+    let mut params = hash.params();
+    if params.options.peppered {
+        params.pepper = Some(pepper.clone());
+    }
+
+    let valid = verified_key(password, &hash, &params)?.is_some();
+    if valid {
+        warn_if_weak(&hash);
+    }
+    Ok(valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "hashing")]
+    use crate::hasher::hash_password_with;
+    #[cfg(feature = "hashing")]
+    use crate::params::HashParams;
+    #[cfg(feature = "hashing")]
+    use crate::verifier::verify_password;
+
+    fn pepper_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sinkproof-pepper-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).expect("Failed to write pepper file");
+        path
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_file_pepper_roundtrip() {
+        let path = pepper_file("roundtrip", b"server secret");
+        let source = PepperSource::File(path.clone());
+        let stored = hash_password_with("password", &HashParams::new(1, 1).pepper_from_file(&path))
+            .expect("Failed to hash")
+            .to_string();
+
+        assert!(stored.contains("pep=1"));
+        assert!(verify_password_peppered("password", &stored, &source).expect("Failed to verify"));
+        assert!(!verify_password_peppered("wrong", &stored, &source).expect("Failed to verify"));
+        assert!(matches!(verify_password("password", &stored), Err(SinkproofError::PepperUnavailable(_))));
+
+        // A different pepper makes the right password fail
+        let other_path = pepper_file("other", b"another secret");
+        let other = PepperSource::File(other_path.clone());
+        assert!(!verify_password_peppered("password", &stored, &other).expect("Failed to verify"));
+        std::fs::remove_file(other_path).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+        let err = verify_password_peppered("password", &stored, &source).unwrap_err();
+        assert!(err.to_string().contains("cannot read pepper file"), "{}", err);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_unpeppered_hash_ignores_source() {
+        let stored = hash_password_with("password", &HashParams::new(1, 1)).unwrap().to_string();
+        let missing = PepperSource::File(PathBuf::from("/nonexistent/sinkproof-pepper"));
+        assert!(verify_password_peppered("password", &stored, &missing).expect("Failed to verify"));
+    }
+
+    #[test]
+    fn test_empty_pepper_rejected() {
+        let path = pepper_file("empty", b"");
+        let source = PepperSource::File(path.clone());
+        assert!(matches!(source.load(), Err(SinkproofError::PepperUnavailable(_))));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(not(feature = "keyring"))]
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_keyring_requires_feature() {
+        let params = HashParams::new(1, 1).pepper_from_keyring("sinkproof", "pepper");
+        let err = hash_password_with("password", &params).unwrap_err();
+        assert!(err.to_string().contains("'keyring' feature"), "{}", err);
+    }
+
+    #[cfg(feature = "keyring")]
+    mod mock_keyring {
+        use super::*;
+        use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+        use std::any::Any;
+        use std::collections::HashMap;
+        use std::sync::{Arc, Mutex};
+
+        type Secrets = Arc<Mutex<HashMap<(String, String), Vec<u8>>>>;
+
+        /// In-memory keyring whose entries share one map, so a secret set
+        /// through one entry is visible to entries created later
+        #[derive(Debug, Default)]
+        struct MapStore(Secrets);
+
+        #[derive(Debug)]
+        struct MapCredential {
+            id: (String, String),
+            secrets: Secrets,
+        }
+
+        impl CredentialBuilderApi for MapStore {
+            fn build(&self, _target: Option<&str>, service: &str, user: &str) -> keyring::Result<Box<Credential>> {
+                Ok(Box::new(MapCredential {
+                    id: (service.to_string(), user.to_string()),
+                    secrets: self.0.clone(),
+                }))
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        impl CredentialApi for MapCredential {
+            fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+                self.secrets.lock().unwrap().insert(self.id.clone(), secret.to_vec());
+                Ok(())
+            }
+
+            fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+                self.secrets.lock().unwrap().get(&self.id).cloned().ok_or(keyring::Error::NoEntry)
+            }
+
+            fn delete_credential(&self) -> keyring::Result<()> {
+                self.secrets.lock().unwrap().remove(&self.id).map(drop).ok_or(keyring::Error::NoEntry)
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        #[test]
+        fn test_keyring_pepper() {
+            keyring::set_default_credential_builder(Box::new(MapStore::default()));
+            keyring::Entry::new("sinkproof-test", "pepper").unwrap().set_secret(b"keyring secret").unwrap();
+
+            let params = HashParams::new(1, 1).pepper_from_keyring("sinkproof-test", "pepper");
+            let source = params.pepper.clone().unwrap();
+            let stored = hash_password_with("password", &params).expect("Failed to hash").to_string();
+
+            assert!(verify_password_peppered("password", &stored, &source).expect("Failed to verify"));
+            assert!(!verify_password_peppered("wrong", &stored, &source).expect("Failed to verify"));
+
+            // A missing entry is reported, not treated as a wrong password
+            let missing = PepperSource::Keyring { service: "sinkproof-test".to_string(), key: "absent".to_string() };
+            let err = verify_password_peppered("password", &stored, &missing).unwrap_err();
+            assert!(err.to_string().contains("keyring entry 'sinkproof-test/absent'"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::error::Result;
+#[cfg(feature = "hashing")]
+use crate::params::HashParams;
+use crate::storage::SinkproofHash;
+use crate::verifier::{parse_for_verification, verify_hash};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+/// Threshold below which `verify_password` warns about weak hashes
+static WEAK_HASH_THRESHOLD: Mutex<Option<HashPolicy>> = Mutex::new(Some(HashPolicy::RECOMMENDED));
+
+/// Parameter sets that have already been warned about in this process
+static WARNED_PARAMS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Site-wide minimum cost that accepted hashes must meet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashPolicy {
+    /// Minimum number of worker threads
+    pub min_threads: usize,
+    /// Minimum memory per thread in MB
+    pub min_memory_mb: usize,
+}
+
+impl HashPolicy {
+    /// Current recommendation, matching `HashParams::default()`
+    pub const RECOMMENDED: HashPolicy = HashPolicy { min_threads: 4, min_memory_mb: 64 };
+
+    /// Create a policy requiring at least `min_threads` and `min_memory_mb`
+    pub const fn new(min_threads: usize, min_memory_mb: usize) -> Self {
+        HashPolicy { min_threads, min_memory_mb }
+    }
+
+    /// Whether a stored hash meets the policy
+    pub fn is_met_by(&self, hash: &SinkproofHash) -> bool {
+        hash.threads >= self.min_threads && hash.memory_mb >= self.min_memory_mb
+    }
+}
+
+/// Set the threshold for the weak-hash warning, or `None` to disable it
+/// 
+/// When `verify_password` succeeds against a hash that does not meet the
+/// threshold, a `log::warn!` record prompts the operator to rehash. Each
+/// parameter set is reported at most once per process. The verification
+/// result is never affected. Defaults to `HashPolicy::RECOMMENDED`.
+pub fn set_weak_hash_threshold(threshold: Option<HashPolicy>) {
+    *WEAK_HASH_THRESHOLD.lock().unwrap_or_else(|e| e.into_inner()) = threshold;
+}
+
+/// Log a one-time warning if `hash` is below the weak-hash threshold
+pub(crate) fn warn_if_weak(hash: &SinkproofHash) {
+    let threshold = *WEAK_HASH_THRESHOLD.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(threshold) = threshold else {
+        return;
+    };
+    if threshold.is_met_by(hash) {
+        return;
+    }
+
+    let params = hash.prefix();
+    if WARNED_PARAMS.lock().unwrap_or_else(|e| e.into_inner()).insert(params.clone()) {
+        log::warn!(
+            "Sinkproof hash parameters {} are below the recommended {} threads / {} MB; rehash on next login",
+            params,
+            threshold.min_threads,
+            threshold.min_memory_mb
+        );
+    }
+}
+
+/// GPU-friendliness warnings that have already been logged in this process
+#[cfg(feature = "hashing")]
+static WARNED_GPU_FRIENDLY: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Log a one-time warning if hashing with `params` is GPU-friendly; see
+/// `HashParams::gpu_resistance_warning`
+#[cfg(feature = "hashing")]
+pub(crate) fn warn_if_gpu_friendly(params: &HashParams) {
+    let Some(warning) = params.gpu_resistance_warning() else {
+        return;
+    };
+    if WARNED_GPU_FRIENDLY.lock().unwrap_or_else(|e| e.into_inner()).insert(warning.clone()) {
+        log::warn!("Sinkproof: {}", warning);
+    }
+}
+
+/// Result of verifying a password against a policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyOutcome {
+    /// The password matches and the hash meets the policy
+    Valid,
+    /// The password matches but the hash is weaker than the policy;
+    /// the application may force a password reset
+    ValidButBelowPolicy,
+    /// The password does not match
+    Invalid,
+}
+
+/// Verify a password and check the stored hash against a policy
+///
+/// The password is always checked, so a wrong password reports `Invalid`
+/// even for hashes below the policy.
+pub fn verify_with_policy(password: &str, stored_hash: &str, policy: &HashPolicy) -> Result<PolicyOutcome> {
+    let hash = parse_for_verification(stored_hash)?;
+
+    if !verify_hash(password, &hash)? {
+        return Ok(PolicyOutcome::Invalid);
+    }
+
+    if policy.is_met_by(&hash) {
+        Ok(PolicyOutcome::Valid)
+    } else {
+        Ok(PolicyOutcome::ValidButBelowPolicy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_phrase;
+    #[cfg(feature = "hashing")]
+    use crate::hasher::hash_password;
+    #[cfg(feature = "hashing")]
+    use crate::verifier::verify_password;
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_policy_outcomes() {
+        let stored = hash_password("password", 1, 1).expect("Failed to hash").to_string();
+
+        let lenient = HashPolicy::new(1, 1);
+        let strict = HashPolicy::new(2, 1);
+
+        assert_eq!(verify_with_policy("password", &stored, &lenient).unwrap(), PolicyOutcome::Valid);
+        assert_eq!(verify_with_policy("password", &stored, &strict).unwrap(), PolicyOutcome::ValidButBelowPolicy);
+        assert_eq!(verify_with_policy("wrong", &stored, &strict).unwrap(), PolicyOutcome::Invalid);
+    }
+
+    #[test]
+    fn test_is_met_by() {
+        let hash = concat!("Sinkproof:v1:4:64:AQID:", test_phrase!()).parse::<SinkproofHash>().expect("Failed to parse");
+
+        assert!(HashPolicy::new(4, 64).is_met_by(&hash));
+        assert!(!HashPolicy::new(8, 64).is_met_by(&hash));
+        assert!(!HashPolicy::new(4, 128).is_met_by(&hash));
+    }
+
+    /// Collects log records so tests can inspect them
+    #[cfg(feature = "hashing")]
+    struct CaptureLogger(Mutex<Vec<String>>);
+
+    #[cfg(feature = "hashing")]
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[cfg(feature = "hashing")]
+    static LOGGER: CaptureLogger = CaptureLogger(Mutex::new(Vec::new()));
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_weak_hash_warning() {
+        log::set_logger(&LOGGER).expect("Logger already set");
+        log::set_max_level(log::LevelFilter::Warn);
+        set_weak_hash_threshold(Some(HashPolicy::new(2, 3)));
+
+        // Parameter sets unique to this test, so other tests can't claim the warning
+        let weak = hash_password("password", 1, 3).expect("Failed to hash").to_string();
+        let adequate = hash_password("password", 2, 3).expect("Failed to hash").to_string();
+
+        assert!(verify_password("password", &weak).unwrap());
+        assert!(verify_password("password", &weak).unwrap());
+        assert!(verify_password("password", &adequate).unwrap());
+        set_weak_hash_threshold(Some(HashPolicy::RECOMMENDED));
+
+        let logs = LOGGER.0.lock().unwrap();
+        assert_eq!(logs.iter().filter(|l| l.contains("Sinkproof:v2:1:3:")).count(), 1);
+        assert!(!logs.iter().any(|l| l.contains("Sinkproof:v2:2:3:")));
+    }
+
+    #[test]
+    fn test_invalid_stored_hash_errors() {
+        assert!(verify_with_policy("password", "invalid", &HashPolicy::new(1, 1)).is_err());
+    }
+}
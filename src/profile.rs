@@ -0,0 +1,139 @@
+//! Per-phase timing of verification, for diagnosing slow logins
+//! 
+//! Enabled with the `profiling` feature. The profiled path runs the same
+//! computation as `verify_password` but times each phase separately.
+
+use crate::encryption::{is_verification_phrase, VERIFICATION_PHRASE};
+use crate::error::Result;
+use crate::hasher::{check_computable, derive_key_with, pepper_key, run_parallel, thread_indices, worker_output};
+use crate::verifier::parse_for_verification;
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Time spent in each phase of one verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyProfile {
+    /// Parsing the stored hash
+    pub parse: Duration,
+    /// From starting the workers until the last one began running
+    /// (thread spawning, or queueing on the rayon pool)
+    pub thread_spawn: Duration,
+    /// Compute time measured inside each worker, by thread index
+    pub worker_compute: Vec<Duration>,
+    /// Wall-clock time from the last worker starting until every worker
+    /// finished
+    pub workers_wall: Duration,
+    /// Deriving the key from the worker outputs, including any pepper
+    pub derive_key: Duration,
+    /// Decrypting and checking the verification phrase
+    pub decrypt: Duration,
+    /// Total wall-clock time of the verification
+    pub total: Duration,
+}
+
+impl VerifyProfile {
+    /// Sum of the sequential phases; close to `total`
+    pub fn phases_sum(&self) -> Duration {
+        self.parse + self.thread_spawn + self.workers_wall + self.derive_key + self.decrypt
+    }
+}
+
+/// Verify a password and report where the time went
+/// 
+/// If `workers_wall` is much larger than the longest `worker_compute`,
+/// threads were waiting for a core (oversubscription); if the worker
+/// compute times themselves dominate, the memory parameter is the cost.
+/// 
+/// Like `verify_password`, a peppered hash returns `PepperUnavailable`.
+pub fn verify_profiled(password: &str, stored_hash: &str) -> Result<(bool, VerifyProfile)> {
+    let start = Instant::now();
+
+    let hash = parse_for_verification(stored_hash)?;
+    hash.check_phrase_id(VERIFICATION_PHRASE)?;
+    let params = hash.params();
+    check_computable(&params)?;
+    let parse = start.elapsed();
+
+    let mut password = params.options.prepare_password(password);
+    let spawn_start = Instant::now();
+    let timed_outputs = run_parallel(thread_indices(params.threads)?, params.thread_stack_size, |thread_index| {
+        let compute_start = Instant::now();
+        let output = worker_output(&password, &hash.salt, thread_index, &params);
+        (output, compute_start, compute_start.elapsed())
+    });
+    let workers_end = Instant::now();
+    if let Cow::Owned(normalized) = &mut password {
+        normalized.zeroize();
+    }
+
+    // Spawning ends when the last worker starts running
+    let mut last_start = spawn_start;
+    let mut thread_outputs = Zeroizing::new(Vec::new());
+    let mut worker_compute = Vec::new();
+    for (output, compute_start, compute) in timed_outputs? {
+        thread_outputs.push(output?);
+        last_start = last_start.max(compute_start);
+        worker_compute.push(compute);
+    }
+    let thread_spawn = last_start - spawn_start;
+    let workers_wall = workers_end - last_start;
+
+    let derive_start = Instant::now();
+    let key = pepper_key(derive_key_with(&thread_outputs, &params.options), &params)?;
+    let derive_key = derive_start.elapsed();
+
+    let decrypt_start = Instant::now();
+    let is_valid = matches!(
+        hash.decrypt_phrase(&key, VERIFICATION_PHRASE),
+        Ok(phrase) if is_verification_phrase(&phrase)
+    );
+    let decrypt = decrypt_start.elapsed();
+
+    let profile = VerifyProfile {
+        parse,
+        thread_spawn,
+        worker_compute,
+        workers_wall,
+        derive_key,
+        decrypt,
+        total: start.elapsed(),
+    };
+
+    Ok((is_valid, profile))
+}
+
+#[cfg(all(test, feature = "hashing"))]
+mod tests {
+    use super::*;
+    use crate::error::SinkproofError;
+    use crate::hasher::{hash_password, hash_password_with};
+    use crate::params::HashParams;
+
+    #[test]
+    fn test_phases_sum_to_total() {
+        let stored = hash_password("password", 2, 1).expect("Failed to hash").to_string();
+
+        let (is_valid, profile) = verify_profiled("password", &stored).expect("Failed to verify");
+        assert!(is_valid);
+        assert_eq!(profile.worker_compute.len(), 2);
+        assert!(profile.phases_sum() <= profile.total);
+        // Only the few instructions between phases are unaccounted for
+        assert!(profile.total - profile.phases_sum() < Duration::from_millis(50));
+        assert!(profile.worker_compute.iter().all(|d| *d <= profile.total));
+
+        let (is_valid, _) = verify_profiled("wrong", &stored).expect("Failed to verify");
+        assert!(!is_valid);
+    }
+
+    #[test]
+    fn test_peppered_hash_needs_the_pepper() {
+        let path = std::env::temp_dir().join(format!("sinkproof-profile-pepper-{}", std::process::id()));
+        std::fs::write(&path, b"server secret").expect("Failed to write pepper file");
+        let params = HashParams::new(1, 1).pepper_from_file(&path);
+        let stored = hash_password_with("password", &params).expect("Failed to hash").to_string();
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(verify_profiled("password", &stored), Err(SinkproofError::PepperUnavailable(_))));
+    }
+}
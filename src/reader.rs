@@ -0,0 +1,77 @@
+//! Bounds-checked cursor shared by the binary parsers
+
+use crate::error::{Result, SinkproofError};
+
+/// Minimal cursor over untrusted bytes
+/// 
+/// Every length is checked against the remaining input before anything is
+/// allocated, so a lying length prefix yields an error rather than a huge
+/// allocation or a panic.
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    /// What is being parsed, for error messages (e.g. "checkpoint")
+    context: &'static str,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], context: &'static str) -> Self {
+        ByteReader { bytes, context }
+    }
+
+    /// Error for malformed input
+    pub(crate) fn invalid(&self, reason: &str) -> SinkproofError {
+        SinkproofError::InvalidFormat(format!("Invalid {}: {}", self.context, reason))
+    }
+
+    #[cfg(feature = "hashing")]
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(self.invalid("unexpected end of data"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    /// Copy `len` bytes into a new buffer, reserving fallibly
+    pub(crate) fn take_vec(&mut self, len: usize) -> Result<Vec<u8>> {
+        let bytes = self.take(len)?;
+        let mut buffer = Vec::new();
+        buffer
+            .try_reserve_exact(len)
+            .map_err(|_| self.invalid("allocation failed"))?;
+        buffer.extend_from_slice(bytes);
+        Ok(buffer)
+    }
+
+    /// Read a `u32` length prefix followed by that many bytes
+    pub(crate) fn read_prefixed(&mut self) -> Result<Vec<u8>> {
+        let len = self.read_u32()? as usize;
+        self.take_vec(len)
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Fail unless every byte has been consumed
+    pub(crate) fn finish(&self) -> Result<()> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(self.invalid("trailing bytes"))
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Aggregated audit report over a collection of stored hashes
+//! 
+//! For security teams reviewing a password database: one pass over the
+//! stored strings, without passwords, summarizing what the inspection
+//! methods of `SinkproofHash` report one hash at a time.
+
+use crate::policy::HashPolicy;
+use crate::storage::SinkproofHash;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Findings of `audit` over a collection of hashes
+/// 
+/// Entries are referred to by their index in the audited slice.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AuditReport {
+    /// Number of entries audited, malformed ones included
+    pub total: usize,
+    /// Parsed hashes per cost level (`SinkproofHash::verify_cost_units`)
+    pub cost_levels: BTreeMap<u64, usize>,
+    /// Groups of entries sharing a salt, each in input order
+    pub duplicate_salts: Vec<Vec<usize>>,
+    /// Entries that fail to parse as a `SinkproofHash`
+    pub malformed: Vec<usize>,
+    /// Parsed hashes that do not meet the policy
+    pub below_policy: Vec<usize>,
+    /// Oldest crate version recorded in a v2 hash
+    /// 
+    /// Hashes carry no creation time; the `ver=` option is the only age
+    /// signal, so this is the closest the report gets to "oldest hash".
+    /// v1 hashes record no version and are not considered.
+    pub oldest_producer_version: Option<String>,
+}
+
+/// Parse every entry of `hashes` and aggregate the findings
+/// 
+/// Malformed entries (including empty ones) are counted and otherwise
+/// skipped, so one bad row never hides the rest of the report.
+pub fn audit(hashes: &[&str], policy: &HashPolicy) -> AuditReport {
+    let mut report = AuditReport { total: hashes.len(), ..AuditReport::default() };
+    let mut salts: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+
+    for (index, stored) in hashes.iter().enumerate() {
+        let hash = match stored.parse::<SinkproofHash>() {
+            Ok(hash) => hash,
+            Err(_) => {
+                report.malformed.push(index);
+                continue;
+            }
+        };
+
+        *report.cost_levels.entry(hash.verify_cost_units()).or_insert(0) += 1;
+        salts.entry(hash.salt().to_vec()).or_default().push(index);
+        if !policy.is_met_by(&hash) {
+            report.below_policy.push(index);
+        }
+        if let Some(version) = hash.producer_version() {
+            let is_older = report
+                .oldest_producer_version
+                .as_deref()
+                .is_none_or(|oldest| version_key(version) < version_key(oldest));
+            if is_older {
+                report.oldest_producer_version = Some(version.to_string());
+            }
+        }
+    }
+
+    report.duplicate_salts = salts.into_values().filter(|group| group.len() > 1).collect();
+    report.duplicate_salts.sort();
+    report
+}
+
+/// Numeric components of a dotted version, for ordering (`1.10.0` after
+/// `1.9.2`); leading digits of each component count, the rest is ignored
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map(|part| {
+            let digits = part.find(|c: char| !c.is_ascii_digit()).unwrap_or(part.len());
+            part[..digits].parse().unwrap_or(0)
+        })
+        .collect()
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Audited {} hashes", self.total)?;
+        writeln!(f, "Cost levels (threads x MB x passes):")?;
+        for (cost, count) in &self.cost_levels {
+            writeln!(f, "  {}: {}", cost, count)?;
+        }
+        writeln!(f, "Malformed: {}", self.malformed.len())?;
+        writeln!(f, "Below policy: {}", self.below_policy.len())?;
+        writeln!(
+            f,
+            "Duplicate salts: {} groups, {} hashes",
+            self.duplicate_salts.len(),
+            self.duplicate_salts.iter().map(Vec::len).sum::<usize>()
+        )?;
+        write!(f, "Oldest producer version: {}", self.oldest_producer_version.as_deref().unwrap_or("unknown"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "hashing")]
+    use crate::hasher::{hash_password_with, hash_password_with_salt};
+    #[cfg(feature = "hashing")]
+    use crate::params::HashParams;
+    #[cfg(feature = "hashing")]
+    use crate::storage::with_checksum;
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_audit_mixed_collection() {
+        let params = HashParams::new(1, 1);
+        let salt = [9u8; 32];
+        let first = hash_password_with_salt("alice", &salt, &params).unwrap().to_string();
+        let same_salt = hash_password_with_salt("bob", &salt, &params.clone().passes(2)).unwrap().to_string();
+        let stronger = hash_password_with("carol", &HashParams::new(2, 2)).unwrap().to_string();
+
+        // v2 hashes from older releases
+        let (body, _) = same_salt.rsplit_once(':').unwrap();
+        let old = with_checksum(&body.replace(&format!("ver={}", env!("CARGO_PKG_VERSION")), "ver=0.9.12"));
+        let older = with_checksum(&body.replace(&format!("ver={}", env!("CARGO_PKG_VERSION")), "ver=0.10.0"));
+
+        let hashes = [first.as_str(), "not a hash", same_salt.as_str(), stronger.as_str(), "", &old, &older];
+        let report = audit(&hashes, &HashPolicy::new(2, 1));
+
+        assert_eq!(report.total, 7);
+        assert_eq!(report.malformed, vec![1, 4]);
+        assert_eq!(report.below_policy, vec![0, 2, 5, 6]);
+        assert_eq!(report.duplicate_salts, vec![vec![0, 2, 5, 6]]);
+        assert_eq!(report.cost_levels, BTreeMap::from([(1, 1), (2, 3), (4, 1)]));
+        assert_eq!(report.oldest_producer_version.as_deref(), Some("0.9.12"));
+
+        let summary = report.to_string();
+        assert!(summary.starts_with("Audited 7 hashes"), "{}", summary);
+        assert!(summary.contains("Malformed: 2"), "{}", summary);
+        assert!(summary.contains("Duplicate salts: 1 groups, 4 hashes"), "{}", summary);
+        assert!(summary.contains("Oldest producer version: 0.9.12"), "{}", summary);
+    }
+
+    #[test]
+    fn test_audit_empty_collection() {
+        let report = audit(&[], &HashPolicy::RECOMMENDED);
+        assert_eq!(report, AuditReport::default());
+        assert!(report.to_string().contains("Oldest producer version: unknown"));
+    }
+}
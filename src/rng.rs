@@ -0,0 +1,101 @@
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::cell::RefCell;
+
+/// A cryptographically secure RNG usable as the crate-wide source
+pub trait SecureRng: RngCore + CryptoRng {}
+
+impl<R: RngCore + CryptoRng> SecureRng for R {}
+
+thread_local! {
+    static RNG: RefCell<Option<Box<dyn SecureRng>>> = const { RefCell::new(None) };
+}
+
+/// Replace the RNG used for salts and AES-GCM nonces on the current thread
+///
+/// Defaults to `OsRng`. The setting is per thread, so it only affects
+/// hashes created on the thread that called it. Production code must only
+/// install a cryptographically secure, properly seeded RNG: a predictable
+/// RNG yields predictable salts and nonces, and a repeated nonce under the
+/// same key breaks AES-GCM. Seeded RNGs are meant for tests and
+/// reproducible fixtures.
+pub fn set_rng<R: RngCore + CryptoRng + 'static>(rng: R) {
+    RNG.with(|cell| *cell.borrow_mut() = Some(Box::new(rng)));
+}
+
+/// Go back to `OsRng` on the current thread
+pub fn reset_rng() {
+    RNG.with(|cell| *cell.borrow_mut() = None);
+}
+
+/// Fill `buf` from the configured RNG
+pub(crate) fn fill_random(buf: &mut [u8]) {
+    RNG.with(|cell| match cell.borrow_mut().as_mut() {
+        Some(rng) => rng.fill_bytes(buf),
+        None => OsRng.fill_bytes(buf),
+    });
+}
+
+/// `RngCore` handle onto the configured RNG, for APIs that take an RNG
+#[cfg(feature = "seal")]
+pub(crate) struct ConfiguredRng;
+
+#[cfg(feature = "seal")]
+impl RngCore for ConfiguredRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        fill_random(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        fill_random(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_random(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        fill_random(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "seal")]
+impl CryptoRng for ConfiguredRng {}
+
+#[cfg(all(test, feature = "hashing"))]
+mod tests {
+    use super::*;
+    use crate::encryption::encrypt_phrase;
+    use crate::hasher::generate_salt;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_seeded_rng_is_reproducible() {
+        let key = b"this_is_a_32_byte_key_for_aes!!";
+
+        set_rng(StdRng::seed_from_u64(42));
+        let salt1 = generate_salt();
+        let encrypted1 = encrypt_phrase(key).expect("Encryption failed");
+
+        set_rng(StdRng::seed_from_u64(42));
+        let salt2 = generate_salt();
+        let encrypted2 = encrypt_phrase(key).expect("Encryption failed");
+
+        reset_rng();
+
+        assert_eq!(salt1, salt2);
+        assert_eq!(encrypted1, encrypted2);
+    }
+
+    #[test]
+    fn test_default_rng_is_random() {
+        reset_rng();
+        assert_ne!(generate_salt(), generate_salt());
+    }
+}
@@ -0,0 +1,77 @@
+use sha2::{Sha256, Digest};
+
+/// Composes a 32-byte salt from several byte segments
+///
+/// Useful for sharded designs that combine e.g. a per-shard prefix with a
+/// per-user random suffix. Each segment is length-prefixed before hashing
+/// with SHA-256, so segment boundaries and order both matter: `["ab", "c"]`
+/// and `["a", "bc"]` produce different salts. The result is what gets stored
+/// in the hash, so verification does not need the segments.
+#[derive(Debug, Clone, Default)]
+pub struct SaltBuilder {
+    segments: Vec<Vec<u8>>,
+}
+
+impl SaltBuilder {
+    /// Start an empty builder
+    pub fn new() -> Self {
+        SaltBuilder::default()
+    }
+
+    /// Append a segment
+    pub fn segment(mut self, bytes: &[u8]) -> Self {
+        self.segments.push(bytes.to_vec());
+        self
+    }
+
+    /// Produce the 32-byte salt
+    pub fn build(&self) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        for segment in &self.segments {
+            hasher.update((segment.len() as u64).to_le_bytes());
+            hasher.update(segment);
+        }
+        hasher.finalize().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "hashing")]
+    use crate::hasher::hash_password_with_salt;
+    #[cfg(feature = "hashing")]
+    use crate::params::HashParams;
+    #[cfg(feature = "hashing")]
+    use crate::verifier::verify_password;
+
+    #[test]
+    fn test_same_segments_same_salt() {
+        let a = SaltBuilder::new().segment(b"shard-7").segment(b"user-salt").build();
+        let b = SaltBuilder::new().segment(b"shard-7").segment(b"user-salt").build();
+
+        assert_eq!(a.len(), 32);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_order_and_boundaries_matter() {
+        let forward = SaltBuilder::new().segment(b"shard-7").segment(b"user-salt").build();
+        let reversed = SaltBuilder::new().segment(b"user-salt").segment(b"shard-7").build();
+        let split = SaltBuilder::new().segment(b"ab").segment(b"c").build();
+        let moved = SaltBuilder::new().segment(b"a").segment(b"bc").build();
+
+        assert_ne!(forward, reversed);
+        assert_ne!(split, moved);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_composed_salt_hashes_and_verifies() {
+        let salt = SaltBuilder::new().segment(b"shard-7").segment(b"user-salt").build();
+        let hash = hash_password_with_salt("password", &salt, &HashParams::new(1, 1)).expect("Failed to hash");
+
+        assert_eq!(hash.salt, salt);
+        assert!(verify_password("password", &hash.to_string()).expect("Failed to verify"));
+    }
+}
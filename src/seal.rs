@@ -0,0 +1,86 @@
+//! Derived keys sealed to a recipient's public key, for envelope encryption
+//! 
+//! Enabled with the `seal` feature. Instead of using the memory-hard key
+//! locally, `derive_and_seal` encrypts it to a recipient's X25519 public
+//! key with a libsodium-compatible sealed box (`crypto_box_seal`):
+//! 
+//! 1. A fresh ephemeral X25519 key pair is generated for every call.
+//! 2. The nonce is BLAKE2b(ephemeral public key || recipient public key),
+//!    truncated to 24 bytes.
+//! 3. The key is encrypted with XSalsa20-Poly1305 under the X25519 shared
+//!    secret of the ephemeral secret key and the recipient public key.
+//! 
+//! The output is `ephemeral public key (32 bytes) || ciphertext || tag
+//! (16 bytes)`. Only the holder of the recipient's secret key can open it,
+//! e.g. with libsodium's `crypto_box_seal_open`; the sender cannot, and the
+//! box does not authenticate who sealed it.
+
+use crate::error::{Result, SinkproofError};
+use crate::hasher::compute_key;
+use crate::params::HashParams;
+use crate::rng::ConfiguredRng;
+use crypto_box::PublicKey;
+
+/// Name reported when the sealed-box primitives are not permitted
+const SEAL_SCHEME: &str = "x25519-xsalsa20poly1305";
+
+/// Derive the memory-hard key for `password` and `salt` and seal it to
+/// `recipient_pubkey`
+/// 
+/// # Arguments
+/// * `password` - The password to derive the key from
+/// * `salt` - Salt for the derivation; store it alongside the sealed key
+/// * `params` - Cost parameters
+/// * `recipient_pubkey` - The recipient's 32-byte X25519 public key
+/// 
+/// # Returns
+/// The sealed box described in the module documentation. X25519 and
+/// XSalsa20 are not FIPS-approved, so `fips` builds return
+/// `AlgorithmNotPermitted`.
+pub fn derive_and_seal(password: &str, salt: &[u8], params: &HashParams, recipient_pubkey: &[u8]) -> Result<Vec<u8>> {
+    if cfg!(feature = "fips") {
+        return Err(SinkproofError::AlgorithmNotPermitted(SEAL_SCHEME));
+    }
+    params.validate()?;
+
+    let recipient = PublicKey::from_slice(recipient_pubkey).map_err(|_| {
+        SinkproofError::EncryptionFailed(format!(
+            "Invalid recipient public key: expected 32 bytes, got {}",
+            recipient_pubkey.len()
+        ))
+    })?;
+
+    let key = compute_key(password, salt, params)?;
+    recipient
+        .seal(&mut ConfiguredRng, &key)
+        .map_err(|e| SinkproofError::EncryptionFailed(e.to_string()))
+}
+
+#[cfg(all(test, not(feature = "fips")))]
+mod tests {
+    use super::*;
+    use crypto_box::aead::OsRng;
+    use crypto_box::SecretKey;
+
+    #[test]
+    fn test_recipient_unseals_derived_key() {
+        let params = HashParams::new(1, 1);
+        let salt = b"sixteen byte salt";
+        let recipient = SecretKey::generate(&mut OsRng);
+
+        let sealed = derive_and_seal("password", salt, &params, recipient.public_key().as_bytes())
+            .expect("Failed to seal");
+
+        let key = recipient.unseal(&sealed).expect("Failed to unseal");
+        assert_eq!(key, *compute_key("password", salt, &params).unwrap());
+
+        let other = SecretKey::generate(&mut OsRng);
+        assert!(other.unseal(&sealed).is_err());
+    }
+
+    #[test]
+    fn test_invalid_public_key() {
+        let result = derive_and_seal("password", b"salt", &HashParams::new(1, 1), &[0u8; 31]);
+        assert!(matches!(result, Err(SinkproofError::EncryptionFailed(_))));
+    }
+}
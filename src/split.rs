@@ -0,0 +1,84 @@
+//! Passwords made of two separately held factors (2-of-2)
+//! 
+//! For high-value accounts whose secret is split in two parts that are
+//! stored or entered separately (e.g. one memorized, one on a device).
+//! The factors are combined with HMAC-SHA256 into the password the workers
+//! see, so both are needed to verify and a guess at one factor is useless
+//! without the other.
+//! 
+//! This is factor combination, not secret sharing: neither factor can be
+//! recovered from the other, and losing either one makes the hash
+//! unverifiable for good.
+
+use crate::error::Result;
+#[cfg(feature = "hashing")]
+use crate::hasher::hash_password_with;
+#[cfg(feature = "hashing")]
+use crate::params::HashParams;
+#[cfg(feature = "hashing")]
+use crate::storage::SinkproofHash;
+use crate::verifier::verify_password;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// HMAC key separating combined factors from other uses of the hash
+const SPLIT_LABEL: &[u8] = b"Sinkproof split factors";
+
+/// Hash the combination of two factors
+/// 
+/// The stored hash is an ordinary Sinkproof hash of the combined secret;
+/// verify it with `verify_split`.
+#[cfg(feature = "hashing")]
+pub fn hash_split(factor_a: &[u8], factor_b: &[u8], params: &HashParams) -> Result<SinkproofHash> {
+    hash_password_with(&combine_factors(factor_a, factor_b), params)
+}
+
+/// Verify two factors against a hash created by `hash_split`
+/// 
+/// # Returns
+/// `Ok(true)` only if both factors are correct and in the same order,
+/// `Ok(false)` otherwise, `Err` if the stored hash cannot be parsed
+pub fn verify_split(factor_a: &[u8], factor_b: &[u8], stored_hash: &str) -> Result<bool> {
+    verify_password(&combine_factors(factor_a, factor_b), stored_hash)
+}
+
+/// HMAC-SHA256 over both factors, hex-encoded
+/// 
+/// `factor_a` is length-prefixed so that moving bytes between the factors
+/// changes the result.
+fn combine_factors(factor_a: &[u8], factor_b: &[u8]) -> Zeroizing<String> {
+    let mut input = Zeroizing::new(Vec::with_capacity(8 + factor_a.len() + factor_b.len()));
+    input.extend_from_slice(&(factor_a.len() as u64).to_be_bytes());
+    input.extend_from_slice(factor_a);
+    input.extend_from_slice(factor_b);
+
+    // HKDF-Extract is HMAC with the salt as key
+    let (combined, _) = Hkdf::<Sha256>::extract(Some(SPLIT_LABEL), &input);
+    Zeroizing::new(hex::encode(combined))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_split_requires_both_factors() {
+        let stored = hash_split(b"memorized", b"device secret", &HashParams::new(1, 1))
+            .expect("Failed to hash")
+            .to_string();
+
+        assert!(verify_split(b"memorized", b"device secret", &stored).expect("Failed to verify"));
+        assert!(!verify_split(b"memorised", b"device secret", &stored).expect("Failed to verify"));
+        assert!(!verify_split(b"memorized", b"device secret!", &stored).expect("Failed to verify"));
+        assert!(!verify_split(b"device secret", b"memorized", &stored).expect("Failed to verify"));
+        assert!(!verify_password("memorizeddevice secret", &stored).expect("Failed to verify"));
+    }
+
+    #[test]
+    fn test_factor_boundary_matters() {
+        assert_ne!(combine_factors(b"ab", b"c"), combine_factors(b"a", b"bc"));
+        assert_eq!(combine_factors(b"a", b"bc"), combine_factors(b"a", b"bc"));
+    }
+}
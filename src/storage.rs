@@ -1,70 +1,1113 @@
 use base64::{Engine as _, engine::general_purpose};
+#[cfg(feature = "hashing")]
+use crate::encryption::encrypt_bytes_with_aad;
+use crate::encryption::{
+    current_phrase_id, decrypt_bytes_with_aad, phrase_id, Cipher, NONCE_LEN, TAG_LEN, VERIFICATION_PHRASE,
+};
+use crate::compress;
+use crate::hasher::{is_valid_worker_output_len, WORKER_OUTPUT_LEN};
+use crate::error::{Result, SinkproofError};
+use crate::format::{ColonFormat, HashSerializer, PhcFormat};
+use crate::params::{
+    Combiner, HashAlgo, HashOptions, HashParams, KeyDigest, NormalizationForm, Variant, MAX_MEMORY_MB, MAX_PASSES, MAX_THREADS,
+    MAX_TOTAL_MEMORY_MB,
+};
+use crate::reader::ByteReader;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io;
+use zeroize::Zeroize;
+
+/// Leading bytes of the binary encoding
+pub(crate) const BINARY_MAGIC: &[u8] = b"SPKH";
+
+/// Layout version of a stored hash
+/// 
+/// The version token is the second field of every layout and decides how
+/// the rest is parsed:
+/// 
+/// - v1: `Sinkproof:v1:threads:memory_mb:salt:encrypted_phrase`
+/// - v2: `Sinkproof:v2:threads:memory_mb:options:salt:encrypted_phrase:checksum`
+/// 
+/// v2 adds the options field (algorithm identifier and every other
+/// non-default choice, see `HashOptions`) and a trailing checksum. v1
+/// hashes are never rewritten and keep verifying unchanged; hashes whose
+/// options equal `HashOptions::v1()` are still written as v1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HashVersion {
+    /// The original layout; every option has its default value
+    V1,
+    /// Adds the options field and a checksum
+    V2,
+}
+
+impl HashVersion {
+    /// Token used for this version in the storage formats
+    pub fn name(self) -> &'static str {
+        match self {
+            HashVersion::V1 => "v1",
+            HashVersion::V2 => "v2",
+        }
+    }
+
+    /// Look up a version by its token
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "v1" => Some(HashVersion::V1),
+            "v2" => Some(HashVersion::V2),
+            _ => None,
+        }
+    }
+
+    /// `from_name`, failing with `InvalidFormat` for unknown versions
+    pub(crate) fn parse(name: &str) -> Result<Self> {
+        Self::from_name(name)
+            .ok_or_else(|| SinkproofError::InvalidFormat(format!("Unsupported hash version '{}'", name)))
+    }
+}
 
 /// Represents a complete Sinkproof hash with all parameters
-#[derive(Debug, Clone)]
+/// 
+/// `Debug` prints the redacted form, so accidentally logging a hash with
+/// `{:?}` never leaks the salt or encrypted phrase.
+/// 
+/// With the `serde` feature it serializes as a map of the stored fields,
+/// with the salt and phrase as base64 strings and the v2 options as their
+/// storage-format field (see `SerdeHash`).
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SerdeHash", try_from = "SerdeHash")
+)]
 pub struct SinkproofHash {
-    pub version: String,
+    pub version: HashVersion,
     pub threads: usize,
     pub memory_mb: usize,
     pub salt: Vec<u8>,
     pub encrypted_phrase: Vec<u8>,
+    /// Algorithm choices; always the defaults for v1 hashes
+    pub options: HashOptions,
+    /// Identifier of the verification phrase (see `encryption::phrase_id`);
+    /// only v2 hashes carry one, older hashes use the built-in phrase
+    pub phrase_id: Option<String>,
+    /// Version of this crate that produced the hash (v2 only); informational,
+    /// bound to the encrypted phrase as associated data
+    pub producer_version: Option<String>,
+    /// Application namespace the hash was stored under (`myapp|Sinkproof:...`);
+    /// not part of the hash itself
+    pub namespace: Option<String>,
+}
+
+/// Ceilings on the cost parameters of a parsed hash (see
+/// `SinkproofHash::from_string_with_limits`)
+/// 
+/// The default (`ParseLimits::DEFAULT`) is far below what the build can
+/// verify, sized for a server checking ordinary login hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Largest accepted thread count
+    pub max_threads: usize,
+    /// Largest accepted memory of any one thread, in MB
+    pub max_memory_mb: usize,
+    /// Largest accepted total memory in MB: the thread count times the
+    /// largest thread memory
+    pub max_total_memory_mb: usize,
+    /// Largest accepted pass count
+    pub max_passes: u32,
+}
+
+impl ParseLimits {
+    /// This build's `MAX_THREADS`, `MAX_MEMORY_MB`, `MAX_TOTAL_MEMORY_MB`
+    /// and `MAX_PASSES` (1024 threads, 16384 MB per thread, 64 GB in total
+    /// and 64 passes unless changed at build time), the most it will ever
+    /// verify with.
+    pub const BUILD: ParseLimits = ParseLimits {
+        max_threads: MAX_THREADS,
+        max_memory_mb: MAX_MEMORY_MB,
+        max_total_memory_mb: MAX_TOTAL_MEMORY_MB,
+        max_passes: MAX_PASSES,
+    };
+
+    /// The default: 64 threads, 4096 MB per thread, 8192 MB in total and
+    /// 16 passes
+    /// 
+    /// Several times the cost of any hash `HashParams` suggests, while one
+    /// planted row cannot make a verifier allocate more than 8 GB: every
+    /// verify function parses with these limits (`verify_password_with_limits`
+    /// takes others).
+    pub const DEFAULT: ParseLimits = ParseLimits {
+        max_threads: 64,
+        max_memory_mb: 4096,
+        max_total_memory_mb: 8192,
+        max_passes: 16,
+    };
+
+    /// Limits of at most `max_threads` threads and `max_memory_mb` MB per
+    /// thread, with no tighter total and the build's `MAX_PASSES`
+    pub const fn new(max_threads: usize, max_memory_mb: usize) -> Self {
+        ParseLimits {
+            max_threads,
+            max_memory_mb,
+            max_total_memory_mb: max_threads.saturating_mul(max_memory_mb),
+            max_passes: MAX_PASSES,
+        }
+    }
+
+    /// Accept at most `max_total_memory_mb` MB over all threads
+    pub const fn max_total_memory_mb(mut self, max_total_memory_mb: usize) -> Self {
+        self.max_total_memory_mb = max_total_memory_mb;
+        self
+    }
+
+    /// Accept at most `max_passes` passes
+    pub const fn max_passes(mut self, max_passes: u32) -> Self {
+        self.max_passes = max_passes;
+        self
+    }
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits::DEFAULT
+    }
 }
 
 impl SinkproofHash {
-    /// Serialize the hash to storage format
-    /// Format: Sinkproof:v1:threads:memory:salt_base64:encrypted_phrase_base64
-    pub fn to_string(&self) -> String {
-        let salt_b64 = general_purpose::STANDARD.encode(&self.salt);
-        let phrase_b64 = general_purpose::STANDARD.encode(&self.encrypted_phrase);
-        
-        format!(
-            "Sinkproof:{}:{}:{}:{}:{}",
-            self.version,
-            self.threads,
-            self.memory_mb,
-            salt_b64,
-            phrase_b64
-        )
+    /// Assemble a freshly computed hash for `params`, encrypting the
+    /// verification phrase under `key`
+    #[cfg(feature = "hashing")]
+    pub(crate) fn create(params: &HashParams, salt: Vec<u8>, key: &[u8]) -> Result<Self> {
+        // Only v2 records the phrase id a custom phrase needs
+        let version = match params.verification_phrase {
+            Some(_) => HashVersion::V2,
+            None => params.options.format_version(),
+        };
+        let is_v2 = version == HashVersion::V2;
+
+        let mut hash = SinkproofHash {
+            version,
+            threads: params.threads,
+            memory_mb: params.memory_mb,
+            salt,
+            encrypted_phrase: Vec::new(),
+            options: params.options.clone(),
+            phrase_id: is_v2.then(|| phrase_id(params.phrase())),
+            producer_version: is_v2.then(|| env!("CARGO_PKG_VERSION").to_string()),
+            namespace: None,
+        };
+        let phrase = if params.options.compress_phrase {
+            compress::compress(params.phrase().as_bytes())
+        } else {
+            params.phrase().as_bytes().to_vec()
+        };
+        hash.encrypted_phrase = encrypt_bytes_with_aad(params.options.cipher, key, &phrase, &hash.phrase_aad())?;
+        Ok(hash)
+    }
+
+    /// Decrypt the stored phrase with a candidate key, decompressing it if
+    /// the hash says so
+    /// 
+    /// `expected` is the phrase a match must decrypt to; it bounds the
+    /// decompressed length.
+    pub(crate) fn decrypt_phrase(&self, key: &[u8], expected: &str) -> Result<Vec<u8>> {
+        let phrase = decrypt_bytes_with_aad(self.options.cipher, key, &self.encrypted_phrase, &self.phrase_aad())?;
+        if !self.options.compress_phrase {
+            return Ok(phrase);
+        }
+
+        // Anything longer than the phrase cannot match; the limit also
+        // stops decompression bombs
+        compress::decompress(&phrase, expected.len())
+    }
+
+    /// Associated data of the phrase encryption: the recorded producer
+    /// version, so editing it makes verification fail; empty otherwise,
+    /// which matches hashes made before it was recorded
+    fn phrase_aad(&self) -> Vec<u8> {
+        match &self.producer_version {
+            Some(version) => format!("ver={}", version).into_bytes(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Crate version that produced the hash, e.g. `1.0.0`
+    /// 
+    /// Only recorded in v2 hashes; `None` for v1 hashes and for v2 hashes
+    /// made before the version was recorded.
+    pub fn producer_version(&self) -> Option<&str> {
+        self.producer_version.as_deref()
+    }
+
+    /// Reject an encrypted phrase too short to hold the nonce, the tag and
+    /// the phrase itself
+    /// 
+    /// Base64 truncated at a 4-character boundary still decodes; without
+    /// this a truncated phrase would only fail decryption and look like a
+    /// wrong password. A hash made with another build's phrase (see
+    /// `phrase_id`) or with a compressed phrase is only required to hold a
+    /// non-empty phrase.
+    pub(crate) fn check_phrase_length(&self) -> Result<()> {
+        let phrase_len = match &self.phrase_id {
+            _ if self.options.compress_phrase => 1,
+            Some(id) if *id != current_phrase_id() => 1,
+            _ => VERIFICATION_PHRASE.len(),
+        };
+        let minimum = NONCE_LEN + phrase_len + TAG_LEN;
+        if self.encrypted_phrase.len() < minimum {
+            return Err(SinkproofError::InvalidFormat(format!(
+                "Encrypted phrase too short: expected at least {} bytes, got {} (truncated?)",
+                minimum,
+                self.encrypted_phrase.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check that the hash was created with `phrase`
+    pub(crate) fn check_phrase_id(&self, phrase: &str) -> Result<()> {
+        match &self.phrase_id {
+            Some(id) if *id != phrase_id(phrase) => Err(SinkproofError::PhraseMismatch),
+            _ => Ok(()),
+        }
+    }
+
+    /// Storage format preceded by an application namespace, e.g.
+    /// `myapp|Sinkproof:v1:...`, for stores shared between applications
+    /// 
+    /// Parsing (`str::parse`) accepts the prefixed form and exposes the
+    /// namespace via `namespace()`. `to_string` always returns the bare hash.
+    /// 
+    /// # Returns
+    /// `InvalidConfig` if `namespace` is empty or contains `|` or `:`, which
+    /// would not parse back to the same namespace
+    pub fn to_string_with_namespace(&self, namespace: &str) -> Result<String> {
+        if namespace.is_empty() || namespace.contains(['|', ':']) {
+            return Err(SinkproofError::InvalidConfig(format!(
+                "Invalid namespace '{}': must be non-empty and contain neither '|' nor ':'",
+                namespace
+            )));
+        }
+        Ok(format!("{}|{}", namespace, self))
+    }
+
+    /// Storage format using only URL-unreserved characters
+    /// 
+    /// For hashes embedded in URLs or JWT-like tokens. The salt and phrase
+    /// are written in unpadded URL-safe base64, and every other character
+    /// outside `A-Z a-z 0-9 - . _ ~` (the `:` separators and the v2 options'
+    /// `=`, `,` and `%`) is percent-encoded, so the string needs no further
+    /// escaping. The v2 checksum is the same as `to_string`'s, as it covers
+    /// the standard form. Parse it back with `from_string_url_safe`.
+    pub fn to_string_url_safe(&self) -> String {
+        let prefix = self.prefix();
+        let mut url_safe = format!(
+            "{}:{}:{}",
+            prefix,
+            general_purpose::URL_SAFE_NO_PAD.encode(&self.salt),
+            general_purpose::URL_SAFE_NO_PAD.encode(&self.encrypted_phrase)
+        );
+        if self.version == HashVersion::V2 {
+            let standard = format!(
+                "{}:{}:{}",
+                prefix,
+                general_purpose::STANDARD.encode(&self.salt),
+                general_purpose::STANDARD.encode(&self.encrypted_phrase)
+            );
+            url_safe.push(':');
+            url_safe.push_str(&checksum(&standard));
+        }
+
+        escape_url(&url_safe)
+    }
+
+    /// Parse a hash written by `to_string_url_safe`
+    pub fn from_string_url_safe(url_safe: &str) -> Result<Self> {
+        unescape_field(url_safe)?.parse::<Self>()
+    }
+
+    /// Namespace the hash was parsed with, if it had one
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Storage format with the salt and phrase masked, for debug logging
+    /// 
+    /// Example: `Sinkproof:v1:4:64:<salt:32B>:<phrase:redacted>`
+    pub fn redacted(&self) -> String {
+        format!("{}:<salt:{}B>:<phrase:redacted>", self.prefix(), self.salt.len())
+    }
+
+    /// Parameters needed to recompute this hash
+    pub fn params(&self) -> HashParams {
+        HashParams {
+            options: self.options.clone(),
+            ..HashParams::new(self.threads, self.memory_mb)
+        }
+    }
+
+    /// Stable, non-reversible identifier for this hash
+    /// 
+    /// The first 16 bytes of SHA-256 over the serialized hash, encoded as
+    /// unpadded base64url (22 characters). Useful for deduplication or as a
+    /// foreign key. It is not a security token: anyone holding the stored
+    /// hash can compute it.
+    pub fn id(&self) -> String {
+        let digest = Sha256::digest(self.to_string().as_bytes());
+        general_purpose::URL_SAFE_NO_PAD.encode(&digest[..16])
+    }
+
+    /// Comparable estimate of the work needed to verify this hash
+    /// 
+    /// One unit is one pass of one thread over one MB, so the cost is the
+    /// total memory of all threads times the number of passes; time grows
+    /// proportionally. Rate limiters can subtract it from a token bucket
+    /// before verifying. Saturates instead of overflowing for absurd
+    /// parameters.
+    pub fn verify_cost_units(&self) -> u64 {
+        let memory = match &self.options.per_thread_memory {
+            Some(sizes) => sizes.iter().fold(0u64, |total, &size| total.saturating_add(size as u64)),
+            None => (self.threads as u64).saturating_mul(self.memory_mb as u64),
+        };
+        memory.saturating_mul(u64::from(self.options.passes))
+    }
+
+    /// Salt the hash was computed with
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    /// Salt in standard base64, as it appears in the storage format
+    pub fn salt_base64(&self) -> String {
+        general_purpose::STANDARD.encode(&self.salt)
+    }
+
+    /// AEAD the verification phrase was encrypted with
+    /// 
+    /// Always AES-256-GCM for v1 hashes.
+    pub fn cipher(&self) -> Cipher {
+        self.options.cipher
+    }
+
+    /// Whether verifying this hash on the current machine uses dedicated
+    /// CPU instructions for its cipher
+    /// 
+    /// An AES-256-GCM hash on a CPU without AES instructions falls back to a
+    /// slow constant-time software implementation; dashboards can use this
+    /// to flag such hashes. See `Cipher::is_hardware_accelerated`.
+    pub fn will_use_hardware_accel(&self) -> bool {
+        self.cipher().is_hardware_accelerated()
+    }
+
+    /// Every field before the salt, e.g. `Sinkproof:v1:4:64`
+    pub(crate) fn prefix(&self) -> String {
+        if self.version == HashVersion::V2 {
+            return format!(
+                "Sinkproof:{}:{}:{}:{}",
+                self.version.name(),
+                self.threads,
+                self.memory_mb,
+                encode_options(self)
+            );
+        }
+
+        format!("Sinkproof:{}:{}:{}", self.version.name(), self.threads, self.memory_mb)
     }
 
     /// Parse a hash from storage format
-    pub fn from_string(hash_str: &str) -> Result<Self, String> {
+    /// 
+    /// Kept for existing callers; `stored.parse::<SinkproofHash>()` (see
+    /// the `FromStr` impl) is the same parser.
+    #[deprecated(note = "use str::parse / FromStr")]
+    pub fn from_string(hash_str: &str) -> Result<Self> {
+        hash_str.parse()
+    }
+
+    /// Parse like `str::parse`, rejecting hashes whose cost exceeds `limits`
+    /// 
+    /// Stored hashes may be attacker-controlled (e.g. an injected database
+    /// row), and one naming 100000 threads of 4 TB each would exhaust the
+    /// machine that verifies it. Verification parses with
+    /// `ParseLimits::DEFAULT`; use this to reject such rows at load time, or
+    /// with other limits. Returns
+    /// `ThreadLimitExceeded`, `MemoryLimitExceeded`,
+    /// `TotalMemoryLimitExceeded` or `PassLimitExceeded`; every thread of a
+    /// per-thread memory list is checked.
+    pub fn from_string_with_limits(hash_str: &str, limits: &ParseLimits) -> Result<Self> {
+        let hash = hash_str.parse::<Self>()?;
+        hash.check_limits(limits)?;
+        Ok(hash)
+    }
+
+    /// Reject a cost above `limits` (see `from_string_with_limits`)
+    pub(crate) fn check_limits(&self, limits: &ParseLimits) -> Result<()> {
+        if self.threads > limits.max_threads {
+            return Err(SinkproofError::ThreadLimitExceeded { requested: self.threads, max: limits.max_threads });
+        }
+
+        let largest = self.params().largest_thread_memory_mb();
+        if largest > limits.max_memory_mb {
+            return Err(SinkproofError::MemoryLimitExceeded { requested: largest, max: limits.max_memory_mb });
+        }
+
+        match self.threads.checked_mul(largest) {
+            Some(total) if total <= limits.max_total_memory_mb => {}
+            total => {
+                return Err(SinkproofError::TotalMemoryLimitExceeded {
+                    requested: total.unwrap_or(usize::MAX),
+                    max: limits.max_total_memory_mb,
+                })
+            }
+        }
+
+        if self.options.passes > limits.max_passes {
+            return Err(SinkproofError::PassLimitExceeded { requested: self.options.passes, max: limits.max_passes });
+        }
+        Ok(())
+    }
+
+    /// Encode in PHC string format (see `PhcFormat`)
+    /// 
+    /// Carries every field of the colon format, so `from_phc` followed by
+    /// `to_string` reproduces the output of `to_string` byte for byte
+    /// (the v2 checksum is recomputed). Use this to migrate stored hashes
+    /// between formats without the password.
+    pub fn to_phc(&self) -> String {
+        PhcFormat.serialize(self)
+    }
+
+    /// Parse a hash written by `to_phc`
+    pub fn from_phc(phc: &str) -> Result<Self> {
+        let hash = PhcFormat.deserialize(phc)?;
+        hash.check_phrase_length()?;
+        Ok(hash)
+    }
+
+    /// Extract only the salt from a stored hash, for lightweight indexing
+    /// 
+    /// Checks the name and locates the salt field, but does not parse the
+    /// parameters, decode the phrase or verify the v2 checksum; use
+    /// `str::parse` to validate the whole hash.
+    pub fn from_string_salt_only(hash_str: &str) -> Result<Vec<u8>> {
+        if hash_str.trim().is_empty() {
+            return Err(SinkproofError::EmptyHash);
+        }
+
+        let (_, hash_str) = split_namespace(hash_str);
         let parts: Vec<&str> = hash_str.split(':').collect();
-        
-        if parts.len() != 6 {
-            return Err(format!("Invalid hash format: expected 6 parts, got {}", parts.len()));
+        let salt_index = match parts.get(1) {
+            Some(&"v2") => 5,
+            _ => 4,
+        };
+        if parts[0] != "Sinkproof" || parts.len() < salt_index + 2 {
+            return Err(SinkproofError::InvalidFormat("Invalid hash format: no salt field".to_string()));
         }
 
-        if parts[0] != "Sinkproof" {
-            return Err(format!("Invalid hash name: expected 'Sinkproof', got '{}'", parts[0]));
+        decode_standard(parts[salt_index])
+            .map_err(|e| SinkproofError::Base64Decode(format!("Invalid salt encoding: {}", e)))
+    }
+
+    /// Compact binary encoding, e.g. for `BYTEA`/`BLOB` columns
+    /// 
+    /// Layout (integers little-endian, `bytes` = u32 length + data):
+    /// `SPKH | version bytes | threads u64 | memory_mb u64 | options bytes |
+    /// salt bytes | encrypted_phrase bytes`. The options are the v2 options
+    /// field, empty for v1.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let options = if self.version == HashVersion::V2 {
+            encode_options(self)
+        } else {
+            String::new()
+        };
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(BINARY_MAGIC);
+        push_prefixed(&mut bytes, self.version.name().as_bytes());
+        bytes.extend_from_slice(&(self.threads as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.memory_mb as u64).to_le_bytes());
+        for field in [options.as_bytes(), &self.salt, &self.encrypted_phrase] {
+            push_prefixed(&mut bytes, field);
         }
 
-        let version = parts[1].to_string();
-        
-        let threads = parts[2]
-            .parse::<usize>()
-            .map_err(|e| format!("Invalid threads value: {}", e))?;
+        bytes
+    }
 
-        let memory_mb = parts[3]
-            .parse::<usize>()
-            .map_err(|e| format!("Invalid memory value: {}", e))?;
+    /// Parse the encoding produced by `to_bytes`
+    /// 
+    /// Every length prefix is checked against the remaining input before
+    /// allocating, so truncated or length-lying input is rejected with
+    /// `SinkproofError::InvalidFormat` instead of panicking. A truncated
+    /// phrase is rejected like in the text format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.is_empty() {
+            return Err(SinkproofError::EmptyHash);
+        }
 
-        let salt = general_purpose::STANDARD
-            .decode(parts[4])
-            .map_err(|e| format!("Invalid salt encoding: {}", e))?;
+        let mut reader = ByteReader::new(bytes, "binary hash");
+        if reader.take(BINARY_MAGIC.len())? != BINARY_MAGIC {
+            return Err(reader.invalid("missing header"));
+        }
 
-        let encrypted_phrase = general_purpose::STANDARD
-            .decode(parts[5])
-            .map_err(|e| format!("Invalid encrypted phrase encoding: {}", e))?;
+        let version = String::from_utf8(reader.read_prefixed()?)
+            .map_err(|_| reader.invalid("version is not UTF-8"))?;
+        let version = HashVersion::parse(&version)?;
+        let threads = usize::try_from(reader.read_u64()?).map_err(|_| reader.invalid("threads out of range"))?;
+        let memory_mb = usize::try_from(reader.read_u64()?).map_err(|_| reader.invalid("memory out of range"))?;
+        let options = String::from_utf8(reader.read_prefixed()?)
+            .map_err(|_| reader.invalid("options are not UTF-8"))?;
+        let salt = reader.read_prefixed()?;
+        let encrypted_phrase = reader.read_prefixed()?;
+        reader.finish()?;
 
-        Ok(SinkproofHash {
+        let field = if version == HashVersion::V2 {
+            parse_options(&options)?
+        } else if options.is_empty() {
+            OptionsField::v1()
+        } else {
+            return Err(reader.invalid("options require v2"));
+        };
+
+        let hash = field.into_hash(version, threads, memory_mb, salt, encrypted_phrase);
+        hash.check_phrase_length()?;
+        Ok(hash)
+    }
+
+    /// Parse a hash, unwrapping one layer of accidental base64 encoding
+    /// 
+    /// Opt-in recovery for stores where the whole serialized hash was
+    /// base64-encoded a second time. Only a single layer is removed.
+    pub fn from_string_unwrapping(hash_str: &str) -> Result<Self> {
+        match unwrap_double_encoded(hash_str) {
+            Some(inner) => inner.parse::<Self>(),
+            None => hash_str.parse::<Self>(),
+        }
+    }
+
+    /// Write the storage format (as `to_string`) followed by a newline
+    /// 
+    /// The fields go straight to `writer`; only the base64 encodings are
+    /// buffered. Pairs with `read_from` for files or sockets holding one
+    /// hash per line.
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let prefix = self.prefix();
+        let salt_b64 = general_purpose::STANDARD.encode(&self.salt);
+        let phrase_b64 = general_purpose::STANDARD.encode(&self.encrypted_phrase);
+        let body: [&[u8]; 5] = [prefix.as_bytes(), b":", salt_b64.as_bytes(), b":", phrase_b64.as_bytes()];
+
+        for part in body {
+            writer.write_all(part)?;
+        }
+        if self.version == HashVersion::V2 {
+            let mut hasher = Sha256::new();
+            for part in body {
+                hasher.update(part);
+            }
+            write!(writer, ":{}", hex::encode(&hasher.finalize()[..4]))?;
+        }
+        writer.write_all(b"\n")
+    }
+
+    /// Read and parse one line written by `write_to`
+    /// 
+    /// Returns `Ok(None)` at end of input. A line that does not parse is
+    /// reported as `io::ErrorKind::InvalidData` wrapping the
+    /// `SinkproofError`.
+    pub fn read_from<R: io::BufRead>(reader: &mut R) -> io::Result<Option<Self>> {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.strip_suffix('\n').unwrap_or(&line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        line.parse::<Self>()
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Overwrite a string's buffer with zeros and leave it empty
+/// 
+/// Useful for a serialized hash (or a password) once it has been stored,
+/// since `String` does not clear its memory on drop. This only wipes this
+/// buffer: clones, earlier reallocations and copies made by other code
+/// (e.g. a database driver) are not affected.
+pub fn wipe_string(s: &mut String) {
+    s.zeroize();
+}
+
+/// Re-emit a stored hash in the canonical colon format
+/// 
+/// Accepts every encoding variant parsing does (unpadded or URL-safe
+/// base64) and returns what `to_string` writes: standard padded base64 and,
+/// for v2, a recomputed checksum. A namespace prefix is kept. No password
+/// is needed, so a batch job can bring a whole database to one
+/// representation; canonical input is returned unchanged.
+pub fn normalize_hash_string(hash_str: &str) -> Result<String> {
+    let hash = hash_str.parse::<SinkproofHash>()?;
+    Ok(match hash.namespace() {
+        Some(namespace) => hash.to_string_with_namespace(namespace)?,
+        None => hash.to_string(),
+    })
+}
+
+/// Split a leading `namespace|` segment off a stored hash
+/// 
+/// A `|` after the first colon belongs to the hash (e.g. inside a label).
+pub(crate) fn split_namespace(hash_str: &str) -> (Option<&str>, &str) {
+    match hash_str.split_once('|') {
+        Some((namespace, rest)) if !namespace.contains(':') => (Some(namespace), rest),
+        _ => (None, hash_str),
+    }
+}
+
+/// Append `field` with a u32 length prefix
+fn push_prefixed(bytes: &mut Vec<u8>, field: &[u8]) {
+    bytes.extend_from_slice(&(field.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(field);
+}
+
+/// Truncated SHA-256 checksum of a serialized v2 hash body, in hex
+pub(crate) fn checksum(body: &str) -> String {
+    hex::encode(&Sha256::digest(body.as_bytes())[..4])
+}
+
+/// `body` with its v2 checksum appended, for tests that build or edit v2
+/// strings
+#[cfg(test)]
+pub(crate) fn with_checksum(body: &str) -> String {
+    format!("{}:{}", body, checksum(body))
+}
+
+/// Encrypted phrase field of the hand-written test hashes, as a literal so
+/// it can be spliced into them with `concat!`; `(unpadded)` drops the `=`
+#[cfg(test)]
+macro_rules! test_phrase {
+    () => {
+        concat!(test_phrase!(unpadded), "=")
+    };
+    (unpadded) => {
+        "BAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYnKCkqKywtLi8wMTIzNDU2Nzg"
+    };
+}
+#[cfg(test)]
+pub(crate) use test_phrase;
+
+/// Decode standard or URL-safe base64, with or without `=` padding
+/// 
+/// Hashes copied through systems that strip padding or switch to the
+/// URL-safe alphabet still parse; the error of the padded standard attempt
+/// is reported if all fail.
+pub(crate) fn decode_standard(value: &str) -> std::result::Result<Vec<u8>, base64::DecodeError> {
+    general_purpose::STANDARD.decode(value).or_else(|e| {
+        [general_purpose::STANDARD_NO_PAD, general_purpose::URL_SAFE, general_purpose::URL_SAFE_NO_PAD]
+            .iter()
+            .find_map(|engine| engine.decode(value).ok())
+            .ok_or(e)
+    })
+}
+
+/// Decode `input` if it is base64 wrapping a `Sinkproof:` string
+pub(crate) fn unwrap_double_encoded(input: &str) -> Option<String> {
+    let trimmed = input.trim();
+    if trimmed.contains(':') {
+        return None;
+    }
+
+    let decoded = decode_standard(trimmed).ok()?;
+    let inner = String::from_utf8(decoded).ok()?;
+
+    inner.starts_with("Sinkproof:").then_some(inner)
+}
+
+/// Structural equality over every field, including the real salt and
+/// phrase (unlike `Debug`) and the namespace
+/// 
+/// This is not verification: the same password hashed twice gives unequal
+/// hashes because the salts differ.
+impl PartialEq for SinkproofHash {
+    fn eq(&self, other: &Self) -> bool {
+        // Destructured so a new field cannot be forgotten here
+        let SinkproofHash {
             version,
             threads,
             memory_mb,
             salt,
             encrypted_phrase,
-        })
+            options,
+            phrase_id,
+            producer_version,
+            namespace,
+        } = self;
+
+        *version == other.version
+            && *threads == other.threads
+            && *memory_mb == other.memory_mb
+            && *salt == other.salt
+            && *encrypted_phrase == other.encrypted_phrase
+            && *options == other.options
+            && *phrase_id == other.phrase_id
+            && *producer_version == other.producer_version
+            && *namespace == other.namespace
+    }
+}
+
+impl Eq for SinkproofHash {}
+
+/// Storage format, as written by `to_string`
+/// 
+/// Format v1: Sinkproof:v1:threads:memory:salt_base64:encrypted_phrase_base64
+/// Format v2: Sinkproof:v2:threads:memory:options:salt_base64:encrypted_phrase_base64:checksum
+/// 
+/// The v2 options field is a comma-separated `key=value` list, e.g. `alg=blake3`.
+/// The v2 checksum is the first 4 bytes of SHA-256 over everything before
+/// it, in hex; it catches storage corruption before the expensive verify.
+/// 
+/// Any hash that parses serializes to at least 60 characters, so callers
+/// may show a fixed-length prefix of it.
+impl fmt::Display for SinkproofHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&ColonFormat.serialize(self))
+    }
+}
+
+/// Parse a hash from storage format, as `"...".parse::<SinkproofHash>()`
+/// 
+/// The salt and phrase may be standard or URL-safe base64, with or without
+/// `=` padding; `to_string` always writes padded standard base64 (see
+/// `normalize_hash_string`).
+/// 
+/// An empty or whitespace-only input (e.g. a NULL column for a user who
+/// never set a password) returns `SinkproofError::EmptyHash` rather than a
+/// format error, so callers can tell "no password" from "corrupt hash".
+/// 
+/// A leading `namespace|` segment is accepted and recorded in `namespace`;
+/// see `SinkproofHash::to_string_with_namespace`.
+impl std::str::FromStr for SinkproofHash {
+    type Err = SinkproofError;
+
+    fn from_str(hash_str: &str) -> Result<Self> {
+        match split_namespace(hash_str) {
+            (Some(""), _) => Err(SinkproofError::InvalidFormat("Invalid hash format: empty namespace".to_string())),
+            (namespace, rest) => {
+                let mut hash = ColonFormat.deserialize(rest)?;
+                hash.check_phrase_length()?;
+                hash.namespace = namespace.map(str::to_string);
+                Ok(hash)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SinkproofHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SinkproofHash({})", self.redacted())
+    }
+}
+
+/// Serde representation of a `SinkproofHash`
+/// 
+/// Mirrors the `JsonFormat` object, plus the namespace. Deserializing
+/// reads the options like `JsonFormat` and checks the phrase length and
+/// `ParseLimits::DEFAULT`, the limits verification applies, so a
+/// deserialized hash is one `verify_password` would accept.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerdeHash {
+    version: String,
+    threads: usize,
+    memory_mb: usize,
+    #[serde(with = "base64_bytes")]
+    salt: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    encrypted_phrase: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    options: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SinkproofHash> for SerdeHash {
+    fn from(hash: SinkproofHash) -> Self {
+        let options = (hash.version == HashVersion::V2).then(|| encode_options(&hash));
+        SerdeHash {
+            options,
+            version: hash.version.name().to_string(),
+            threads: hash.threads,
+            memory_mb: hash.memory_mb,
+            salt: hash.salt.clone(),
+            encrypted_phrase: hash.encrypted_phrase.clone(),
+            namespace: hash.namespace.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerdeHash> for SinkproofHash {
+    type Error = SinkproofError;
+
+    fn try_from(value: SerdeHash) -> Result<Self> {
+        let version = HashVersion::parse(&value.version)?;
+        let field = OptionsField::for_version(version, value.options.as_deref())?;
+
+        let mut hash = field.into_hash(version, value.threads, value.memory_mb, value.salt, value.encrypted_phrase);
+        hash.check_phrase_length()?;
+        hash.check_limits(&ParseLimits::DEFAULT)?;
+        hash.namespace = value.namespace;
+        Ok(hash)
+    }
+}
+
+/// Serde helpers storing bytes as standard base64 (any alphabet on input)
+#[cfg(feature = "serde")]
+mod base64_bytes {
+    use super::decode_standard;
+    use base64::{engine::general_purpose, Engine as _};
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        decode_standard(&encoded).map_err(D::Error::custom)
+    }
+}
+
+/// Contents of the v2 options field: the hash options plus metadata
+pub(crate) struct OptionsField {
+    pub(crate) options: HashOptions,
+    pub(crate) phrase_id: Option<String>,
+    pub(crate) producer_version: Option<String>,
+}
+
+impl OptionsField {
+    /// The options of a v1 hash, which has no options field
+    pub(crate) fn v1() -> Self {
+        OptionsField { options: HashOptions::v1(), phrase_id: None, producer_version: None }
+    }
+
+    /// Parse the options member of a `version` hash in a format where it is
+    /// a separate, optional member (`JsonFormat`, serde)
+    /// 
+    /// Required for v2; rejected for v1, which has no options field.
+    pub(crate) fn for_version(version: HashVersion, options: Option<&str>) -> Result<Self> {
+        match (version, options) {
+            (HashVersion::V2, Some(options)) => parse_options(options),
+            (HashVersion::V2, None) => Err(SinkproofError::InvalidFormat("Missing options for a v2 hash".to_string())),
+            (HashVersion::V1, None) => Ok(OptionsField::v1()),
+            (HashVersion::V1, Some(_)) => Err(SinkproofError::InvalidFormat("Options require v2, got v1".to_string())),
+        }
+    }
+
+    /// Combine with the remaining parsed fields
+    pub(crate) fn into_hash(
+        self,
+        version: HashVersion,
+        threads: usize,
+        memory_mb: usize,
+        salt: Vec<u8>,
+        encrypted_phrase: Vec<u8>,
+    ) -> SinkproofHash {
+        SinkproofHash {
+            version,
+            threads,
+            memory_mb,
+            salt,
+            encrypted_phrase,
+            options: self.options,
+            phrase_id: self.phrase_id,
+            producer_version: self.producer_version,
+            namespace: None,
+        }
+    }
+}
+
+/// Encode the v2 options field of `hash`
+pub(crate) fn encode_options(hash: &SinkproofHash) -> String {
+    encode_options_with(&hash.options, hash.phrase_id.as_deref(), hash.producer_version.as_deref())
+}
+
+/// Encode an options field from its parts; `parse_options` reverses it
+pub(crate) fn encode_options_with(options: &HashOptions, phrase_id: Option<&str>, producer_version: Option<&str>) -> String {
+    let mut fields = vec![format!("alg={}", options.algorithm.name())];
+    if let Some(form) = options.normalization {
+        fields.push(format!("norm={}", form.name()));
+    }
+    if options.variant != Variant::default() {
+        fields.push(format!("var={}", options.variant.name()));
+    }
+    if options.cipher != Cipher::default() {
+        fields.push(format!("enc={}", options.cipher.name()));
+    }
+    if let Some(sizes) = &options.per_thread_memory {
+        let sizes: Vec<String> = sizes.iter().map(usize::to_string).collect();
+        fields.push(format!("mem={}", sizes.join("/")));
+    }
+    if options.passes != 1 {
+        fields.push(format!("pass={}", options.passes));
+    }
+    if options.combiner != Combiner::default() {
+        fields.push(format!("comb={}", options.combiner.name()));
+    }
+    if options.key_digest != KeyDigest::default() {
+        fields.push(format!("kd={}", options.key_digest.name()));
+    }
+    if options.worker_output_len != WORKER_OUTPUT_LEN {
+        fields.push(format!("out={}", options.worker_output_len));
+    }
+    if options.compress_phrase {
+        fields.push(format!("cmp={}", compress::NAME));
+    }
+    if options.peppered {
+        fields.push("pep=1".to_string());
+    }
+    if let Some(id) = phrase_id {
+        fields.push(format!("pid={}", id));
+    }
+    if let Some(label) = &options.label {
+        fields.push(format!("lbl={}", escape_field(label)));
+    }
+    if let Some(version) = producer_version {
+        fields.push(format!("ver={}", escape_field(version)));
+    }
+    fields.join(",")
+}
+
+/// Whether `byte` must be escaped inside a v2 text field
+/// 
+/// Covers the delimiters of every text encoding (`:` colon format, `,` and
+/// `=` options list, `$` PHC), the escape character itself and control
+/// characters, so a stored hash always stays on one line.
+fn needs_escape(byte: u8) -> bool {
+    matches!(byte, b'%' | b':' | b',' | b'=' | b'$') || byte.is_ascii_control()
+}
+
+/// Percent-encode every byte outside the URL unreserved set (RFC 3986)
+fn escape_url(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            escaped.push(byte as char);
+        } else {
+            escaped.push_str(&format!("%{:02X}", byte));
+        }
     }
+    escaped
+}
+
+/// Percent-encode the delimiter characters of a free-text field
+pub(crate) fn escape_field(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_ascii() && needs_escape(c as u8) {
+            escaped.push_str(&format!("%{:02X}", c as u8));
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Reverse `escape_field`
+pub(crate) fn unescape_field(value: &str) -> Result<String> {
+    let invalid = || SinkproofError::InvalidFormat(format!("Invalid escaped field '{}'", value));
+    let bytes = value.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3).ok_or_else(invalid)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+            unescaped.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            i += 3;
+        } else {
+            unescaped.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(unescaped).map_err(|_| invalid())
+}
+
+/// Parse the v2 options field; missing keys keep their v1 values
+pub(crate) fn parse_options(field: &str) -> Result<OptionsField> {
+    let mut options = HashOptions::v1();
+    let mut phrase_id = None;
+    let mut producer_version = None;
+
+    for entry in field.split(',').filter(|entry| !entry.is_empty()) {
+        let (key, value) = entry
+            .split_once('=')
+            .ok_or_else(|| SinkproofError::InvalidFormat(format!("Invalid option '{}': expected key=value", entry)))?;
+
+        match key {
+            "alg" => {
+                options.algorithm = HashAlgo::from_name(value)
+                    .ok_or_else(|| SinkproofError::InvalidFormat(format!("Unknown algorithm '{}'", value)))?;
+            }
+            "norm" => {
+                options.normalization = Some(
+                    NormalizationForm::from_name(value)
+                        .ok_or_else(|| SinkproofError::InvalidFormat(format!("Unknown normalization '{}'", value)))?,
+                );
+            }
+            "var" => {
+                options.variant = Variant::from_name(value)
+                    .ok_or_else(|| SinkproofError::InvalidFormat(format!("Unknown variant '{}'", value)))?;
+            }
+            "enc" => {
+                options.cipher = Cipher::from_name(value)
+                    .ok_or_else(|| SinkproofError::InvalidFormat(format!("Unknown cipher '{}'", value)))?;
+            }
+            "mem" => {
+                let sizes = value
+                    .split('/')
+                    .map(|size| size.parse::<usize>())
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| SinkproofError::InvalidFormat(format!("Invalid per-thread memory '{}': {}", value, e)))?;
+                options.per_thread_memory = Some(sizes);
+            }
+            "pass" => {
+                options.passes = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&passes| passes > 0)
+                    .ok_or_else(|| SinkproofError::InvalidFormat(format!("Invalid pass count '{}'", value)))?;
+            }
+            "kd" => {
+                options.key_digest = KeyDigest::from_name(value)
+                    .ok_or_else(|| SinkproofError::InvalidFormat(format!("Unknown key digest '{}'", value)))?;
+            }
+            "out" => {
+                options.worker_output_len = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&len| is_valid_worker_output_len(len))
+                    .ok_or_else(|| SinkproofError::InvalidFormat(format!("Invalid worker output length '{}'", value)))?;
+            }
+            "cmp" if value == compress::NAME => options.compress_phrase = true,
+            "cmp" => return Err(SinkproofError::InvalidFormat(format!("Unknown compression '{}'", value))),
+            "pep" if value == "1" => options.peppered = true,
+            "pep" => return Err(SinkproofError::InvalidFormat(format!("Invalid pepper flag '{}'", value))),
+            "comb" => {
+                options.combiner = Combiner::from_name(value)
+                    .ok_or_else(|| SinkproofError::InvalidFormat(format!("Unknown combiner '{}'", value)))?;
+            }
+            "pid" => {
+                if value.len() != 8 || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(SinkproofError::InvalidFormat(format!("Invalid phrase id '{}'", value)));
+                }
+                phrase_id = Some(value.to_ascii_lowercase());
+            }
+            "lbl" => options.label = Some(unescape_field(value)?),
+            "ver" => producer_version = Some(unescape_field(value)?),
+            _ => return Err(SinkproofError::InvalidFormat(format!("Unknown option '{}'", key))),
+        }
+    }
+
+    Ok(OptionsField { options, phrase_id, producer_version })
 }
 
 #[cfg(test)]
@@ -74,32 +1117,54 @@ mod tests {
     #[test]
     fn test_serialization_roundtrip() {
         let original = SinkproofHash {
-            version: "v1".to_string(),
+            version: HashVersion::V1,
             threads: 4,
             memory_mb: 100,
             salt: vec![1, 2, 3, 4, 5, 6, 7, 8],
-            encrypted_phrase: vec![10, 20, 30, 40, 50],
+            encrypted_phrase: vec![10; 53],
+            options: HashOptions::v1(),
+            phrase_id: None,
+            producer_version: None,
+            namespace: None,
         };
 
         let serialized = original.to_string();
-        let deserialized = SinkproofHash::from_string(&serialized)
+        let deserialized = serialized.parse::<SinkproofHash>()
             .expect("Failed to deserialize");
 
-        assert_eq!(original.version, deserialized.version);
-        assert_eq!(original.threads, deserialized.threads);
-        assert_eq!(original.memory_mb, deserialized.memory_mb);
-        assert_eq!(original.salt, deserialized.salt);
-        assert_eq!(original.encrypted_phrase, deserialized.encrypted_phrase);
+        assert_eq!(original, deserialized);
+    }
+
+    #[test]
+    fn test_structural_equality() {
+        let stored = concat!("Sinkproof:v1:4:64:AQID:", test_phrase!());
+        let hash = stored.parse::<SinkproofHash>().unwrap();
+        assert_eq!(hash, stored.parse::<SinkproofHash>().unwrap());
+        assert_eq!(hash, hash.clone());
+
+        let mut other = hash.clone();
+        other.salt[0] ^= 1;
+        assert_ne!(hash, other);
+
+        let mut other = hash.clone();
+        other.options.passes = 2;
+        assert_ne!(hash, other);
+
+        assert_ne!(hash, format!("myapp|{}", stored).parse::<SinkproofHash>().unwrap());
     }
 
     #[test]
     fn test_format_structure() {
         let hash = SinkproofHash {
-            version: "v1".to_string(),
+            version: HashVersion::V1,
             threads: 2,
             memory_mb: 50,
             salt: vec![1, 2, 3],
-            encrypted_phrase: vec![4, 5, 6],
+            encrypted_phrase: vec![4; 53],
+            options: HashOptions::v1(),
+            phrase_id: None,
+            producer_version: None,
+            namespace: None,
         };
 
         let serialized = hash.to_string();
@@ -114,22 +1179,759 @@ mod tests {
         assert_eq!(parts[3], "50");
     }
 
+    #[test]
+    fn test_v2_roundtrip() {
+        let original = SinkproofHash {
+            version: HashVersion::V2,
+            threads: 2,
+            memory_mb: 8,
+            salt: vec![1, 2, 3],
+            encrypted_phrase: (4..57).collect(),
+            options: HashOptions { algorithm: HashAlgo::Blake3, ..HashOptions::v1() },
+            phrase_id: None,
+            producer_version: None,
+            namespace: None,
+        };
+
+        let serialized = original.to_string();
+        assert!(serialized.starts_with(concat!("Sinkproof:v2:2:8:alg=blake3:AQID:", test_phrase!(), ":")));
+        assert_eq!(serialized.split(':').count(), 8);
+
+        let parsed = serialized.parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!(parsed.version, HashVersion::V2);
+        assert_eq!(parsed.options, original.options);
+        assert_eq!(parsed.salt, original.salt);
+        assert_eq!(parsed.encrypted_phrase, original.encrypted_phrase);
+    }
+
+    #[test]
+    fn test_minimum_serialized_length() {
+        let shortest_v1 = SinkproofHash {
+            version: HashVersion::V1,
+            threads: 1,
+            memory_mb: 1,
+            salt: Vec::new(),
+            encrypted_phrase: vec![0; NONCE_LEN + VERIFICATION_PHRASE.len() + TAG_LEN],
+            options: HashOptions::v1(),
+            phrase_id: None,
+            producer_version: None,
+            namespace: None,
+        };
+        // A compressed phrase may be a single byte, but only v2 carries it
+        let shortest_v2 = SinkproofHash {
+            version: HashVersion::V2,
+            encrypted_phrase: vec![0; NONCE_LEN + 1 + TAG_LEN],
+            options: HashOptions { compress_phrase: true, ..HashOptions::v1() },
+            ..shortest_v1.clone()
+        };
+
+        for hash in [shortest_v1, shortest_v2] {
+            assert!(hash.check_phrase_length().is_ok());
+            assert!(hash.to_string().len() >= 60, "{}", hash.to_string());
+        }
+    }
+
+    #[test]
+    fn test_normalize_hash_string() {
+        // 0xfb bytes encode to "+/v7", so both alphabets and padding differ
+        let v1 = SinkproofHash {
+            version: HashVersion::V1,
+            threads: 1,
+            memory_mb: 1,
+            salt: vec![0xfb; 31],
+            encrypted_phrase: vec![0xfb; NONCE_LEN + VERIFICATION_PHRASE.len() + TAG_LEN],
+            options: HashOptions::v1(),
+            phrase_id: None,
+            producer_version: None,
+            namespace: None,
+        };
+        let v2 = SinkproofHash {
+            version: HashVersion::V2,
+            options: HashOptions { passes: 2, ..HashOptions::v1() },
+            ..v1.clone()
+        };
+
+        for canonical in [v1.to_string(), v2.to_string()] {
+            assert!(canonical.contains('+') && canonical.contains('/') && canonical.contains('='));
+            assert_eq!(normalize_hash_string(&canonical).unwrap(), canonical);
+
+            // Re-encode only the salt and phrase, keeping any v2 checksum
+            let parts: Vec<&str> = canonical.split(':').collect();
+            let fields = if canonical.starts_with("Sinkproof:v2") { 5..7 } else { 4..6 };
+            let variant = |encode: fn(&str) -> String| {
+                let mut parts: Vec<String> = parts.iter().map(|part| part.to_string()).collect();
+                for field in &mut parts[fields.clone()] {
+                    *field = encode(field);
+                }
+                parts.join(":")
+            };
+
+            let unpadded = variant(|field| field.trim_end_matches('=').to_string());
+            let url_safe = variant(|field| field.replace('+', "-").replace('/', "_"));
+            let url_safe_unpadded =
+                variant(|field| field.replace('+', "-").replace('/', "_").trim_end_matches('=').to_string());
+            for input in [unpadded, url_safe, url_safe_unpadded] {
+                assert_ne!(input, canonical);
+                assert_eq!(normalize_hash_string(&input).unwrap(), canonical, "{}", input);
+            }
+        }
+
+        assert_eq!(normalize_hash_string(&format!("app|{}", v1)).unwrap(), format!("app|{}", v1));
+        assert!(normalize_hash_string("Sinkproof:v1:1:1:!!!:AQID").is_err());
+    }
+
+    #[test]
+    fn test_v2_checksum() {
+        let stored = concat!("Sinkproof:v2:2:8:alg=blake3:AQID:", test_phrase!(), ":a0d8407d").parse::<SinkproofHash>()
+            .expect("Failed to parse")
+            .to_string();
+        assert!(stored.parse::<SinkproofHash>().is_ok());
+
+        // Flip one character of the salt field
+        let corrupted = stored.replacen("AQID", "AQIE", 1);
+        assert_eq!(
+            corrupted.parse::<SinkproofHash>().unwrap_err(),
+            SinkproofError::ChecksumMismatch
+        );
+
+        // Dropping the checksum does not skip the check
+        let (body, _) = stored.rsplit_once(':').unwrap();
+        assert!(matches!(body.parse::<SinkproofHash>(), Err(SinkproofError::InvalidFormat(_))));
+
+        // v1 has no checksum and still parses
+        let v1 = concat!("Sinkproof:v1:4:64:AQID:", test_phrase!());
+        assert_eq!(v1.parse::<SinkproofHash>().expect("Failed to parse").to_string(), v1);
+    }
+
+    #[test]
+    fn test_v2_normalization_option() {
+        let parsed = concat!("Sinkproof:v2:1:1:alg=sha256,norm=nfkc:AQID:", test_phrase!(), ":c9172957").parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!(parsed.options.normalization, Some(NormalizationForm::Nfkc));
+        assert!(parsed.to_string().starts_with(concat!("Sinkproof:v2:1:1:alg=sha256,norm=nfkc:AQID:", test_phrase!(), ":")));
+
+        assert!(concat!("Sinkproof:v2:1:1:norm=nfd:AQID:", test_phrase!(), ":c3e8fd0d").parse::<SinkproofHash>().is_err());
+    }
+
+    #[test]
+    fn test_v2_variant_option() {
+        let parsed = concat!("Sinkproof:v2:1:1:alg=sha256,var=id:AQID:", test_phrase!(), ":ee3c0ff7").parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!(parsed.options.variant, Variant::Hybrid);
+        assert!(parsed.to_string().starts_with(concat!("Sinkproof:v2:1:1:alg=sha256,var=id:AQID:", test_phrase!(), ":")));
+
+        assert!(concat!("Sinkproof:v2:1:1:var=x:AQID:", test_phrase!(), ":d8032452").parse::<SinkproofHash>().is_err());
+    }
+
+    #[test]
+    fn test_v2_per_thread_memory_option() {
+        let parsed = concat!("Sinkproof:v2:3:1:alg=sha256,mem=2/1/3:AQID:", test_phrase!(), ":4f2a669c").parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!(parsed.options.per_thread_memory, Some(vec![2, 1, 3]));
+        assert!(parsed.to_string().starts_with(concat!("Sinkproof:v2:3:1:alg=sha256,mem=2/1/3:AQID:", test_phrase!(), ":")));
+
+        assert!(concat!("Sinkproof:v2:3:1:mem=2/x:AQID:", test_phrase!(), ":8857d2e6").parse::<SinkproofHash>().is_err());
+    }
+
+    #[test]
+    fn test_v2_cipher_option() {
+        let parsed = concat!("Sinkproof:v2:1:1:alg=sha256,enc=chacha20poly1305:AQID:", test_phrase!(), ":a436bc4d").parse::<SinkproofHash>()
+            .expect("Failed to parse");
+        assert_eq!(parsed.options.cipher, Cipher::ChaCha20Poly1305);
+        assert!(parsed.to_string().starts_with(concat!("Sinkproof:v2:1:1:alg=sha256,enc=chacha20poly1305:AQID:", test_phrase!(), ":")));
+
+        assert!(concat!("Sinkproof:v2:1:1:enc=des:AQID:", test_phrase!(), ":40928278").parse::<SinkproofHash>().is_err());
+    }
+
+    #[test]
+    fn test_phrase_id() {
+        let id = current_phrase_id();
+        let matching = with_checksum(&format!(concat!("Sinkproof:v2:1:1:alg=sha256,pid={}:AQID:", test_phrase!()), id)).parse::<SinkproofHash>()
+            .expect("Failed to parse");
+        assert_eq!(matching.phrase_id.as_deref(), Some(id.as_str()));
+        assert!(matching.check_phrase_id(VERIFICATION_PHRASE).is_ok());
+
+        let other = concat!("Sinkproof:v2:1:1:alg=sha256,pid=00000000:AQID:", test_phrase!(), ":16a9f5e8").parse::<SinkproofHash>()
+            .expect("Failed to parse");
+        assert_eq!(other.check_phrase_id(VERIFICATION_PHRASE), Err(SinkproofError::PhraseMismatch));
+
+        // Hashes without an id are assumed to use the built-in phrase
+        let v1 = concat!("Sinkproof:v1:1:1:AQID:", test_phrase!()).parse::<SinkproofHash>().expect("Failed to parse");
+        assert!(v1.check_phrase_id(VERIFICATION_PHRASE).is_ok());
+
+        assert!(concat!("Sinkproof:v2:1:1:pid=xyz:AQID:", test_phrase!(), ":468e3014").parse::<SinkproofHash>().is_err());
+    }
+
+    #[test]
+    fn test_v2_invalid_options() {
+        assert!(concat!("Sinkproof:v2:2:8:alg=md5:AQID:", test_phrase!(), ":baffeab3").parse::<SinkproofHash>().is_err());
+        assert!(concat!("Sinkproof:v2:2:8:foo=bar:AQID:", test_phrase!(), ":e738c28d").parse::<SinkproofHash>().is_err());
+        assert!(concat!("Sinkproof:v2:2:8:alg:AQID:", test_phrase!(), ":b1001933").parse::<SinkproofHash>().is_err());
+        assert!(concat!("Sinkproof:v2:2:8:AQID:", test_phrase!()).parse::<SinkproofHash>().is_err());
+    }
+
+    #[test]
+    fn test_redacted_and_debug_hide_secrets() {
+        let hash = SinkproofHash {
+            version: HashVersion::V1,
+            threads: 4,
+            memory_mb: 64,
+            salt: vec![7; 32],
+            encrypted_phrase: vec![9; 53],
+            options: HashOptions::v1(),
+            phrase_id: None,
+            producer_version: None,
+            namespace: None,
+        };
+
+        assert_eq!(hash.redacted(), "Sinkproof:v1:4:64:<salt:32B>:<phrase:redacted>");
+
+        let debug = format!("{:?}", hash);
+        assert!(debug.contains("Sinkproof:v1:4:64"));
+        assert!(!debug.contains(&general_purpose::STANDARD.encode(&hash.salt)));
+        assert!(!debug.contains(&general_purpose::STANDARD.encode(&hash.encrypted_phrase)));
+    }
+
+    #[test]
+    fn test_id_is_stable_and_distinct() {
+        let hash = concat!("Sinkproof:v1:4:64:AQID:", test_phrase!()).parse::<SinkproofHash>().expect("Failed to parse");
+        let reparsed = hash.to_string().parse::<SinkproofHash>().expect("Failed to parse");
+        let other = concat!("Sinkproof:v1:4:64:AQIE:", test_phrase!()).parse::<SinkproofHash>().expect("Failed to parse");
+
+        assert_eq!(hash.id().len(), 22);
+        assert_eq!(hash.id(), reparsed.id());
+        assert_ne!(hash.id(), other.id());
+        assert!(!hash.id().contains("AQID"));
+    }
+
+    #[test]
+    fn test_empty_hash() {
+        for input in ["", " ", "\t\n"] {
+            assert_eq!(input.parse::<SinkproofHash>().unwrap_err(), SinkproofError::EmptyHash);
+        }
+    }
+
     #[test]
     fn test_invalid_format() {
-        assert!(SinkproofHash::from_string("invalid").is_err());
-        assert!(SinkproofHash::from_string("Sinkproof:v1:2:50").is_err());
-        assert!(SinkproofHash::from_string("WrongName:v1:2:50:AQID:BAUG").is_err());
+        assert!("invalid".parse::<SinkproofHash>().is_err());
+        assert!("Sinkproof:v1:2:50".parse::<SinkproofHash>().is_err());
+        assert!(concat!("WrongName:v1:2:50:AQID:", test_phrase!()).parse::<SinkproofHash>().is_err());
+    }
+
+    #[test]
+    fn test_double_encoded_hash() {
+        let stored = concat!("Sinkproof:v1:4:64:AQID:", test_phrase!());
+        let wrapped = general_purpose::STANDARD.encode(stored);
+
+        let err = wrapped.parse::<SinkproofHash>().unwrap_err();
+        assert!(err.to_string().contains("base64-encoded Sinkproof hash"));
+
+        let parsed = SinkproofHash::from_string_unwrapping(&wrapped).expect("Failed to unwrap");
+        assert_eq!(parsed.to_string(), stored);
+
+        // Plain hashes pass through, and only one layer is removed
+        assert!(SinkproofHash::from_string_unwrapping(stored).is_ok());
+        let twice = general_purpose::STANDARD.encode(&wrapped);
+        assert!(SinkproofHash::from_string_unwrapping(&twice).is_err());
+    }
+
+    #[test]
+    fn test_binary_roundtrip() {
+        for stored in [concat!("Sinkproof:v1:4:64:AQID:", test_phrase!()), concat!("Sinkproof:v2:2:8:alg=sha256,var=d,pid=0a1b2c3d:AQID:", test_phrase!(), ":1e72c9e2")] {
+            let hash = stored.parse::<SinkproofHash>().expect("Failed to parse");
+            let bytes = hash.to_bytes();
+            assert!(bytes.starts_with(BINARY_MAGIC));
+
+            let parsed = SinkproofHash::from_bytes(&bytes).expect("Failed to parse bytes");
+            assert_eq!(parsed.to_string(), hash.to_string());
+
+            let mut truncated = hash.clone();
+            truncated.encrypted_phrase.truncate(NONCE_LEN + TAG_LEN);
+            assert!(matches!(
+                SinkproofHash::from_bytes(&truncated.to_bytes()),
+                Err(SinkproofError::InvalidFormat(msg)) if msg.contains("too short")
+            ));
+        }
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_bad_input_without_panicking() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let valid = concat!("Sinkproof:v2:2:8:alg=sha256:AQID:", test_phrase!(), ":d13d0abd").parse::<SinkproofHash>()
+            .expect("Failed to parse")
+            .to_bytes();
+
+        // Every truncation, and trailing garbage
+        for len in 0..valid.len() {
+            assert!(SinkproofHash::from_bytes(&valid[..len]).is_err());
+        }
+        let mut extended = valid.clone();
+        extended.push(0);
+        assert!(SinkproofHash::from_bytes(&extended).is_err());
+
+        // Length prefixes claiming far more than the buffer holds
+        let mut lying = BINARY_MAGIC.to_vec();
+        lying.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(SinkproofHash::from_bytes(&lying), Err(SinkproofError::InvalidFormat(_))));
+
+        // Random corruption of a valid blob
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..2000 {
+            let mut blob = valid.clone();
+            for _ in 0..rng.gen_range(1..4) {
+                let index = rng.gen_range(0..blob.len());
+                blob[index] = rng.gen();
+            }
+            let _ = SinkproofHash::from_bytes(&blob);
+        }
+    }
+
+    #[test]
+    fn test_wipe_string_zeroes_buffer() {
+        let mut stored = String::from(concat!("Sinkproof:v1:4:64:AQID:", test_phrase!()));
+        let ptr = stored.as_ptr();
+        let capacity = stored.capacity();
+
+        wipe_string(&mut stored);
+
+        assert!(stored.is_empty());
+        assert_eq!(stored.capacity(), capacity);
+        // The allocation is still owned by `stored`, so reading it is sound
+        let buffer = unsafe { std::slice::from_raw_parts(ptr, capacity) };
+        assert!(buffer.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_write_to_and_read_from() {
+        let hashes: Vec<SinkproofHash> = [
+            concat!("Sinkproof:v1:4:64:AQID:", test_phrase!()),
+            concat!("Sinkproof:v2:2:8:alg=sha256,var=id,lbl=a%3Ab:AQIDBA==:", test_phrase!(), ":2608f1d2"),
+            concat!("myapp|Sinkproof:v1:1:2:AQIDBAU=:", test_phrase!()),
+        ]
+        .iter()
+        .map(|stored| stored.parse::<SinkproofHash>().unwrap())
+        .collect();
+
+        let mut buffer = Vec::new();
+        for hash in &hashes {
+            hash.write_to(&mut buffer).expect("Failed to write");
+        }
+        let expected: String = hashes.iter().map(|hash| hash.to_string() + "\n").collect();
+        assert_eq!(String::from_utf8(buffer.clone()).unwrap(), expected);
+
+        let mut reader = io::Cursor::new(buffer);
+        for hash in &hashes {
+            let read = SinkproofHash::read_from(&mut reader).expect("Failed to read").expect("Missing hash");
+            assert_eq!(read.to_string(), hash.to_string());
+        }
+        assert!(SinkproofHash::read_from(&mut reader).unwrap().is_none());
+
+        let mut garbage = io::Cursor::new("not a hash\r\n");
+        let error = SinkproofHash::read_from(&mut garbage).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_verify_cost_units() {
+        let cost = |stored: &str| stored.parse::<SinkproofHash>().unwrap().verify_cost_units();
+
+        assert_eq!(cost(concat!("Sinkproof:v1:4:64:AQID:", test_phrase!())), 256);
+        assert_eq!(cost(concat!("Sinkproof:v1:1:256:AQID:", test_phrase!())), 256);
+        assert!(cost(concat!("Sinkproof:v1:2:16:AQID:", test_phrase!())) < cost(concat!("Sinkproof:v1:4:16:AQID:", test_phrase!())));
+        assert_eq!(cost(concat!("Sinkproof:v2:3:1:alg=sha256,mem=2/1/3:AQID:", test_phrase!(), ":4f2a669c")), 6);
+        assert_eq!(cost(concat!("Sinkproof:v2:4:64:alg=sha256,pass=3:AQID:", test_phrase!(), ":e406c9dc")), 768);
+        assert_eq!(cost(&format!(concat!("Sinkproof:v1:{}:{}:AQID:", test_phrase!()), usize::MAX, usize::MAX)), u64::MAX);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_producer_version() {
+        use crate::hasher::hash_password_with;
+        use crate::verifier::verify_password;
+
+        let v1 = hash_password_with("password", &HashParams::new(1, 1).variant(Variant::Independent)).unwrap();
+        assert_eq!(v1.producer_version(), None);
+
+        let v2 = hash_password_with("password", &HashParams::new(1, 1).variant(Variant::Hybrid)).unwrap();
+        assert_eq!(v2.producer_version(), Some(env!("CARGO_PKG_VERSION")));
+        let stored = v2.to_string();
+        assert!(stored.contains(&format!("ver={}", env!("CARGO_PKG_VERSION"))));
+
+        let parsed = stored.parse::<SinkproofHash>().unwrap();
+        assert_eq!(parsed.producer_version(), Some(env!("CARGO_PKG_VERSION")));
+        assert!(verify_password("password", &stored).unwrap());
+
+        // The version is authenticated: editing it (and recomputing the
+        // checksum that would also catch the edit) breaks verification
+        let (body, _) = stored.rsplit_once(':').unwrap();
+        let tampered = with_checksum(&body.replace(&format!("ver={}", env!("CARGO_PKG_VERSION")), "ver=0.0.1"));
+        assert_eq!(tampered.parse::<SinkproofHash>().unwrap().producer_version(), Some("0.0.1"));
+        assert!(!verify_password("password", &tampered).unwrap());
+    }
+
+    #[test]
+    fn test_namespace_prefix() {
+        let bare = concat!("Sinkproof:v1:4:64:AQID:", test_phrase!()).parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!(bare.namespace(), None);
+
+        let namespaced = concat!("myapp|Sinkproof:v1:4:64:AQID:", test_phrase!()).parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!(namespaced.namespace(), Some("myapp"));
+        assert_eq!(namespaced.salt, bare.salt);
+        assert_eq!(namespaced.to_string(), bare.to_string());
+        assert_eq!(namespaced.to_string_with_namespace("myapp").unwrap(), concat!("myapp|Sinkproof:v1:4:64:AQID:", test_phrase!()));
+
+        let v2 = concat!("Sinkproof:v2:2:8:alg=sha256,lbl=a|b:AQID:", test_phrase!(), ":75297dc0").parse::<SinkproofHash>().expect("Failed to parse");
+        let stored = v2.to_string_with_namespace("other").unwrap();
+        let reparsed = stored.parse::<SinkproofHash>().expect("Failed to parse namespaced v2");
+        assert_eq!(reparsed.namespace(), Some("other"));
+        assert_eq!(reparsed.to_string(), v2.to_string());
+
+        assert!(concat!("|Sinkproof:v1:4:64:AQID:", test_phrase!()).parse::<SinkproofHash>().is_err());
+        assert_eq!("myapp|".parse::<SinkproofHash>().unwrap_err(), SinkproofError::EmptyHash);
+
+        // Namespaces that would not parse back are refused when writing
+        for namespace in ["", "a|b", "a:b", "|", ":"] {
+            assert!(
+                matches!(bare.to_string_with_namespace(namespace), Err(SinkproofError::InvalidConfig(_))),
+                "{:?}",
+                namespace
+            );
+        }
+        for namespace in ["myapp", "tenant-1.prod", "ñ"] {
+            let reparsed = bare.to_string_with_namespace(namespace).unwrap().parse::<SinkproofHash>().unwrap();
+            assert_eq!(reparsed.namespace(), Some(namespace));
+            assert_eq!(reparsed.to_string(), bare.to_string());
+        }
+    }
+
+    #[test]
+    fn test_salt_only_matches_full_parse() {
+        for stored in [
+            concat!("Sinkproof:v1:4:64:AQIDBA==:", test_phrase!()),
+            concat!("Sinkproof:v2:2:8:alg=sha256,var=id:AQIDBA==:", test_phrase!(), ":2ec05f55"),
+            concat!("Sinkproof:v2:2:8:alg=sha256:AQIDBA==:", test_phrase!(), ":00000000"),
+            concat!("myapp|Sinkproof:v1:4:64:AQIDBA==:", test_phrase!()),
+        ] {
+            let salt = SinkproofHash::from_string_salt_only(stored).expect("Failed to extract salt");
+            assert_eq!(salt, [1, 2, 3, 4]);
+            if let Ok(hash) = stored.parse::<SinkproofHash>() {
+                assert_eq!(hash.salt(), salt.as_slice());
+                assert_eq!(hash.salt_base64(), "AQIDBA==");
+            }
+        }
+
+        assert_eq!(SinkproofHash::from_string_salt_only(" ").unwrap_err(), SinkproofError::EmptyHash);
+        assert!(SinkproofHash::from_string_salt_only("Sinkproof:v1:4:64").is_err());
+        assert!(SinkproofHash::from_string_salt_only(concat!("Other:v1:4:64:AQID:", test_phrase!())).is_err());
+        assert!(SinkproofHash::from_string_salt_only(concat!("Sinkproof:v1:4:64:!!!:", test_phrase!())).is_err());
+    }
+
+    #[test]
+    fn test_cipher_accessor() {
+        let v1 = concat!("Sinkproof:v1:4:64:AQID:", test_phrase!()).parse::<SinkproofHash>().expect("Failed to parse");
+        assert_eq!(v1.cipher(), Cipher::Aes256Gcm);
+        assert_eq!(v1.will_use_hardware_accel(), Cipher::Aes256Gcm.is_hardware_accelerated());
+
+        let chacha = concat!("Sinkproof:v2:4:64:alg=sha256,enc=chacha20poly1305:AQID:", test_phrase!(), ":e08f276e").parse::<SinkproofHash>()
+            .expect("Failed to parse");
+        assert_eq!(chacha.cipher(), Cipher::ChaCha20Poly1305);
+        assert!(!chacha.will_use_hardware_accel());
+    }
+
+    #[test]
+    fn test_label_with_delimiters_roundtrips() {
+        let options = HashOptions { label: Some("tenant:42, role=admin 100%$".to_string()), ..HashOptions::v1() };
+        let hash = SinkproofHash {
+            version: options.format_version(),
+            threads: 2,
+            memory_mb: 8,
+            salt: vec![1, 2, 3],
+            encrypted_phrase: vec![4; 53],
+            options,
+            phrase_id: None,
+            producer_version: None,
+            namespace: None,
+        };
+
+        let serialized = hash.to_string();
+        assert!(serialized.contains("lbl=tenant%3A42%2C role%3Dadmin 100%25%24"));
+        assert_eq!(serialized.split(':').count(), 8);
+
+        let parsed = serialized.parse::<SinkproofHash>().expect("Failed to parse labeled hash");
+        assert_eq!(parsed.options.label.as_deref(), Some("tenant:42, role=admin 100%$"));
+        assert_eq!(parsed.to_string(), serialized);
+    }
+
+    #[test]
+    fn test_escape_field() {
+        assert_eq!(escape_field("plain text ñ"), "plain text ñ");
+        assert_eq!(escape_field("a\nb"), "a%0Ab");
+        assert_eq!(unescape_field("a%0ab").expect("Failed to unescape"), "a\nb");
+        assert!(unescape_field("100%").is_err());
+        assert!(unescape_field("%zz").is_err());
+        assert!(unescape_field("%FF").is_err());
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_phc_conversion_is_lossless() {
+        use crate::hasher::hash_password_with;
+
+        for params in [
+            HashParams::new(1, 1),
+            HashParams::new(2, 1).passes(2).variant(Variant::Hybrid).label("tenant:42,$x"),
+        ] {
+            let original = hash_password_with("password", &params).unwrap().to_string();
+            let phc = original.parse::<SinkproofHash>().unwrap().to_phc();
+            assert!(phc.starts_with("$sinkproof$"));
+            assert_eq!(SinkproofHash::from_phc(&phc).unwrap().to_string(), original);
+        }
+
+        assert!(SinkproofHash::from_phc("$sinkproof$v=1$t=2,m=50$AQID$BAUG").is_err());
+    }
+
+    #[cfg(feature = "deflate")]
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_compressed_phrase_roundtrip() {
+        use crate::hasher::{compute_key, hash_password_with};
+        use crate::verifier::{verify_detailed, verify_password, VerifyResult};
+
+        let params = HashParams::new(1, 1).compress_phrase(true);
+        let hash = hash_password_with("password", &params).unwrap();
+        let stored = hash.to_string();
+        assert!(stored.contains("cmp=deflate"));
+
+        let parsed = stored.parse::<SinkproofHash>().unwrap();
+        assert!(parsed.options.compress_phrase);
+        assert!(verify_password("password", &stored).unwrap());
+        assert!(!verify_password("wrong", &stored).unwrap());
+        assert_eq!(verify_detailed("password", &stored).unwrap(), VerifyResult::Match);
+
+        // The plaintext under the AEAD is the compressed phrase
+        let key = compute_key("password", &hash.salt, &params).unwrap();
+        let raw = decrypt_bytes_with_aad(hash.cipher(), &key, &hash.encrypted_phrase, &hash.phrase_aad()).unwrap();
+        assert_ne!(raw, VERIFICATION_PHRASE.as_bytes());
+        assert_eq!(hash.decrypt_phrase(&key, VERIFICATION_PHRASE).unwrap(), VERIFICATION_PHRASE.as_bytes());
+
+        assert!(stored.replace("cmp=deflate", "cmp=zstd").parse::<SinkproofHash>().is_err());
+    }
+
+    #[cfg(not(feature = "deflate"))]
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_compressed_phrase_requires_feature() {
+        use crate::hasher::hash_password_with;
+
+        let params = HashParams::new(1, 1).compress_phrase(true);
+        assert_eq!(params.validate(), Err(SinkproofError::AlgorithmUnavailable { algorithm: "deflate", feature: "deflate" }));
+        assert!(hash_password_with("password", &params).is_err());
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_truncated_phrase_rejected() {
+        use crate::hasher::hash_password_with;
+        use crate::verifier::verify_password;
+
+        let stored = hash_password_with("password", &HashParams::new(1, 1)).unwrap().to_string();
+        // Dropping one base64 quantum still decodes, just to 3 fewer bytes.
+        let truncated = &stored[..stored.len() - 4];
+        assert!(truncated.parse::<SinkproofHash>().is_err());
+        assert!(verify_password("password", truncated).is_err());
+
+        assert!("Sinkproof:v1:2:50:AQID:BAUG".parse::<SinkproofHash>().is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_display_and_from_str() {
+        let stored = concat!("Sinkproof:v1:4:64:AQID:", test_phrase!());
+        let parsed: SinkproofHash = stored.parse().unwrap();
+        assert_eq!(parsed, SinkproofHash::from_string(stored).unwrap());
+        assert_eq!(format!("{}", parsed), stored);
+        assert_eq!(format!("{:>120}", parsed), format!("{:>120}", stored));
+
+        let err = "Sinkproof:v1:4".parse::<SinkproofHash>().unwrap_err();
+        assert!(matches!(err, SinkproofError::InvalidFormat(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn test_url_safe_roundtrip() {
+        // These bytes encode to `+` and `/` in the standard alphabet
+        let salt = vec![0xfb, 0xff, 0xbf, 0xfe, 0x01];
+        let phrase = vec![0xff; 53];
+        let labelled = HashOptions {
+            variant: Variant::Hybrid,
+            label: Some("tenant:42,50% ñ".to_string()),
+            ..HashOptions::v1()
+        };
+        for (version, options) in [
+            (HashVersion::V1, HashOptions::v1()),
+            (HashVersion::V2, HashOptions::v1()),
+            (HashVersion::V2, labelled),
+        ] {
+            let hash = SinkproofHash {
+                version,
+                threads: 2,
+                memory_mb: 8,
+                salt: salt.clone(),
+                encrypted_phrase: phrase.clone(),
+                options,
+                phrase_id: Some("0a1b2c3d".to_string()).filter(|_| version == HashVersion::V2),
+                producer_version: None,
+                namespace: None,
+            };
+
+            let standard = hash.to_string();
+            let url_safe = hash.to_string_url_safe();
+            assert!(standard.contains('+') && standard.contains('/') && standard.contains('='), "{}", standard);
+            // Only unreserved characters, apart from well-formed %XX escapes
+            let mut rest = url_safe.as_bytes();
+            while let [first, tail @ ..] = rest {
+                rest = match (first, tail) {
+                    (b'%', [hi, lo, tail @ ..]) if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => tail,
+                    (b, _) if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') => tail,
+                    _ => panic!("not URL-safe at {:?}: {}", String::from_utf8_lossy(rest), url_safe),
+                };
+            }
+
+            assert_eq!(standard.parse::<SinkproofHash>().unwrap(), hash);
+            assert_eq!(SinkproofHash::from_string_url_safe(&url_safe).unwrap(), hash);
+            assert_eq!(SinkproofHash::from_string_url_safe(&url_safe).unwrap().to_string(), standard);
+        }
+        assert!(SinkproofHash::from_string_url_safe("Sinkproof%3Av1%3A%G1").is_err());
+    }
+
+    #[test]
+    fn test_version_dispatch() {
+        let phrase = test_phrase!();
+        let v1 = format!("Sinkproof:v1:4:64:AQID:{}", phrase).parse::<SinkproofHash>().unwrap();
+        let v2 = with_checksum(&format!("Sinkproof:v2:4:64:alg=sha256:AQID:{}", phrase)).parse::<SinkproofHash>().unwrap();
+        assert_eq!(v1.version, HashVersion::V1);
+        assert_eq!(v2.version, HashVersion::V2);
+        assert_eq!(v1.params(), v2.params());
+
+        for version in [HashVersion::V1, HashVersion::V2] {
+            assert_eq!(HashVersion::from_name(version.name()), Some(version));
+        }
+        let err = format!("Sinkproof:v3:4:64:AQID:{}", phrase).parse::<SinkproofHash>().unwrap_err();
+        assert_eq!(err, SinkproofError::InvalidFormat("Unsupported hash version 'v3'".to_string()));
+        assert!(SinkproofHash::from_phc(&v1.to_phc().replace("v=1", "v=3")).is_err());
+        assert!(SinkproofHash::from_bytes(&v1.to_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_from_string_with_limits() {
+        let phrase = test_phrase!();
+        let limits = ParseLimits::new(8, 64);
+
+        let ok = format!("Sinkproof:v1:8:64:AQID:{}", phrase);
+        assert_eq!(SinkproofHash::from_string_with_limits(&ok, &limits).unwrap(), ok.parse::<SinkproofHash>().unwrap());
+
+        let many_threads = format!("Sinkproof:v1:100000:1:AQID:{}", phrase);
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&many_threads, &limits),
+            Err(SinkproofError::ThreadLimitExceeded { requested: 100000, max: 8 })
+        );
+        assert!(SinkproofHash::from_string_with_limits(&many_threads, &ParseLimits::default()).is_err());
+        assert!(many_threads.parse::<SinkproofHash>().is_ok());
+
+        let huge_memory = format!("Sinkproof:v1:1:4000000:AQID:{}", phrase);
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&huge_memory, &limits),
+            Err(SinkproofError::MemoryLimitExceeded { requested: 4000000, max: 64 })
+        );
+
+        let per_thread = with_checksum(&format!("Sinkproof:v2:3:1:alg=sha256,mem=2/100/3:AQID:{}", phrase));
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&per_thread, &limits),
+            Err(SinkproofError::MemoryLimitExceeded { requested: 100, max: 64 })
+        );
+
+        let total = format!("Sinkproof:v1:8:64:AQID:{}", phrase);
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&total, &limits.max_total_memory_mb(256)),
+            Err(SinkproofError::TotalMemoryLimitExceeded { requested: 512, max: 256 })
+        );
+        let per_thread_total = with_checksum(&format!("Sinkproof:v2:3:1:alg=sha256,mem=2/100/3:AQID:{}", phrase));
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&per_thread_total, &ParseLimits::new(8, 128).max_total_memory_mb(256)),
+            Err(SinkproofError::TotalMemoryLimitExceeded { requested: 300, max: 256 })
+        );
+
+        // The product is checked, so a huge count cannot wrap below the ceiling
+        let unbounded = ParseLimits::new(usize::MAX, usize::MAX).max_total_memory_mb(usize::MAX - 1);
+        let overflow = format!("Sinkproof:v1:{}:{}:AQID:{}", usize::MAX / 2, 4, phrase);
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&overflow, &unbounded),
+            Err(SinkproofError::TotalMemoryLimitExceeded { requested: usize::MAX, max: usize::MAX - 1 })
+        );
+
+        // The default is far below the build limits, for passes too
+        let defaults = ParseLimits::default();
+        assert!(defaults.max_total_memory_mb < ParseLimits::BUILD.max_total_memory_mb);
+        assert!(defaults.max_passes < ParseLimits::BUILD.max_passes);
+        let default_ok = with_checksum(&format!("Sinkproof:v2:4:1024:alg=sha256,pass=3:AQID:{}", phrase));
+        assert!(SinkproofHash::from_string_with_limits(&default_ok, &defaults).is_ok());
+        let default_total = format!("Sinkproof:v1:16:1024:AQID:{}", phrase);
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&default_total, &defaults),
+            Err(SinkproofError::TotalMemoryLimitExceeded { requested: 16384, max: 8192 })
+        );
+
+        let many_passes = with_checksum(&format!("Sinkproof:v2:1:1:alg=sha256,pass=4294967295:AQID:{}", phrase));
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&many_passes, &ParseLimits::BUILD),
+            Err(SinkproofError::PassLimitExceeded { requested: u32::MAX, max: MAX_PASSES })
+        );
+        let default_passes = with_checksum(&format!("Sinkproof:v2:1:1:alg=sha256,pass=17:AQID:{}", phrase));
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&default_passes, &defaults),
+            Err(SinkproofError::PassLimitExceeded { requested: 17, max: 16 })
+        );
+        let three_passes = with_checksum(&format!("Sinkproof:v2:1:1:alg=sha256,pass=3:AQID:{}", phrase));
+        assert!(SinkproofHash::from_string_with_limits(&three_passes, &limits).is_ok());
+        assert_eq!(
+            SinkproofHash::from_string_with_limits(&three_passes, &limits.max_passes(2)),
+            Err(SinkproofError::PassLimitExceeded { requested: 3, max: 2 })
+        );
     }
 
     #[test]
     fn test_invalid_numbers() {
-        assert!(SinkproofHash::from_string("Sinkproof:v1:abc:50:AQID:BAUG").is_err());
-        assert!(SinkproofHash::from_string("Sinkproof:v1:2:xyz:AQID:BAUG").is_err());
+        assert!(concat!("Sinkproof:v1:abc:50:AQID:", test_phrase!()).parse::<SinkproofHash>().is_err());
+        assert!(concat!("Sinkproof:v1:2:xyz:AQID:", test_phrase!()).parse::<SinkproofHash>().is_err());
     }
 
     #[test]
     fn test_invalid_base64() {
-        assert!(SinkproofHash::from_string("Sinkproof:v1:2:50:!!!:BAUG").is_err());
-        assert!(SinkproofHash::from_string("Sinkproof:v1:2:50:AQID:!!!").is_err());
+        assert!(concat!("Sinkproof:v1:2:50:!!!:", test_phrase!()).parse::<SinkproofHash>().is_err());
+        assert!("Sinkproof:v1:2:50:AQID:!!!".parse::<SinkproofHash>().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip_matches_parse() {
+        let phrase = test_phrase!();
+        let v1 = format!("Sinkproof:v1:4:64:AQID:{}", phrase);
+        let v2 = format!("app|{}", with_checksum(&format!("Sinkproof:v2:2:8:alg=sha256,pass=3,pid=00000000:AQID:{}", phrase)));
+
+        for stored in [v1, v2] {
+            let parsed = stored.parse::<SinkproofHash>().unwrap();
+            let json = serde_json::to_string(&parsed).unwrap();
+            assert!(json.contains("\"salt\":\"AQID\""), "{}", json);
+            assert!(json.contains(phrase), "{}", json);
+
+            let restored: SinkproofHash = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored, parsed);
+        }
+
+        let v1_with_options = r#"{"version":"v1","threads":1,"memory_mb":1,"salt":"AQID","encrypted_phrase":"AQID","options":"alg=sha256"}"#;
+        assert!(serde_json::from_str::<SinkproofHash>(v1_with_options).is_err());
+        let over_limit = format!(r#"{{"version":"v1","threads":1000,"memory_mb":1,"salt":"AQID","encrypted_phrase":"{}"}}"#, phrase);
+        let err = serde_json::from_str::<SinkproofHash>(&over_limit).unwrap_err();
+        assert!(err.to_string().contains("Thread count 1000 exceeds"), "{}", err);
+        let bad_salt = format!(r#"{{"version":"v1","threads":1,"memory_mb":1,"salt":"!!!","encrypted_phrase":"{}"}}"#, phrase);
+        assert!(serde_json::from_str::<SinkproofHash>(&bad_salt).is_err());
     }
 }
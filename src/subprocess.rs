@@ -0,0 +1,134 @@
+//! Hashing in a short-lived child process
+//! 
+//! The worker reads the password from its own stdin, so when stdin is
+//! inherited the plaintext never passes through the parent's memory. A
+//! memory disclosure in the long-running parent then cannot leak it.
+
+use crate::error::{Result, SinkproofError};
+use crate::hasher::hash_password_with;
+use crate::params::HashParams;
+use crate::storage::{encode_options_with, parse_options};
+use std::ffi::OsStr;
+use std::io::{self, BufRead};
+use std::process::{Command, Stdio};
+
+/// First argument that switches a binary into hash-worker mode
+pub const WORKER_FLAG: &str = "--hash-worker";
+
+/// Hash a password read by a worker subprocess from the inherited stdin
+/// 
+/// `worker` is a binary that calls `run_worker` when started with
+/// `WORKER_FLAG`, such as this crate's own `sinkproof` binary. All options
+/// recorded in the hash are passed to the worker. A pepper source or a
+/// custom verification phrase cannot be, and is rejected.
+/// 
+/// # Returns
+/// The stored hash string printed by the worker
+pub fn hash_in_subprocess(worker: impl AsRef<OsStr>, params: &HashParams) -> Result<String> {
+    hash_in_subprocess_with_stdin(worker, params, Stdio::inherit())
+}
+
+/// Like `hash_in_subprocess`, but with an explicit stdin for the worker
+/// 
+/// Useful to hand the worker a pipe or file descriptor the parent never reads.
+pub fn hash_in_subprocess_with_stdin(
+    worker: impl AsRef<OsStr>,
+    params: &HashParams,
+    stdin: Stdio,
+) -> Result<String> {
+    params.validate()?;
+    if params.pepper.is_some() || params.verification_phrase.is_some() {
+        return Err(SinkproofError::SubprocessFailed(
+            "A pepper source or custom verification phrase cannot be passed to a worker".to_string(),
+        ));
+    }
+
+    let output = Command::new(worker)
+        .arg(WORKER_FLAG)
+        .arg(params.threads.to_string())
+        .arg(params.memory_mb.to_string())
+        .arg(encode_options_with(&params.options, None, None))
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| SinkproofError::SubprocessFailed(format!("Failed to start worker: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(SinkproofError::SubprocessFailed(format!(
+            "Worker exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let stored = String::from_utf8(output.stdout)
+        .map_err(|_| SinkproofError::SubprocessFailed("Worker output is not UTF-8".to_string()))?;
+
+    Ok(stored.trim_end().to_string())
+}
+
+/// Worker side: read one password line from stdin and print its hash
+/// 
+/// `args` are the arguments after `WORKER_FLAG`: threads, memory in MB and
+/// the options in the v2 `key=value` list format.
+pub fn run_worker(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let mut next_arg = |name: &str| {
+        args.next()
+            .ok_or_else(|| SinkproofError::SubprocessFailed(format!("Missing worker argument: {}", name)))
+    };
+
+    let threads = next_arg("threads")?
+        .parse()
+        .map_err(|e| SinkproofError::SubprocessFailed(format!("Invalid threads value: {}", e)))?;
+    let memory_mb = next_arg("memory")?
+        .parse()
+        .map_err(|e| SinkproofError::SubprocessFailed(format!("Invalid memory value: {}", e)))?;
+    let options = parse_options(&next_arg("options")?)
+        .map_err(|e| SinkproofError::SubprocessFailed(format!("Invalid options: {}", e)))?
+        .options;
+
+    let mut password = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut password)
+        .map_err(|e| SinkproofError::SubprocessFailed(format!("Failed to read password: {}", e)))?;
+    let password = password.strip_suffix('\n').unwrap_or(&password);
+    let password = password.strip_suffix('\r').unwrap_or(password);
+
+    let hash = hash_password_with(password, &HashParams { options, ..HashParams::new(threads, memory_mb) })?;
+    println!("{}", hash);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_worker_fails() {
+        let result = hash_in_subprocess_with_stdin(
+            "/nonexistent/sinkproof-worker",
+            &HashParams::new(1, 1),
+            Stdio::null(),
+        );
+        assert!(matches!(result, Err(SinkproofError::SubprocessFailed(_))));
+    }
+
+    #[test]
+    fn test_worker_rejects_bad_arguments() {
+        let args = ["1", "abc", "alg=sha256"].map(String::from);
+        assert!(run_worker(args.into_iter()).is_err());
+        let args = ["1", "1", "sha256"].map(String::from);
+        assert!(run_worker(args.into_iter()).is_err());
+        assert!(run_worker(std::iter::empty()).is_err());
+    }
+
+    #[test]
+    fn test_unforwardable_params_are_rejected() {
+        let params = HashParams::new(1, 1).verification_phrase("custom phrase");
+        let result = hash_in_subprocess_with_stdin("/nonexistent/sinkproof-worker", &params, Stdio::null());
+        assert!(matches!(result, Err(SinkproofError::SubprocessFailed(msg)) if msg.contains("cannot be passed")));
+    }
+}
@@ -0,0 +1,191 @@
+//! Helpers for tests and load-test fixtures
+//! 
+//! **Not for production use.** Enabled with the `testing` feature.
+
+use crate::hasher::hash_password_with;
+use crate::params::HashParams;
+use crate::rng::{reset_rng, set_rng};
+use crate::storage::SinkproofHash;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::ops::{Bound, RangeBounds};
+
+/// Seed of the RNG used by `deterministic_hash`
+const DETERMINISTIC_SEED: u64 = 0x5EED;
+
+/// A field of a stored hash in the colon format, for `corrupt_field`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashField {
+    /// The `v1`/`v2` version tag
+    Version,
+    /// The thread count
+    Threads,
+    /// The memory size in MB
+    Memory,
+    /// The v2 options list
+    Options,
+    /// The base64 salt
+    Salt,
+    /// The base64 encrypted verification phrase
+    Phrase,
+    /// The v2 checksum
+    Checksum,
+}
+
+/// Assert that `s` parses as a stored hash with valid parameters
+/// 
+/// # Panics
+/// Panics with the parse or validation error otherwise
+pub fn assert_valid_hash(s: &str) {
+    let hash = s.parse::<SinkproofHash>()
+        .unwrap_or_else(|e| panic!("not a valid Sinkproof hash: {} ({:?})", e, s));
+    if let Err(e) = hash.params().validate() {
+        panic!("Sinkproof hash has invalid parameters: {} ({:?})", e, s);
+    }
+}
+
+/// Hash `password` reproducibly with the cheapest parameters
+/// 
+/// Salt and nonce come from a fixed-seed RNG, so the same password always
+/// gives the same string, which suits snapshot tests. The hash is trivially
+/// brute-forced. Afterwards the current thread is back on `OsRng`
+/// (`reset_rng`), replacing any RNG installed with `set_rng`.
+/// 
+/// # Panics
+/// Panics if hashing fails
+pub fn deterministic_hash(password: &str) -> String {
+    set_rng(StdRng::seed_from_u64(DETERMINISTIC_SEED));
+    let hash = hash_password_with(password, &HashParams::no_memory_hardness_for_tests());
+    reset_rng();
+
+    hash.expect("Failed to generate deterministic hash").to_string()
+}
+
+/// Return `hash` with one character of `field` changed, for negative tests
+/// 
+/// The first character of the field is replaced (the version swaps
+/// between `v1` and `v2`), so the result either fails to parse or fails to
+/// verify (for v2, usually with a checksum mismatch).
+/// 
+/// # Panics
+/// Panics if `hash` is not in the colon format or lacks the field (v1
+/// hashes have no options or checksum)
+pub fn corrupt_field(hash: &str, field: HashField) -> String {
+    let mut parts: Vec<String> = hash.split(':').map(str::to_string).collect();
+    let v2 = parts.get(1).map(String::as_str) == Some("v2");
+    let index = match (field, v2) {
+        (HashField::Version, _) => 1,
+        (HashField::Threads, _) => 2,
+        (HashField::Memory, _) => 3,
+        (HashField::Options, true) => 4,
+        (HashField::Salt, false) => 4,
+        (HashField::Salt, true) => 5,
+        (HashField::Phrase, false) => 5,
+        (HashField::Phrase, true) => 6,
+        (HashField::Checksum, true) => 7,
+        (HashField::Options | HashField::Checksum, false) => panic!("{:?} is only present in v2 hashes", field),
+    };
+
+    let value = parts
+        .get_mut(index)
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| panic!("hash has no {:?} field", field));
+    if field == HashField::Version {
+        // Only the v1/v2 layouts are distinguished, so swap between them
+        *value = if v2 { "v1" } else { "v2" }.to_string();
+    } else {
+        let replacement = if value.starts_with('A') { "B" } else { "A" };
+        value.replace_range(..1, replacement);
+    }
+
+    parts.join(":")
+}
+
+/// Generate `count` valid stored hashes for load-test fixtures
+/// 
+/// Every hash uses one thread of `password-<n>`, with `memory_mb` cycling
+/// through `memory_mb_range`, so the fixture exercises several cost
+/// settings. Each hash has its own random salt, so all results are distinct.
+/// An unbounded end means the range contains only its start.
+/// 
+/// # Panics
+/// Panics if the range is empty, contains 0, or hashing fails
+pub fn generate_test_hashes(count: usize, memory_mb_range: impl RangeBounds<usize>) -> Vec<String> {
+    let start = match memory_mb_range.start_bound() {
+        Bound::Included(&n) => n,
+        Bound::Excluded(&n) => n + 1,
+        Bound::Unbounded => 1,
+    };
+    let end = match memory_mb_range.end_bound() {
+        Bound::Included(&n) => n,
+        Bound::Excluded(&n) => n.checked_sub(1).expect("empty memory range"),
+        Bound::Unbounded => start,
+    };
+    assert!(start > 0 && start <= end, "memory range must be non-empty and non-zero");
+
+    (0..count)
+        .map(|n| {
+            let memory_mb = start + n % (end - start + 1);
+            let params = HashParams::new(1, memory_mb);
+            hash_password_with(&format!("password-{}", n), &params)
+                .expect("Failed to generate test hash")
+                .to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Variant;
+    use crate::verifier::verify_password;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_generate_test_hashes() {
+        let hashes = generate_test_hashes(4, 1..=2);
+        assert_eq!(hashes.len(), 4);
+
+        let distinct: HashSet<&String> = hashes.iter().collect();
+        assert_eq!(distinct.len(), 4);
+
+        let memories: Vec<usize> = hashes
+            .iter()
+            .map(|h| h.parse::<SinkproofHash>().expect("Failed to parse").memory_mb)
+            .collect();
+        assert_eq!(memories, vec![1, 2, 1, 2]);
+    }
+
+    #[test]
+    fn test_assert_valid_hash() {
+        assert_valid_hash(&deterministic_hash("password"));
+        assert!(std::panic::catch_unwind(|| assert_valid_hash("Sinkproof:v1:0:1:AQID:BAUG")).is_err());
+        assert!(std::panic::catch_unwind(|| assert_valid_hash("not a hash")).is_err());
+    }
+
+    #[test]
+    fn test_deterministic_hash() {
+        let hash = deterministic_hash("password");
+        assert_eq!(deterministic_hash("password"), hash);
+        assert_ne!(deterministic_hash("other"), hash);
+        assert!(verify_password("password", &hash).unwrap());
+    }
+
+    #[test]
+    fn test_corrupt_field() {
+        let v1_params = HashParams::no_memory_hardness_for_tests().variant(Variant::Independent);
+        let v1 = hash_password_with("password", &v1_params).unwrap().to_string();
+        for field in [HashField::Version, HashField::Threads, HashField::Memory, HashField::Salt, HashField::Phrase] {
+            let corrupted = corrupt_field(&v1, field);
+            assert_ne!(corrupted, v1);
+            assert!(!matches!(verify_password("password", &corrupted), Ok(true)), "{:?}", field);
+        }
+        assert!(std::panic::catch_unwind(|| corrupt_field(&v1, HashField::Checksum)).is_err());
+
+        let params = HashParams::no_memory_hardness_for_tests().passes(2);
+        let v2 = hash_password_with("password", &params).unwrap().to_string();
+        for field in [HashField::Options, HashField::Salt, HashField::Checksum] {
+            assert!(!matches!(verify_password("password", &corrupt_field(&v2, field)), Ok(true)), "{:?}", field);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use sha2::{Sha256, Digest};
+#[cfg(feature = "hashing")]
+use crate::encryption::encrypt_bytes;
+use crate::encryption::{constant_time_eq, decrypt_bytes};
+use crate::error::Result;
+use crate::hasher::compute_key;
+#[cfg(feature = "hashing")]
+use crate::hasher::hash_with_key;
+#[cfg(feature = "hashing")]
+use crate::params::HashParams;
+#[cfg(feature = "hashing")]
+use crate::storage::SinkproofHash;
+use crate::verifier::parse_for_verification;
+
+/// Domain separation label so the token key never equals the phrase key
+const TOKEN_KEY_LABEL: &[u8] = b"Sinkproof reset token";
+
+/// Hash a password and also emit a reset token derived from the same work
+/// 
+/// The token is the hash's salt encrypted with AES-256-GCM under a key
+/// derived from the memory-hard output, using a fresh random nonce. It is
+/// bound to this particular stored hash: once the password is rehashed with
+/// a new salt, old tokens stop verifying. Store it separately from the hash.
+/// 
+/// # Arguments
+/// * `password` - The password to hash
+/// * `params` - Cost parameters for hashing
+/// 
+/// # Returns
+/// The SinkproofHash and the token bytes
+#[cfg(feature = "hashing")]
+pub fn hash_password_with_token(password: &str, params: &HashParams) -> Result<(SinkproofHash, Vec<u8>)> {
+    let (hash, key) = hash_with_key(password, params)?;
+    let token = encrypt_bytes(&token_key(&key), &hash.salt)?;
+
+    Ok((hash, token))
+}
+
+/// Verify a reset token issued by `hash_password_with_token`
+/// 
+/// # Returns
+/// `Ok(true)` if the password is correct and the token belongs to this hash,
+/// `Ok(false)` otherwise, `Err` if the stored hash cannot be parsed
+pub fn verify_token(password: &str, stored_hash: &str, token: &[u8]) -> Result<bool> {
+    let hash = parse_for_verification(stored_hash)?;
+    let key = compute_key(password, &hash.salt, &hash.params())?;
+
+    match decrypt_bytes(&token_key(&key), token) {
+        Ok(salt) => Ok(constant_time_eq(&salt, &hash.salt)),
+        Err(_) => Ok(false), // Wrong password or tampered token
+    }
+}
+
+/// Derive the token key from the memory-hard key
+fn token_key(key: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(TOKEN_KEY_LABEL);
+    hasher.update(key);
+    hasher.finalize().to_vec()
+}
+
+#[cfg(all(test, feature = "hashing"))]
+mod tests {
+    use super::*;
+    use crate::verifier::verify_password;
+
+    #[test]
+    fn test_token_roundtrip() {
+        let params = HashParams::new(1, 1);
+        let (hash, token) = hash_password_with_token("old_password", &params).expect("Failed to hash");
+        let stored = hash.to_string();
+
+        assert!(verify_password("old_password", &stored).expect("Failed to verify"));
+        assert!(verify_token("old_password", &stored, &token).expect("Failed to verify token"));
+        assert!(!verify_token("wrong_password", &stored, &token).expect("Failed to verify token"));
+    }
+
+    #[test]
+    fn test_token_bound_to_hash() {
+        let params = HashParams::new(1, 1);
+        let (_, token) = hash_password_with_token("password", &params).expect("Failed to hash");
+        let (other, _) = hash_password_with_token("password", &params).expect("Failed to hash");
+
+        // Same password, different salt: the token must not carry over
+        assert!(!verify_token("password", &other.to_string(), &token).expect("Failed to verify token"));
+    }
+
+    #[test]
+    fn test_tampered_token_fails() {
+        let params = HashParams::new(1, 1);
+        let (hash, mut token) = hash_password_with_token("password", &params).expect("Failed to hash");
+        let stored = hash.to_string();
+
+        let last = token.len() - 1;
+        token[last] ^= 0x01;
+        assert!(!verify_token("password", &stored, &token).expect("Failed to verify token"));
+        assert!(!verify_token("password", &stored, &[]).expect("Failed to verify token"));
+    }
+}
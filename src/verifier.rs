@@ -1,8 +1,14 @@
-use crate::storage::SinkproofHash;
-use crate::hasher::{thread_worker, derive_key};
-use crate::encryption::decrypt_phrase;
-use std::sync::Arc;
-use std::thread;
+use crate::storage::{checksum, ParseLimits, SinkproofHash};
+use crate::hasher::compute_key;
+use crate::policy::warn_if_weak;
+use crate::encryption::{is_verification_phrase, matches_phrase, VERIFICATION_PHRASE};
+use crate::error::{Result, SinkproofError};
+use crate::params::HashParams;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
 
 /// Verify a password against a stored Sinkproof hash
 /// 
@@ -12,102 +18,352 @@ use std::thread;
 /// 
 /// # Returns
 /// `Ok(true)` if password matches, `Ok(false)` if it doesn't, `Err` on error
-pub fn verify_password(password: &str, stored_hash: &str) -> Result<bool, String> {
+/// 
+/// A successful match against a hash below the weak-hash threshold logs a
+/// one-time warning; see `set_weak_hash_threshold`.
+/// 
+/// The stored hash is parsed within `ParseLimits::DEFAULT`, so a planted
+/// row with huge cost parameters fails before any memory is allocated;
+/// use `verify_password_with_limits` for hashes above those limits.
+pub fn verify_password(password: &str, stored_hash: &str) -> Result<bool> {
+    verify_password_with_limits(password, stored_hash, &ParseLimits::DEFAULT)
+}
+
+/// `verify_password` with caller-chosen limits on the stored parameters
+/// 
+/// # Returns
+/// As `verify_password`, or `ThreadLimitExceeded`, `MemoryLimitExceeded`,
+/// `TotalMemoryLimitExceeded` or `PassLimitExceeded` for a hash over
+/// `limits` (see `SinkproofHash::from_string_with_limits`)
+pub fn verify_password_with_limits(password: &str, stored_hash: &str, limits: &ParseLimits) -> Result<bool> {
     // Parse the stored hash
-    let hash = SinkproofHash::from_string(stored_hash)?;
+    let hash = SinkproofHash::from_string_with_limits(stored_hash, limits)?;
 
-    // Re-hash the password with the same parameters
-    let memory_size = hash.memory_mb * 1024 * 1024;
-    let mut handles = vec![];
-    let password = Arc::new(password.to_string());
-    let salt = Arc::new(hash.salt.clone());
+    let valid = verify_hash(password, &hash)?;
+    if valid {
+        warn_if_weak(&hash);
+    }
 
-    // Spawn worker threads with same parameters
-    for thread_index in 0..hash.threads {
-        let password = Arc::clone(&password);
-        let salt = Arc::clone(&salt);
+    Ok(valid)
+}
 
-        let handle = thread::spawn(move || {
-            thread_worker(&password, &salt, thread_index, memory_size)
-        });
+/// Verify a password against a hash made with a custom verification phrase
+/// 
+/// `phrase` must be the one passed to `HashParams::verification_phrase`
+/// when hashing; a different phrase returns `PhraseMismatch`, as
+/// `verify_password` does for such hashes. Hashes made with the built-in
+/// phrase verify only with `verify_password` (v1 hashes, which record no
+/// phrase id, return `Ok(false)` here).
+/// 
+/// # Returns
+/// `Ok(true)` if the password matches, `Ok(false)` if it doesn't, `Err` on
+/// error
+pub fn verify_password_with_phrase(password: &str, stored_hash: &str, phrase: &str) -> Result<bool> {
+    let hash = parse_for_verification(stored_hash)?;
+    let params = hash.params().verification_phrase(phrase);
 
-        handles.push(handle);
+    let valid = verified_key(password, &hash, &params)?.is_some();
+    if valid {
+        warn_if_weak(&hash);
     }
+    Ok(valid)
+}
 
-    // Collect results
-    let mut thread_outputs = Vec::new();
-    for handle in handles {
-        match handle.join() {
-            Ok(output) => thread_outputs.push(output),
-            Err(_) => return Err("Thread panicked during verification".to_string()),
-        }
-    }
+/// Verify a password and derive a session key from the same work
+/// 
+/// Login flows that also need a key (e.g. for session encryption) would
+/// otherwise run the memory-hard derivation twice. The session key is
+/// HKDF-SHA256 over the derived key, with the hash's salt as HKDF salt and
+/// `info` for domain separation; it never equals the key that encrypts the
+/// verification phrase. Different `info` values yield independent keys.
+/// 
+/// # Returns
+/// `Ok(Some(key))` with a 32-byte key if the password matches, `Ok(None)`
+/// if it doesn't, `Err` on error
+pub fn verify_and_derive(password: &str, stored_hash: &str, info: &[u8]) -> Result<Option<Vec<u8>>> {
+    let hash = parse_for_verification(stored_hash)?;
+
+    let Some(key) = verified_key(password, &hash, &hash.params())? else {
+        return Ok(None);
+    };
+    warn_if_weak(&hash);
 
-    // Derive key from outputs
-    let key = derive_key(&thread_outputs);
+    let mut session_key = vec![0u8; 32];
+    Hkdf::<Sha256>::new(Some(&hash.salt), &key)
+        .expand(info, &mut session_key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    Ok(Some(session_key))
+}
+
+/// Parse a stored hash for verification, within `ParseLimits::DEFAULT`
+/// 
+/// Every verify entry point goes through this (or an explicit limit), so
+/// planted cost parameters are refused before the workers allocate.
+pub(crate) fn parse_for_verification(stored_hash: &str) -> Result<SinkproofHash> {
+    SinkproofHash::from_string_with_limits(stored_hash, &ParseLimits::DEFAULT)
+}
+
+/// Verify a password against an already parsed hash
+pub(crate) fn verify_hash(password: &str, hash: &SinkproofHash) -> Result<bool> {
+    Ok(verified_key(password, hash, &hash.params())?.is_some())
+}
+
+/// The derived key if `password` matches the hash, `None` otherwise
+/// 
+/// `params` are the hash's own parameters, plus anything that is not
+/// stored in it such as a pepper source.
+pub(crate) fn verified_key(
+    password: &str,
+    hash: &SinkproofHash,
+    params: &HashParams,
+) -> Result<Option<Zeroizing<Vec<u8>>>> {
+    // A different phrase would make every password look wrong
+    hash.check_phrase_id(params.phrase())?;
+
+    // Re-hash the password with the same parameters
+    let key = compute_key(password, &hash.salt, params)?;
 
     // Try to decrypt the stored encrypted phrase
-    match decrypt_phrase(&key, &hash.encrypted_phrase) {
-        Ok(decrypted) => {
-            // If decryption succeeds and matches expected phrase, password is correct
-            Ok(decrypted == "No vendo cigarros sueltos")
-        }
-        Err(_) => {
-            // If decryption fails, password is incorrect
-            Ok(false)
-        }
+    match hash.decrypt_phrase(&key, params.phrase()) {
+        // If decryption succeeds and matches expected phrase, password is correct
+        Ok(decrypted) if matches_phrase(&decrypted, params.phrase()) => Ok(Some(key)),
+        // If decryption fails, password is incorrect
+        _ => Ok(None),
     }
 }
 
-/// Alternative verification method: Re-encrypt and compare
-/// This is more robust as it handles the random nonce in encryption
-pub fn verify_password_robust(password: &str, stored_hash: &str) -> Result<bool, String> {
-    // Parse the stored hash
-    let hash = SinkproofHash::from_string(stored_hash)?;
+/// Detailed outcome of a verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyResult {
+    /// The password is correct
+    Match,
+    /// The password is wrong
+    NoMatch,
+    /// The stored hash is corrupt; the password could not be checked
+    Malformed(String),
+}
 
-    // Re-hash the password with the same parameters
-    let memory_size = hash.memory_mb * 1024 * 1024;
-    let mut handles = vec![];
-    let password = Arc::new(password.to_string());
-    let salt = Arc::new(hash.salt.clone());
+/// Verify a password, distinguishing a wrong password from a corrupt hash
+/// 
+/// `verify_password` reports both as `Ok(false)` or a parse error; this
+/// returns `Malformed` for stored hashes that cannot be parsed, whose
+/// encrypted phrase is too short to hold the nonce, tag and phrase, or that decrypt
+/// to something other than the verification phrase. AES-GCM cannot tell a
+/// wrong key from a corrupted ciphertext, so a failed decryption is
+/// reported as `NoMatch`.
+/// 
+/// # Returns
+/// `Err` only for failures unrelated to the stored hash's integrity, such
+/// as a panicked worker, an algorithm unavailable in this build, or a hash
+/// created with a different verification phrase (`PhraseMismatch`). An
+/// empty stored hash means no password was set and returns `EmptyHash`.
+pub fn verify_detailed(password: &str, stored_hash: &str) -> Result<VerifyResult> {
+    let hash = match parse_for_verification(stored_hash) {
+        Ok(hash) => hash,
+        Err(SinkproofError::InvalidFormat(reason) | SinkproofError::Base64Decode(reason)) => {
+            return Ok(VerifyResult::Malformed(reason))
+        }
+        Err(e @ SinkproofError::ChecksumMismatch) => return Ok(VerifyResult::Malformed(e.to_string())),
+        Err(e) => return Err(e),
+    };
 
-    // Spawn worker threads with same parameters
-    for thread_index in 0..hash.threads {
-        let password = Arc::clone(&password);
-        let salt = Arc::clone(&salt);
+    // Catch inconsistent parameters before running the expensive work;
+    // truncated phrases are already rejected by the parser
+    if let Err(e) = hash.params().check_thread_memory() {
+        return Ok(VerifyResult::Malformed(e.to_string()));
+    }
 
-        let handle = thread::spawn(move || {
-            thread_worker(&password, &salt, thread_index, memory_size)
-        });
+    hash.check_phrase_id(VERIFICATION_PHRASE)?;
+    let key = compute_key(password, &hash.salt, &hash.params())?;
 
-        handles.push(handle);
+    match hash.decrypt_phrase(&key, VERIFICATION_PHRASE) {
+        Ok(phrase) if is_verification_phrase(&phrase) => Ok(VerifyResult::Match),
+        Ok(_) => Ok(VerifyResult::Malformed(
+            "Encrypted phrase decrypts to an unexpected value".to_string(),
+        )),
+        Err(_) => Ok(VerifyResult::NoMatch),
     }
+}
 
-    // Collect results
-    let mut thread_outputs = Vec::new();
-    for handle in handles {
-        match handle.join() {
-            Ok(output) => thread_outputs.push(output),
-            Err(_) => return Err("Thread panicked during verification".to_string()),
-        }
+/// Verify a password and report how long verification took
+/// 
+/// Monitoring can alert when verification becomes too fast, a sign that
+/// hardware has outpaced the stored cost parameters.
+/// 
+/// # Returns
+/// The verification result and the elapsed time, including parsing
+pub fn verify_and_time(password: &str, stored_hash: &str) -> Result<(bool, Duration)> {
+    let start = Instant::now();
+    let is_valid = verify_password(password, stored_hash)?;
+    Ok((is_valid, start.elapsed()))
+}
+
+/// Verify a password, taking at least `min` in total
+/// 
+/// Verification time depends on the stored parameters and on cache state,
+/// so an observer timing logins can tell accounts apart even though the
+/// comparison itself is constant-time. This sleeps after verifying until
+/// `min` has elapsed since the call, for matches, mismatches and malformed
+/// hashes alike. It trades latency for privacy: pick `min` above the
+/// slowest verification in the database, or slower hashes still stand
+/// out. A verification that already took `min` or longer is returned
+/// immediately. Sleeping may overshoot `min` by the OS timer granularity.
+pub fn verify_password_min_time(password: &str, stored_hash: &str, min: Duration) -> Result<bool> {
+    let start = Instant::now();
+    let result = verify_password(password, stored_hash);
+    std::thread::sleep(min.saturating_sub(start.elapsed()));
+    result
+}
+
+/// Most thread counts `verify_try_thread_range` will try
+pub const MAX_THREAD_RECOVERY_ATTEMPTS: usize = 16;
+
+/// **Last-resort recovery tool:** find the thread count a hash was made with
+/// 
+/// The key depends on the thread count, so a hash whose thread field was
+/// corrupted never verifies normally. This retries verification with every
+/// count in `threads` (at most `MAX_THREAD_RECOVERY_ATTEMPTS`, zero
+/// skipped) and returns the first that matches, so the stored hash can be
+/// repaired. A v2 checksum that no longer matches is ignored for this
+/// purpose. Each attempt costs a full verification, and a hit only proves
+/// the password matches under that count; never use this for routine
+/// logins, which must go through `verify_password`.
+/// 
+/// # Returns
+/// `Ok(Some(threads))` for the matching count, `Ok(None)` if none matches
+pub fn verify_try_thread_range(
+    password: &str,
+    stored_hash: &str,
+    threads: RangeInclusive<usize>,
+) -> Result<Option<usize>> {
+    let attempts = threads.end().saturating_sub(*threads.start()).saturating_add(1);
+    if threads.is_empty() || attempts > MAX_THREAD_RECOVERY_ATTEMPTS {
+        return Err(SinkproofError::InvalidConfig(format!(
+            "Thread range must hold 1 to {} counts",
+            MAX_THREAD_RECOVERY_ATTEMPTS
+        )));
     }
 
-    // Derive key from outputs
-    let key = derive_key(&thread_outputs);
+    let mut hash = match parse_for_verification(stored_hash) {
+        Err(SinkproofError::ChecksumMismatch) => {
+            // The corruption being repaired also broke the checksum; parse
+            // the fields under a recomputed one
+            let (body, _) = stored_hash.trim().rsplit_once(':').ok_or(SinkproofError::ChecksumMismatch)?;
+            parse_for_verification(&format!("{}:{}", body, checksum(body)))?
+        }
+        parsed => parsed?,
+    };
 
-    // Try to decrypt the stored phrase with the derived key
-    // If the password is correct, decryption will succeed
-    match decrypt_phrase(&key, &hash.encrypted_phrase) {
-        Ok(phrase) => Ok(phrase == "No vendo cigarros sueltos"),
-        Err(_) => Ok(false), // Wrong password leads to wrong key, decryption fails
+    for count in threads.filter(|&count| count > 0) {
+        hash.threads = count;
+        if hash.params().check_thread_memory().is_err() {
+            continue;
+        }
+        if verified_key(password, &hash, &hash.params())?.is_some() {
+            return Ok(Some(count));
+        }
     }
+
+    Ok(None)
+}
+
+/// Former alternative to `verify_password`
+/// 
+/// Despite its name it never re-encrypted anything: it decrypted the
+/// phrase exactly like `verify_password`, only without the phrase-id check
+/// and the weak-hash warning. It now simply calls `verify_password`.
+#[deprecated(note = "identical to `verify_password`; use that instead")]
+pub fn verify_password_robust(password: &str, stored_hash: &str) -> Result<bool> {
+    verify_password(password, stored_hash)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::hasher::hash_password;
+    #[cfg(feature = "hashing")]
+    use crate::hasher::{hash_password, hash_password_with};
+    #[cfg(feature = "hashing")]
+    use crate::params::{HashParams, NormalizationForm};
+    #[cfg(feature = "hashing")]
+    use crate::storage::test_phrase;
+    #[cfg(feature = "hashing")]
+    use crate::encryption::CONSTANT_TIME_COMPARISONS;
 
+    #[cfg(feature = "hashing")]
+    fn constant_time_comparisons() -> usize {
+        CONSTANT_TIME_COMPARISONS.with(|count| count.get())
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    #[allow(deprecated)]
+    fn test_verification_compares_in_constant_time() {
+        let hash = hash_password_with("password", &HashParams::new(1, 1)).unwrap().to_string();
+
+        let before = constant_time_comparisons();
+        assert!(verify_password("password", &hash).unwrap());
+        assert_eq!(constant_time_comparisons(), before + 1);
+
+        assert_eq!(verify_detailed("password", &hash).unwrap(), VerifyResult::Match);
+        assert_eq!(constant_time_comparisons(), before + 2);
+
+        assert!(verify_password_robust("password", &hash).unwrap());
+        assert_eq!(constant_time_comparisons(), before + 3);
+
+        // A wrong key fails AEAD authentication (itself constant time), so
+        // there is nothing left to compare
+        assert!(!verify_password("wrong", &hash).unwrap());
+        assert_eq!(constant_time_comparisons(), before + 3);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_over_limit_rows_are_rejected_before_allocating() {
+        use crate::policy::{verify_with_policy, HashPolicy};
+
+        // Within the build's limits, but verifying would allocate 8 × 2 GB
+        let planted = concat!("Sinkproof:v1:8:2048:AQID:", test_phrase!());
+        let over_total = SinkproofError::TotalMemoryLimitExceeded { requested: 16384, max: 8192 };
+        assert_eq!(verify_password("password", planted), Err(over_total.clone()));
+        assert_eq!(verify_detailed("password", planted), Err(over_total.clone()));
+        assert_eq!(verify_and_derive("password", planted, b"session"), Err(over_total.clone()));
+        assert_eq!(verify_password_with_phrase("password", planted, "phrase"), Err(over_total.clone()));
+        assert_eq!(verify_with_policy("password", planted, &HashPolicy::RECOMMENDED), Err(over_total));
+
+        let many_threads = planted.replace("v1:8:2048", "v1:128:1");
+        assert_eq!(
+            verify_password("password", &many_threads),
+            Err(SinkproofError::ThreadLimitExceeded { requested: 128, max: 64 })
+        );
+
+        // Callers can tighten or relax the limits
+        let stored = hash_password_with("password", &HashParams::new(2, 1)).unwrap().to_string();
+        assert_eq!(
+            verify_password_with_limits("password", &stored, &ParseLimits::new(1, 1)),
+            Err(SinkproofError::ThreadLimitExceeded { requested: 2, max: 1 })
+        );
+        assert_eq!(verify_password_with_limits("password", &stored, &ParseLimits::BUILD), Ok(true));
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_verify_and_derive() {
+        let stored = hash_password_with("password", &HashParams::new(1, 1)).unwrap();
+        let stored_str = stored.to_string();
+
+        let key = verify_and_derive("password", &stored_str, b"session").unwrap().expect("Password should match");
+        assert_eq!(key.len(), 32);
+        assert_eq!(verify_and_derive("password", &stored_str, b"session").unwrap(), Some(key.clone()));
+
+        // Separate domains and the phrase key are all distinct
+        let other = verify_and_derive("password", &stored_str, b"other").unwrap().unwrap();
+        assert_ne!(key, other);
+        assert_ne!(key, *compute_key("password", &stored.salt, &stored.params()).unwrap());
+
+        assert_eq!(verify_and_derive("wrong", &stored_str, b"session").unwrap(), None);
+    }
+
+    #[cfg(feature = "hashing")]
     #[test]
     fn test_verify_correct_password() {
         let password = "mi_contraseña_segura";
@@ -118,6 +374,7 @@ mod tests {
         assert!(result);
     }
 
+    #[cfg(feature = "hashing")]
     #[test]
     fn test_verify_incorrect_password() {
         let password = "mi_contraseña_segura";
@@ -128,7 +385,51 @@ mod tests {
         assert!(!result);
     }
 
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_verify_try_thread_range() {
+        // A v1 hash, whose lack of checksum lets the corruption parse
+        let params = HashParams::new(3, 1).variant(crate::params::Variant::Independent);
+        let stored = hash_password_with("password", &params).unwrap().to_string();
+        let corrupted = stored.replacen(":3:1:", ":5:1:", 1);
+        assert!(!verify_password("password", &corrupted).unwrap());
+
+        assert_eq!(verify_try_thread_range("password", &corrupted, 2..=4).unwrap(), Some(3));
+        assert_eq!(verify_try_thread_range("password", &corrupted, 0..=3).unwrap(), Some(3));
+        assert_eq!(verify_try_thread_range("password", &corrupted, 4..=6).unwrap(), None);
+        assert_eq!(verify_try_thread_range("wrong", &corrupted, 2..=4).unwrap(), None);
+
+        // A corrupted v2 hash no longer matches its checksum
+        let stored = hash_password_with("password", &HashParams::new(2, 1).passes(2)).unwrap().to_string();
+        let corrupted = stored.replacen(":2:1:", ":1:1:", 1);
+        assert_eq!(corrupted.parse::<SinkproofHash>().unwrap_err(), SinkproofError::ChecksumMismatch);
+        assert_eq!(verify_try_thread_range("password", &corrupted, 1..=2).unwrap(), Some(2));
+
+        assert!(verify_try_thread_range("password", &corrupted, 1..=100).is_err());
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 3..=2;
+        assert!(verify_try_thread_range("password", &corrupted, empty).is_err());
+    }
+
+    #[cfg(feature = "hashing")]
     #[test]
+    #[allow(deprecated)]
+    fn test_verify_robust_matches_verify_password() {
+        let stored = hash_password_with("password", &HashParams::new(1, 1)).unwrap().to_string();
+        for password in ["password", "wrong"] {
+            assert_eq!(verify_password_robust(password, &stored), verify_password(password, &stored));
+        }
+        assert_eq!(verify_password_robust("password", ""), verify_password("password", ""));
+
+        // Hashes made with another verification phrase are now reported, not `false`
+        let mut foreign = hash_password_with("password", &HashParams::new(1, 1).passes(2)).unwrap();
+        foreign.phrase_id = Some("00000000".to_string());
+        assert_eq!(verify_password_robust("password", &foreign.to_string()), Err(SinkproofError::PhraseMismatch));
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    #[allow(deprecated)]
     fn test_verify_robust_correct_password() {
         let password = "test123";
         let hash = hash_password(password, 2, 5).expect("Failed to hash");
@@ -138,7 +439,9 @@ mod tests {
         assert!(result);
     }
 
+    #[cfg(feature = "hashing")]
     #[test]
+    #[allow(deprecated)]
     fn test_verify_robust_incorrect_password() {
         let password = "test123";
         let hash = hash_password(password, 2, 5).expect("Failed to hash");
@@ -148,6 +451,148 @@ mod tests {
         assert!(!result);
     }
 
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_verify_and_time() {
+        let stored = hash_password("password", 1, 1).expect("Failed to hash").to_string();
+
+        let (is_valid, elapsed) = verify_and_time("password", &stored).expect("Verification failed");
+        assert!(is_valid);
+        assert!(elapsed > Duration::ZERO);
+
+        let (is_valid, _) = verify_and_time("wrong", &stored).expect("Verification failed");
+        assert!(!is_valid);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_custom_verification_phrase() {
+        let params = HashParams::new(1, 1).verification_phrase("Deployment A canary");
+        let stored = hash_password_with("password", &params).expect("Failed to hash").to_string();
+
+        assert!(stored.starts_with("Sinkproof:v2:"));
+        assert!(stored.contains(&format!("pid={}", crate::encryption::phrase_id("Deployment A canary"))));
+        assert!(verify_password_with_phrase("password", &stored, "Deployment A canary").expect("Failed to verify"));
+        assert!(!verify_password_with_phrase("wrong", &stored, "Deployment A canary").expect("Failed to verify"));
+
+        // Another deployment's phrase, or the built-in one, is reported
+        assert_eq!(verify_password("password", &stored), Err(SinkproofError::PhraseMismatch));
+        assert_eq!(
+            verify_password_with_phrase("password", &stored, "Deployment B canary"),
+            Err(SinkproofError::PhraseMismatch)
+        );
+
+        let builtin = hash_password_with("password", &HashParams::new(1, 1)).unwrap().to_string();
+        assert_eq!(
+            verify_password_with_phrase("password", &builtin, "Deployment A canary"),
+            Err(SinkproofError::PhraseMismatch)
+        );
+        let v1_params = HashParams::new(1, 1).variant(crate::params::Variant::Independent);
+        let v1 = hash_password_with("password", &v1_params).unwrap().to_string();
+        assert!(!verify_password_with_phrase("password", &v1, "Deployment A canary").unwrap());
+
+        assert!(matches!(
+            HashParams::new(1, 1).verification_phrase("").validate(),
+            Err(SinkproofError::InvalidConfig(_))
+        ));
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_verify_password_min_time() {
+        let stored = hash_password("password", 1, 1).expect("Failed to hash").to_string();
+        let min = Duration::from_millis(300);
+
+        // Fast outcomes, including a malformed hash, are padded to `min`
+        let start = Instant::now();
+        assert!(verify_password_min_time("password", &stored, min).expect("Verification failed"));
+        assert!(start.elapsed() >= min);
+        let start = Instant::now();
+        assert!(verify_password_min_time("password", "invalid", min).is_err());
+        assert!(start.elapsed() >= min);
+
+        // A verification slower than `min` is not extended
+        let (_, baseline) = verify_and_time("password", &stored).expect("Verification failed");
+        let start = Instant::now();
+        assert!(!verify_password_min_time("wrong", &stored, baseline / 4).expect("Verification failed"));
+        let elapsed = start.elapsed();
+        assert!(elapsed < baseline * 4 + Duration::from_millis(500), "{:?} vs {:?}", elapsed, baseline);
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_verify_detailed_outcomes() {
+        let stored = hash_password("password", 1, 1).expect("Failed to hash").to_string();
+
+        assert_eq!(verify_detailed("password", &stored).unwrap(), VerifyResult::Match);
+        assert_eq!(verify_detailed("wrong", &stored).unwrap(), VerifyResult::NoMatch);
+        assert!(matches!(verify_detailed("password", "invalid").unwrap(), VerifyResult::Malformed(_)));
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_binary_phrase_is_not_a_utf8_error() {
+        use crate::encryption::encrypt_bytes;
+
+        // v1, so the phrase is encrypted without associated data
+        let params = HashParams::new(1, 1).variant(crate::params::Variant::Independent);
+        let mut hash = hash_password_with("password", &params).expect("Failed to hash");
+        let key = compute_key("password", &hash.salt, &hash.params()).expect("Failed to hash");
+        let binary = vec![0xff; crate::encryption::VERIFICATION_PHRASE.len()];
+        hash.encrypted_phrase = encrypt_bytes(&key, &binary).expect("Encryption failed");
+        let stored = hash.to_string();
+
+        assert_eq!(verify_password("password", &stored), Ok(false));
+        assert!(matches!(verify_detailed("password", &stored), Ok(VerifyResult::Malformed(_))));
+    }
+
+    #[test]
+    fn test_verify_detailed_short_phrase_is_malformed() {
+        let result = verify_detailed("password", "Sinkproof:v1:1:1:AQID:BAUG").unwrap();
+        assert_eq!(
+            result,
+            VerifyResult::Malformed("Encrypted phrase too short: expected at least 53 bytes, got 3 (truncated?)".to_string())
+        );
+    }
+
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_phrase_mismatch_is_reported() {
+        let params = HashParams::new(1, 1).normalize_unicode(NormalizationForm::Nfc);
+        let mut hash = hash_password_with("password", &params).expect("Failed to hash");
+        assert!(hash.phrase_id.is_some());
+        assert!(verify_password("password", &hash.to_string()).unwrap());
+
+        // As if hashed by a build with another phrase
+        hash.phrase_id = Some("00000000".to_string());
+        let stored = hash.to_string();
+        assert_eq!(verify_password("password", &stored), Err(SinkproofError::PhraseMismatch));
+        assert_eq!(verify_detailed("password", &stored), Err(SinkproofError::PhraseMismatch));
+    }
+
+    #[cfg(all(feature = "chacha20", not(feature = "fips")))]
+    #[cfg(feature = "hashing")]
+    #[test]
+    fn test_chacha_hash_verifies_and_binds_cipher() {
+        use crate::encryption::Cipher;
+
+        let params = HashParams::new(1, 1).cipher(Cipher::ChaCha20Poly1305);
+        let mut hash = hash_password_with("password", &params).expect("Failed to hash");
+        assert!(verify_password("password", &hash.to_string()).unwrap());
+        assert!(!verify_password("wrong", &hash.to_string()).unwrap());
+
+        // Tampering with the recorded cipher makes verification fail
+        hash.options.cipher = Cipher::Aes256Gcm;
+        assert!(!verify_password("password", &hash.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_verify_empty_hash() {
+        assert_eq!(verify_password("", ""), Err(SinkproofError::EmptyHash));
+        assert_eq!(verify_password("password", "   "), Err(SinkproofError::EmptyHash));
+        assert_eq!(verify_detailed("password", ""), Err(SinkproofError::EmptyHash));
+    }
+
     #[test]
     fn test_verify_invalid_format() {
         let result = verify_password("password", "invalid_format");
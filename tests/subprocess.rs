@@ -0,0 +1,51 @@
+use sinkproof::subprocess::{hash_in_subprocess_with_stdin, WORKER_FLAG};
+use sinkproof::{verify_password, HashParams, SinkproofHash, Variant};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const WORKER: &str = env!("CARGO_BIN_EXE_sinkproof");
+
+#[test]
+fn test_hash_in_subprocess_verifies() {
+    let (reader, mut writer) = std::io::pipe().expect("Failed to create pipe");
+    writer.write_all(b"password\n").expect("Failed to write password");
+    drop(writer);
+
+    let stored = hash_in_subprocess_with_stdin(WORKER, &HashParams::new(1, 1), reader.into())
+        .expect("Subprocess hashing failed");
+
+    assert!(stored.starts_with("Sinkproof:v2:1:1:"));
+    assert!(verify_password("password", &stored).expect("Failed to verify"));
+    assert!(!verify_password("wrong", &stored).expect("Failed to verify"));
+}
+
+#[test]
+fn test_worker_hash_records_caller_params() {
+    let (reader, mut writer) = std::io::pipe().expect("Failed to create pipe");
+    writer.write_all(b"password\n").expect("Failed to write password");
+    drop(writer);
+
+    let params = HashParams::new(2, 1)
+        .variant(Variant::Dependent)
+        .passes(2)
+        .per_thread_memory(vec![1, 2])
+        .label("subprocess");
+    let stored = hash_in_subprocess_with_stdin(WORKER, &params, reader.into())
+        .expect("Subprocess hashing failed");
+
+    let hash: SinkproofHash = stored.parse().expect("Failed to parse worker output");
+    assert_eq!(hash.params(), params);
+    assert!(verify_password("password", &stored).expect("Failed to verify"));
+}
+
+#[test]
+fn test_worker_reports_failure() {
+    let output = Command::new(WORKER)
+        .args([WORKER_FLAG, "1", "not-a-number", "alg=sha256"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("Failed to run worker");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid memory value"));
+}
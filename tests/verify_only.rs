@@ -0,0 +1,25 @@
+//! Verification against fixed hashes, so it also runs in verify-only
+//! builds: `cargo test --no-default-features --features verify-only --test verify_only`
+
+use sinkproof::{verify_detailed, verify_password, SinkproofHash, VerifyResult};
+
+const V1_HASH: &str = "Sinkproof:v1:1:1:T98ALi5/VDcMk83JFnF/w5JYSZx0ZuciNy+H8VRFnuo=:fVDTad1H6Xoh478oaJ6HHWlevGpjxiLqJAHlqYGAmjjWZord+H/lrnOBuL/AzWssIZh50HI=";
+
+const V2_HASH: &str = "Sinkproof:v2:2:1:alg=sha256,var=id,pass=2,pid=b894e032,ver=1.0.0:v4Y6A9qLB/hbmXfR09z+ZwuZGSywrQsYoZLThMxevtU=:o4zareuezJq7w2YcLL1K4HKwmgXtxJfew3DYgxNZt8DotetZi5IOACVzlCIzpjSRJVFKil4=:55fff49d";
+
+#[test]
+fn test_verifies_stored_hashes() {
+    for stored in [V1_HASH, V2_HASH] {
+        assert!(verify_password("correct horse", stored).expect("Failed to verify"));
+        assert!(!verify_password("wrong horse", stored).expect("Failed to verify"));
+    }
+}
+
+#[test]
+fn test_parses_stored_hashes() {
+    let parsed = V2_HASH.parse::<SinkproofHash>().expect("Failed to parse");
+    assert_eq!(parsed.threads, 2);
+    assert_eq!(parsed.params().options.passes, 2);
+
+    assert!(matches!(verify_detailed("correct horse", V1_HASH), Ok(VerifyResult::Match)));
+}